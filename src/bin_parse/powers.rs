@@ -241,9 +241,9 @@ where
     );
 
     // next 3 added i26p5
-    let _: f32 = bin_read(reader)?; // default 1.0?
-    let _: f32 = bin_read(reader)?; // default 999999.0?
-    let _: f32 = bin_read(reader)?; // default 1.0?
+    for value in power.pf_unknown.iter_mut() {
+        *value = bin_read(reader)?;
+    }
 
     // changed i26p5: pp_vars appears to be an array of character attributes now
     pwr_attrib_arr!(pp_vars);
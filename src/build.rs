@@ -0,0 +1,784 @@
+use crate::structs::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+/// The same set bonus only counts this many times (the "rule of five").
+const SET_BONUS_LIMIT: usize = 5;
+
+/// Enhancement diminishing returns schedules. Each entry is the strength at which the
+/// next, smaller multiplier kicks in. Below the first threshold, enhancements apply in full.
+const ED_SCHEDULE_A: [f32; 3] = [0.7, 0.9, 1.0];
+const ED_SCHEDULE_B: [f32; 3] = [0.4, 0.5, 0.6];
+const ED_SCHEDULE_C: [f32; 3] = [0.8, 1.0, 1.2];
+const ED_SCHEDULE_D: [f32; 3] = [1.2, 1.5, 1.8];
+/// Multipliers applied above each of the ED thresholds.
+const ED_MULTIPLIERS: [f32; 3] = [0.9, 0.7, 0.15];
+
+/// A power chosen in a build.
+#[derive(Debug, Deserialize)]
+pub struct BuildPower {
+    /// The full name of the power (category, power set, and power).
+    pub name: NameKey,
+    /// If `true`, the power is considered to be running. Auto powers are always running.
+    #[serde(default)]
+    pub active: bool,
+    /// Full names of the enhancements (boost powers) slotted in the power.
+    #[serde(default)]
+    pub enhancements: Vec<NameKey>,
+}
+
+/// Describes a character build to evaluate.
+#[derive(Debug, Deserialize)]
+pub struct Build {
+    /// Internal name (e.g. `Class_Tanker`) or display name of the archetype.
+    pub archetype: NameKey,
+    /// Character level, from 1 to 50.
+    pub level: i32,
+    /// The powers chosen in the build.
+    pub powers: Vec<BuildPower>,
+}
+
+impl Build {
+    /// Parses a .toml file to create a `Build`.
+    ///
+    /// # Arguments:
+    ///
+    /// * `path` - The path to the .toml file to parse.
+    ///
+    /// # Returns:
+    ///
+    /// If successful, a `Build`. Otherwise, a `std::io::Error`.
+    pub fn load(path: &Path) -> Result<Build> {
+        let mut file = File::open(path)?;
+        let mut buf = String::new();
+        let _ = file.read_to_string(&mut buf)?;
+        let build: Build = toml::from_str(&buf).map_err(|e| Error::new(ErrorKind::Other, e))?;
+        if build.level < 1 || build.level > 50 {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "level must be between 1 and 50 (inclusive)",
+            ));
+        }
+        Ok(build)
+    }
+}
+
+/// A single total in a `BuildTotals` sheet.
+#[derive(Debug)]
+pub struct BuildTotal {
+    /// What the total is for (e.g. `Smashing` or `Melee`).
+    pub name: String,
+    /// The total after caps are applied.
+    pub value: f32,
+    /// The total before caps are applied.
+    pub uncapped: f32,
+}
+
+impl BuildTotal {
+    fn new(name: String, uncapped: f32, cap: Option<f32>) -> Self {
+        BuildTotal {
+            name,
+            value: match cap {
+                Some(cap) => uncapped.min(cap),
+                None => uncapped,
+            },
+            uncapped,
+        }
+    }
+}
+
+/// The totals for a build. Bonuses are expressed as fractions (0.45 == 45%) unless otherwise noted.
+#[derive(Debug)]
+pub struct BuildTotals {
+    /// Defense by type and position.
+    pub defense: Vec<BuildTotal>,
+    /// Damage resistance by type.
+    pub resistance: Vec<BuildTotal>,
+    /// Damage bonus by type.
+    pub damage_bonus: Vec<BuildTotal>,
+    /// Global recharge bonus.
+    pub recharge: BuildTotal,
+    /// Global accuracy bonus.
+    pub accuracy: BuildTotal,
+    /// To-hit bonus.
+    pub to_hit: BuildTotal,
+    /// Regeneration, including the base rate (1.0 == 100%). Strength buffs multiply the rate.
+    pub regeneration: BuildTotal,
+    /// Recovery, including the base rate (1.0 == 100%). Strength buffs multiply the rate.
+    pub recovery: BuildTotal,
+    /// Maximum hit points.
+    pub max_hit_points: BuildTotal,
+    /// Maximum endurance.
+    pub max_endurance: BuildTotal,
+}
+
+impl fmt::Display for BuildTotals {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_percent(f: &mut fmt::Formatter<'_>, total: &BuildTotal) -> fmt::Result {
+            if total.value < total.uncapped {
+                writeln!(
+                    f,
+                    "\t{}: {:.2}% (capped from {:.2}%)",
+                    total.name,
+                    total.value * 100.0,
+                    total.uncapped * 100.0
+                )
+            } else {
+                writeln!(f, "\t{}: {:.2}%", total.name, total.value * 100.0)
+            }
+        }
+        writeln!(f, "Defense:")?;
+        for total in &self.defense {
+            write_percent(f, total)?;
+        }
+        writeln!(f, "Resistance:")?;
+        for total in &self.resistance {
+            write_percent(f, total)?;
+        }
+        writeln!(f, "Damage:")?;
+        for total in &self.damage_bonus {
+            write_percent(f, total)?;
+        }
+        writeln!(f, "Other:")?;
+        write_percent(f, &self.recharge)?;
+        write_percent(f, &self.accuracy)?;
+        write_percent(f, &self.to_hit)?;
+        write_percent(f, &self.regeneration)?;
+        write_percent(f, &self.recovery)?;
+        writeln!(
+            f,
+            "\t{}: {:.2}",
+            self.max_hit_points.name, self.max_hit_points.value
+        )?;
+        writeln!(
+            f,
+            "\t{}: {:.2}",
+            self.max_endurance.name, self.max_endurance.value
+        )?;
+        Ok(())
+    }
+}
+
+/// Sums of attribute modifiers keyed by (aspect, attribute offset).
+type AttribSums = HashMap<(u32, usize), f32>;
+
+/// Evaluates a build, adding up all of the effects the build's powers have on the character.
+///
+/// # Arguments:
+///
+/// * `build` - The build to evaluate.
+/// * `powers_dict` - The powers dictionary containing the powers, enhancements, and boost sets used by `build`.
+///
+/// # Returns:
+///
+/// A `BuildTotals` sheet if successful. Otherwise, a `std::io::Error` describing which
+/// part of the build couldn't be resolved.
+///
+/// # Notes:
+///
+/// Only effects on the character themselves are counted, from auto powers and from toggles or clicks
+/// marked `active`. Enhancements are scaled using the archetype's tables at the build's level and are
+/// subject to enhancement diminishing returns (ED). Set bonuses are granted when enough distinct
+/// enhancements from a set are slotted in the same power, and the same bonus only counts 5 times.
+/// Regeneration and recovery are the base rate plus `Cur` buffs, multiplied by `Str` buffs.
+/// Totals are capped using the archetype's attribute max tables.
+pub fn evaluate_build(build: &Build, powers_dict: &PowersDictionary) -> Result<BuildTotals> {
+    let archetype = powers_dict
+        .find_archetype(&build.archetype)
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::Other,
+                format!("Archetype {} not found", build.archetype),
+            )
+        })?;
+    let level = build.level as usize - 1;
+    let boost_set_lookup = make_boost_set_lookup(&powers_dict.boost_sets);
+
+    let mut sums = AttribSums::new();
+    let mut set_bonuses: HashMap<NameKey, usize> = HashMap::new();
+    for build_power in &build.powers {
+        let power = find_power(powers_dict, &build_power.name)?;
+        // enhancement strength, before ED
+        let mut strength = AttribSums::new();
+        let mut boosts_per_set: HashMap<NameKey, Vec<&NameKey>> = HashMap::new();
+        for enh_name in &build_power.enhancements {
+            let enh = find_power(powers_dict, enh_name)?;
+            for effect_group in &enh.pp_effects {
+                add_enhancement_strength(&mut strength, effect_group, archetype, level);
+            }
+            if let Some(boost_set) = boost_set_lookup.get(enh_name) {
+                if let Some(set_name) = &boost_set.pch_name {
+                    let boosts = boosts_per_set.entry(set_name.clone()).or_default();
+                    if !boosts.contains(&enh_name) {
+                        boosts.push(enh_name);
+                    }
+                }
+            }
+        }
+        // the power's own effects
+        let active = build_power.active || matches!(power.e_type, PowerType::kPowerType_Auto);
+        if active {
+            for effect_group in &power.pp_effects {
+                add_effect_group(&mut sums, effect_group, power, archetype, level, &strength);
+            }
+        }
+        // set bonuses unlocked by this power's slotting
+        for (set_name, boosts) in &boosts_per_set {
+            let boost_set = &powers_dict.boost_sets[set_name];
+            for bonus in &boost_set.pp_bonuses {
+                if boosts.len() < bonus.i_min_boosts as usize
+                    || (bonus.i_max_boosts > 0 && boosts.len() > bonus.i_max_boosts as usize)
+                {
+                    continue;
+                }
+                for bonus_power in bonus.ppch_auto_powers.iter().chain(&bonus.pch_bonus_power) {
+                    *set_bonuses.entry(bonus_power.clone()).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+    for (bonus_power, count) in &set_bonuses {
+        if let Some(power) = powers_dict.find_power(bonus_power) {
+            for _ in 0..(*count).min(SET_BONUS_LIMIT) {
+                for effect_group in &power.pp_effects {
+                    add_effect_group(
+                        &mut sums,
+                        effect_group,
+                        power,
+                        archetype,
+                        level,
+                        &AttribSums::new(),
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(make_totals(
+        &sums,
+        archetype,
+        level,
+        &powers_dict.attrib_names,
+    ))
+}
+
+/// Finds a power or returns an error naming it.
+fn find_power<'a>(powers_dict: &'a PowersDictionary, name: &NameKey) -> Result<&'a BasePower> {
+    powers_dict
+        .find_power(name)
+        .map(|power| &**power)
+        .ok_or_else(|| Error::new(ErrorKind::Other, format!("Power {} not found", name)))
+}
+
+/// Creates a lookup of boost (enhancement) names to the set that contains them.
+fn make_boost_set_lookup(boost_sets: &Keyed<BoostSet>) -> HashMap<&NameKey, &BoostSet> {
+    let mut lookup = HashMap::new();
+    for boost_set in boost_sets.values() {
+        for boost_list in &boost_set.pp_boost_lists {
+            for boost in &boost_list.ppch_boosts {
+                lookup.insert(boost, &**boost_set);
+            }
+        }
+    }
+    lookup
+}
+
+/// Gets the character attribute offset modified by `attrib`, if any.
+fn attrib_offset(attrib: &SpecialAttrib) -> Option<usize> {
+    match attrib {
+        SpecialAttrib::kSpecialAttrib_Character(a) => Some(*a as usize),
+        _ => None,
+    }
+}
+
+/// Calculates the value of `template` for `archetype` at `level` (0-based).
+fn scaled_value(template: &AttribModTemplate, archetype: &Archetype, level: usize) -> Option<f32> {
+    if !matches!(template.e_type, ModType::kModType_Magnitude) {
+        return None;
+    }
+    let table_value = template
+        .pch_table
+        .as_ref()
+        .and_then(|table| archetype.pp_named_tables.get(&table.to_lowercase()))
+        .and_then(|table| table.pf_values.get(level))
+        .copied()
+        .unwrap_or(1.0);
+    Some(table_value * template.f_scale)
+}
+
+/// Adds up the strength granted by an enhancement's effect group.
+fn add_enhancement_strength(
+    strength: &mut AttribSums,
+    effect_group: &EffectGroup,
+    archetype: &Archetype,
+    level: usize,
+) {
+    for template in &effect_group.pp_templates {
+        if template.off_aspect != OFFSET_STRENGTH {
+            continue;
+        }
+        if let Some(value) = scaled_value(template, archetype, level) {
+            // keep enhancements that ignore ED separate so they can be added in after
+            let aspect = if template
+                .i_flags
+                .contains(AttribModFlag::BoostIgnoreDiminishing)
+            {
+                OFFSET_MODIFIERS
+            } else {
+                OFFSET_STRENGTH
+            };
+            for attrib in &template.p_attrib {
+                if let Some(offset) = attrib_offset(attrib) {
+                    *strength.entry((aspect, offset)).or_insert(0.0) += value;
+                }
+            }
+        }
+    }
+    for child in &effect_group.pp_effects {
+        add_enhancement_strength(strength, child, archetype, level);
+    }
+}
+
+/// Adds the effects of `effect_group` that apply to the character using `power`.
+fn add_effect_group(
+    sums: &mut AttribSums,
+    effect_group: &EffectGroup,
+    power: &BasePower,
+    archetype: &Archetype,
+    level: usize,
+    strength: &AttribSums,
+) {
+    if effect_group.i_flags.contains(EffectGroupFlag::PVPOnly) {
+        return;
+    }
+    let self_only = matches!(power.e_target_type, TargetType::kTargetType_Caster);
+    for template in &effect_group.pp_templates {
+        let on_self = match template.e_target {
+            ModTarget::kModTarget_Caster | ModTarget::kModTarget_CastersOwnerAndAllPets => true,
+            ModTarget::kModTarget_Affected | ModTarget::kModTarget_Focus => self_only,
+            _ => false,
+        };
+        let applied = matches!(
+            template.e_application_type,
+            ModApplicationType::kModApplicationType_OnTick
+                | ModApplicationType::kModApplicationType_OnActivate
+                | ModApplicationType::kModApplicationType_OnEnable
+        );
+        if !on_self || !applied {
+            continue;
+        }
+        let value = match scaled_value(template, archetype, level) {
+            Some(value) => value * effect_group.f_chance,
+            None => continue,
+        };
+        for attrib in &template.p_attrib {
+            if let Some(offset) = attrib_offset(attrib) {
+                let enhancement = if power.b_ignore_strength
+                    || template.i_flags.contains(AttribModFlag::IgnoreStrength)
+                {
+                    0.0
+                } else {
+                    enhanced_strength(strength, template.off_aspect, offset)
+                };
+                *sums.entry((template.off_aspect, offset)).or_insert(0.0) +=
+                    value * (1.0 + enhancement);
+            }
+        }
+    }
+    for child in &effect_group.pp_effects {
+        add_effect_group(sums, child, power, archetype, level, strength);
+    }
+}
+
+/// Gets the enhancement strength for an effect on `offset`, after ED.
+fn enhanced_strength(strength: &AttribSums, aspect: u32, offset: usize) -> f32 {
+    let diminished = strength
+        .get(&(OFFSET_STRENGTH, offset))
+        .map(|s| apply_ed(*s, ed_schedule(aspect, offset)))
+        .unwrap_or(0.0);
+    let undiminished = strength
+        .get(&(OFFSET_MODIFIERS, offset))
+        .copied()
+        .unwrap_or(0.0);
+    diminished + undiminished
+}
+
+/// Picks the ED schedule for enhancing an effect on `offset` in `aspect`.
+fn ed_schedule(aspect: u32, offset: usize) -> &'static [f32; 3] {
+    if aspect == OFFSET_RESIST {
+        return &ED_SCHEDULE_B;
+    }
+    match offset {
+        CharacterAttributes::OFFSET_DEF_0..=CharacterAttributes::OFFSET_DEFENSE
+        | CharacterAttributes::OFFSET_TOHIT
+        | CharacterAttributes::OFFSET_RANGE => &ED_SCHEDULE_B,
+        CharacterAttributes::OFFSET_INTERRUPT_TIME => &ED_SCHEDULE_C,
+        CharacterAttributes::OFFSET_KNOCKUP
+        | CharacterAttributes::OFFSET_KNOCKBACK
+        | CharacterAttributes::OFFSET_REPEL
+        | CharacterAttributes::OFFSET_INTANGIBLE => &ED_SCHEDULE_D,
+        _ => &ED_SCHEDULE_A,
+    }
+}
+
+/// Applies enhancement diminishing returns to `strength` using `schedule`.
+fn apply_ed(strength: f32, schedule: &[f32; 3]) -> f32 {
+    let mut result = strength.min(schedule[0]);
+    let mut lower = schedule[0];
+    for (i, multiplier) in ED_MULTIPLIERS.iter().enumerate() {
+        if strength <= lower {
+            break;
+        }
+        let upper = schedule.get(i + 1).copied().unwrap_or(f32::INFINITY);
+        result += (strength.min(upper) - lower) * multiplier;
+        lower = upper;
+    }
+    result
+}
+
+/// Gets the value for `level` (0-based) from the first table returned by `get`.
+fn table_value<F>(tables: &Vec<CharacterAttributesTable>, level: usize, get: F) -> Option<f32>
+where
+    F: Fn(&CharacterAttributesTable) -> &Vec<f32>,
+{
+    tables
+        .get(0)
+        .and_then(|table| get(table).get(level))
+        .copied()
+}
+
+/// Turns the attribute sums into a totals sheet, applying caps from `archetype`.
+fn make_totals(
+    sums: &AttribSums,
+    archetype: &Archetype,
+    level: usize,
+    attrib_names: &AttribNames,
+) -> BuildTotals {
+    let sum = |aspect: u32, offset: usize| sums.get(&(aspect, offset)).copied().unwrap_or(0.0);
+    let base = archetype.pp_attrib_base.get(0);
+    // strength caps are the total strength, so remove the base 100% to get the bonus cap
+    let str_cap = |get: &dyn Fn(&CharacterAttributesTable) -> &Vec<f32>| {
+        table_value(&archetype.pp_attrib_temp_strength_max, level, get).map(|cap| cap - 1.0)
+    };
+
+    let mut defense = Vec::new();
+    for (i, name) in attrib_names.pp_defense.iter().enumerate() {
+        let value = sum(OFFSET_MODIFIERS, CharacterAttributes::OFFSET_DEF_0 + i * 4);
+        if value != 0.0 {
+            defense.push(BuildTotal::new(
+                name.pch_display_name.clone().unwrap_or_default(),
+                value,
+                table_value(&archetype.pp_attrib_temp_max, level, |t| {
+                    &t.pf_defense_type[i]
+                }),
+            ));
+        }
+    }
+    let mut resistance = Vec::new();
+    let mut damage_bonus = Vec::new();
+    for (i, name) in attrib_names.pp_damage.iter().enumerate() {
+        let offset = CharacterAttributes::OFFSET_DMG_0 + i * 4;
+        let display_name = name.pch_display_name.clone().unwrap_or_default();
+        let value = sum(OFFSET_RESIST, offset);
+        if value != 0.0 {
+            resistance.push(BuildTotal::new(
+                display_name.clone(),
+                value,
+                table_value(&archetype.pp_attrib_temp_resistance_max, level, |t| {
+                    &t.pf_damage_type[i]
+                }),
+            ));
+        }
+        let value = sum(OFFSET_STRENGTH, offset);
+        if value != 0.0 {
+            damage_bonus.push(BuildTotal::new(
+                display_name,
+                value,
+                str_cap(&|t| &t.pf_damage_type[i]),
+            ));
+        }
+    }
+
+    let base_regeneration = base.map(|b| b.f_regeneration).unwrap_or(1.0);
+    let base_recovery = base.map(|b| b.f_recovery).unwrap_or(1.0);
+    // strength buffs to regeneration and recovery scale the whole rate, up to the strength cap
+    let capped_strength = |offset: usize, cap: Option<f32>| {
+        let strength = sum(OFFSET_STRENGTH, offset);
+        cap.map(|cap| strength.min(cap)).unwrap_or(strength)
+    };
+    let regeneration_strength = capped_strength(
+        CharacterAttributes::OFFSET_REGENERATION,
+        str_cap(&|t| &t.pf_regeneration),
+    );
+    let recovery_strength = capped_strength(
+        CharacterAttributes::OFFSET_RECOVERY,
+        str_cap(&|t| &t.pf_recovery),
+    );
+    let base_hit_points =
+        table_value(&archetype.pp_attrib_temp_max, level, |t| &t.pf_hit_points).unwrap_or(0.0);
+    let base_endurance =
+        table_value(&archetype.pp_attrib_temp_max, level, |t| &t.pf_endurance).unwrap_or(100.0);
+    BuildTotals {
+        defense,
+        resistance,
+        damage_bonus,
+        recharge: BuildTotal::new(
+            String::from("Recharge"),
+            sum(OFFSET_STRENGTH, CharacterAttributes::OFFSET_RECHARGE_TIME),
+            str_cap(&|t| &t.pf_recharge_time),
+        ),
+        accuracy: BuildTotal::new(
+            String::from("Accuracy"),
+            sum(OFFSET_STRENGTH, CharacterAttributes::OFFSET_ACCURACY),
+            str_cap(&|t| &t.pf_accuracy),
+        ),
+        to_hit: BuildTotal::new(
+            String::from("ToHit"),
+            sum(OFFSET_MODIFIERS, CharacterAttributes::OFFSET_TOHIT),
+            table_value(&archetype.pp_attrib_temp_max, level, |t| &t.pf_to_hit),
+        ),
+        regeneration: BuildTotal::new(
+            String::from("Regeneration"),
+            (base_regeneration + sum(OFFSET_MODIFIERS, CharacterAttributes::OFFSET_REGENERATION))
+                * (1.0 + regeneration_strength),
+            table_value(&archetype.pp_attrib_temp_max, level, |t| &t.pf_regeneration),
+        ),
+        recovery: BuildTotal::new(
+            String::from("Recovery"),
+            (base_recovery + sum(OFFSET_MODIFIERS, CharacterAttributes::OFFSET_RECOVERY))
+                * (1.0 + recovery_strength),
+            table_value(&archetype.pp_attrib_temp_max, level, |t| &t.pf_recovery),
+        ),
+        max_hit_points: BuildTotal::new(
+            String::from("HitPoints"),
+            base_hit_points + sum(OFFSET_MAXIMUM, CharacterAttributes::OFFSET_HIT_POINTS),
+            table_value(&archetype.pp_attrib_temp_max_max, level, |t| {
+                &t.pf_hit_points
+            }),
+        ),
+        max_endurance: BuildTotal::new(
+            String::from("Endurance"),
+            base_endurance + sum(OFFSET_MAXIMUM, CharacterAttributes::OFFSET_ENDURANCE),
+            table_value(&archetype.pp_attrib_temp_max_max, level, |t| {
+                &t.pf_endurance
+            }),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    fn make_template(aspect: u32, offset: usize, scale: f32) -> AttribModTemplate {
+        let mut template = AttribModTemplate::new();
+        template.p_attrib = vec![SpecialAttrib::kSpecialAttrib_Character(offset as i32)];
+        template.off_aspect = aspect;
+        template.e_application_type = ModApplicationType::kModApplicationType_OnActivate;
+        template.e_type = ModType::kModType_Magnitude;
+        template.e_target = ModTarget::kModTarget_Caster;
+        template.f_scale = scale;
+        template
+    }
+
+    fn make_power(
+        full_name: &str,
+        e_type: PowerType,
+        templates: Vec<AttribModTemplate>,
+    ) -> BasePower {
+        let mut power = BasePower::new();
+        power.pch_full_name = Some(NameKey::new(full_name));
+        power.e_type = e_type;
+        let mut effect_group = EffectGroup::new();
+        effect_group.f_chance = 1.0;
+        effect_group.pp_templates = templates;
+        power.pp_effects.push(Rc::new(effect_group));
+        power
+    }
+
+    fn make_power_category(name: &str, powers: Vec<BasePower>) -> PowerCategory {
+        let mut pset = BasePowerSet::new();
+        pset.pp_powers = powers.into_iter().map(Rc::new).collect();
+        let mut pcat = PowerCategory::new();
+        pcat.pch_name = Some(NameKey::new(name));
+        pcat.pp_power_sets.push(Rc::new(pset));
+        pcat
+    }
+
+    /// A tanker that can reach 45% smashing defense and 400% recharge, with:
+    /// * a toggle for 30% smashing defense and one for 100%,
+    /// * an auto power for 40% regeneration, 50% regeneration strength, and 25% recovery,
+    /// * an attack with no effects on the caster,
+    /// * a set of two 20% defense enhancements that grant 5% defense and 10% recharge together.
+    fn make_powers_dict() -> PowersDictionary {
+        let def = CharacterAttributes::OFFSET_DEF_0;
+        let regen = CharacterAttributes::OFFSET_REGENERATION;
+        let recovery = CharacterAttributes::OFFSET_RECOVERY;
+        let recharge = CharacterAttributes::OFFSET_RECHARGE_TIME;
+        let powers = vec![
+            make_power(
+                "Tanker.Armor.Armor",
+                PowerType::kPowerType_Toggle,
+                vec![make_template(OFFSET_MODIFIERS, def, 0.3)],
+            ),
+            make_power(
+                "Tanker.Armor.Unused_Armor",
+                PowerType::kPowerType_Toggle,
+                vec![make_template(OFFSET_MODIFIERS, def, 1.0)],
+            ),
+            make_power(
+                "Tanker.Armor.Health",
+                PowerType::kPowerType_Auto,
+                vec![
+                    make_template(OFFSET_MODIFIERS, regen, 0.4),
+                    make_template(OFFSET_STRENGTH, regen, 0.5),
+                    make_template(OFFSET_MODIFIERS, recovery, 0.25),
+                ],
+            ),
+            make_power(
+                "Tanker.Armor.Punch",
+                PowerType::kPowerType_Click,
+                Vec::new(),
+            ),
+        ];
+        let boosts = vec![
+            make_power(
+                "Boosts.Def_A.Def_A",
+                PowerType::kPowerType_Boost,
+                vec![make_template(OFFSET_STRENGTH, def, 0.2)],
+            ),
+            make_power(
+                "Boosts.Def_B.Def_B",
+                PowerType::kPowerType_Boost,
+                vec![make_template(OFFSET_STRENGTH, def, 0.2)],
+            ),
+            make_power(
+                "Boosts.Def_Bonus.Def_Bonus",
+                PowerType::kPowerType_Auto,
+                vec![
+                    make_template(OFFSET_MODIFIERS, def, 0.05),
+                    make_template(OFFSET_STRENGTH, recharge, 0.1),
+                ],
+            ),
+        ];
+
+        let mut boost_set = BoostSet::new();
+        boost_set.pch_name = Some(NameKey::new("Def_Set"));
+        for boost in &["Boosts.Def_A.Def_A", "Boosts.Def_B.Def_B"] {
+            let mut boost_list = BoostList::new();
+            boost_list.ppch_boosts.push(NameKey::new(*boost));
+            boost_set.pp_boost_lists.push(boost_list);
+        }
+        let mut bonus = BoostSetBonus::new();
+        bonus.i_min_boosts = 2;
+        bonus.pch_bonus_power = Some(NameKey::new("Boosts.Def_Bonus.Def_Bonus"));
+        boost_set.pp_bonuses.push(bonus);
+        let mut boost_sets = Keyed::new();
+        boost_sets.insert(NameKey::new("Def_Set"), Rc::new(boost_set));
+
+        let mut temp_max = CharacterAttributesTable::default();
+        temp_max.pf_defense_type[0] = vec![0.45; 50];
+        let mut temp_strength_max = CharacterAttributesTable::default();
+        temp_strength_max.pf_recharge_time = vec![5.0; 50];
+        let mut archetype = Archetype::new();
+        archetype.pch_name = Some(String::from("Class_Tanker"));
+        archetype.pch_display_name = Some(String::from("Tanker"));
+        archetype.pp_attrib_temp_max.push(temp_max);
+        archetype
+            .pp_attrib_temp_strength_max
+            .push(temp_strength_max);
+        let mut archetypes = Keyed::new();
+        archetypes.insert(NameKey::new("Class_Tanker"), Rc::new(archetype));
+
+        let mut attrib_names = AttribNames::new();
+        let mut smashing = AttribName::new();
+        smashing.pch_display_name = Some(String::from("Smashing"));
+        attrib_names.pp_defense.push(smashing);
+
+        PowersDictionary {
+            power_categories: vec![
+                Rc::new(make_power_category("Tanker", powers)),
+                Rc::new(make_power_category("Boosts", boosts)),
+            ],
+            archetypes,
+            boost_sets,
+            attrib_names,
+        }
+    }
+
+    fn make_build_power(name: &str, active: bool, enhancements: &[&str]) -> BuildPower {
+        BuildPower {
+            name: NameKey::new(name),
+            active,
+            enhancements: enhancements.iter().map(|e| NameKey::new(*e)).collect(),
+        }
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 0.0001,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn test_evaluate_build() {
+        let set = ["Boosts.Def_A.Def_A", "Boosts.Def_B.Def_B"];
+        let mut build = Build {
+            archetype: NameKey::new("Tanker"),
+            level: 50,
+            powers: vec![
+                make_build_power("Tanker.Armor.Armor", true, &set),
+                make_build_power("Tanker.Armor.Unused_Armor", false, &[]),
+                make_build_power("Tanker.Armor.Health", false, &[]),
+            ],
+        };
+        // the same set slotted in 5 more powers, for 6 bonuses in all
+        for _ in 0..5 {
+            build
+                .powers
+                .push(make_build_power("Tanker.Armor.Punch", false, &set));
+        }
+        let powers_dict = make_powers_dict();
+        let totals = evaluate_build(&build, &powers_dict).unwrap();
+
+        // the running toggle is enhanced by 40%, and only 5 of the 6 set bonuses count
+        assert_eq!(totals.defense.len(), 1);
+        assert_eq!(totals.defense[0].name, "Smashing");
+        assert_close(totals.defense[0].uncapped, 0.3 * 1.4 + 5.0 * 0.05);
+        assert_close(totals.defense[0].value, 0.45);
+        assert_close(totals.recharge.value, 5.0 * 0.1);
+        // auto powers count without being marked active
+        assert_close(totals.regeneration.value, (1.0 + 0.4) * 1.5);
+        assert_close(totals.recovery.value, 1.25);
+        assert!(totals.resistance.is_empty());
+
+        // a toggle that isn't running doesn't count
+        build.powers[0].active = false;
+        let totals = evaluate_build(&build, &powers_dict).unwrap();
+        assert_close(totals.defense[0].value, 5.0 * 0.05);
+
+        build.archetype = NameKey::new("Blaster");
+        assert!(evaluate_build(&build, &powers_dict).is_err());
+    }
+
+    #[test]
+    fn test_apply_ed() {
+        // below the first threshold, nothing changes
+        assert_eq!(apply_ed(0.5, &ED_SCHEDULE_A), 0.5);
+        // three SOs of schedule A
+        assert!((apply_ed(1.0, &ED_SCHEDULE_A) - 0.95).abs() < 0.0001);
+        // four SOs of schedule A
+        assert!((apply_ed(4.0 / 3.0, &ED_SCHEDULE_A) - 1.0).abs() < 0.0001);
+        // three SOs of schedule B
+        assert!((apply_ed(0.6, &ED_SCHEDULE_B) - 0.56).abs() < 0.0001);
+    }
+}
@@ -0,0 +1,647 @@
+//! Reads the published .json back into memory, for Rust tools that consume the output rather
+//! than produce it.
+
+mod structs;
+
+use crate::NameKey;
+use flate2::read::GzDecoder;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, Error};
+use std::path::{Component, Path, PathBuf};
+pub use structs::*;
+
+/// Name of the .json files in an output tree.
+pub const JSON_FILE: &'static str = "index.json";
+
+/// The archetypes file's directory, used to work out the base URL of absolute links.
+const ARCHETYPES_DIR: &'static str = "archetypes/";
+
+/// A published powers dictionary, loaded from an output tree or a bundle.
+#[derive(Debug, Default)]
+pub struct Dictionary {
+    pub header: Header,
+    pub archetypes: Vec<Archetype>,
+    /// Power categories, in the order they're listed in the root index.
+    pub power_categories: Vec<PowerCategory>,
+    pub power_sets: Vec<PowerSet>,
+    pub powers: Vec<Power>,
+    /// Positions in the vectors above, keyed by name.
+    archetype_index: HashMap<NameKey, usize>,
+    power_category_index: HashMap<NameKey, usize>,
+    power_set_index: HashMap<NameKey, usize>,
+    power_index: HashMap<NameKey, usize>,
+    /// What each link followed while loading pointed to, keyed by the link as written.
+    links: HashMap<String, Target>,
+}
+
+/// Something a link can point to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Target {
+    Archetypes,
+    PowerCategory(usize),
+    PowerSet(usize),
+}
+
+impl Dictionary {
+    /// Loads an output tree written by `write_powers_dictionary`.
+    ///
+    /// # Arguments:
+    ///
+    /// * `path` - The output path, containing the root `index.json`.
+    ///
+    /// # Returns:
+    ///
+    /// A `Dictionary` if successful. Otherwise, an `io::Error` containing the error information.
+    ///
+    /// # Notes:
+    ///
+    /// Links are followed from the root index the same way a client of the published files would,
+    /// so the tree can be written with or without `base_json_url`. Categories that aren't in the root
+    /// index (e.g. ones holding pet powers) are found by following the links in powers. Power sets'
+    /// powers are moved out into `powers`.
+    pub fn load_tree(path: &Path) -> io::Result<Self> {
+        let root: Root = read_json(&path.join(JSON_FILE))?;
+        let resolver = LinkResolver::new(path, &root.archetypes);
+        let mut dict = Dictionary {
+            header: root.header.clone(),
+            ..Default::default()
+        };
+
+        let archetypes: Archetypes =
+            read_json(&resolver.resolve(Path::new(""), &root.archetypes)?)?;
+        dict.links
+            .insert(root.archetypes.clone(), Target::Archetypes);
+        for archetype in archetypes.archetypes {
+            dict.add_archetype(archetype);
+        }
+
+        // power set files still to be read, along with the links that led to them
+        let mut pending: Vec<(PathBuf, String)> = Vec::new();
+        let mut loaded: HashMap<PathBuf, Target> = HashMap::new();
+        for root_category in &root.power_categories {
+            let category_file = resolver.resolve(Path::new(""), &root_category.url)?;
+            let target = dict.load_power_category(&resolver, &category_file, &mut pending)?;
+            loaded.insert(category_file, target);
+            dict.links.insert(root_category.url.clone(), target);
+        }
+        while let Some((set_file, link)) = pending.pop() {
+            if let Some(target) = loaded.get(&set_file) {
+                dict.links.insert(link, *target);
+                continue;
+            }
+            let mut power_set: PowerSet = read_json(&set_file)?;
+            let set_dir = resolver.relative_dir(&set_file);
+            for power in power_set.powers.drain(..) {
+                for power_link in power_links(&power) {
+                    pending.push((
+                        resolver.resolve(&set_dir, power_link)?,
+                        power_link.to_owned(),
+                    ));
+                }
+                dict.add_power(power);
+            }
+            let target = Target::PowerSet(dict.add_power_set(power_set));
+            dict.links.insert(link, target);
+            dict.links.insert(resolver.root_relative(&set_file), target);
+            loaded.insert(set_file.clone(), target);
+
+            // make sure the set's category is there too
+            if let Some(category_dir) = set_file.parent().and_then(|dir| dir.parent()) {
+                let category_file = category_dir.join(JSON_FILE);
+                if !loaded.contains_key(&category_file) && category_file.is_file() {
+                    let target =
+                        dict.load_power_category(&resolver, &category_file, &mut pending)?;
+                    dict.links
+                        .insert(resolver.root_relative(&category_file), target);
+                    loaded.insert(category_file, target);
+                }
+            }
+        }
+        Ok(dict)
+    }
+
+    /// Loads a bundle file written by the bundle output, gzipped or not.
+    ///
+    /// # Arguments:
+    ///
+    /// * `path` - Path to the bundle file. If it ends with .gz, it's decompressed first.
+    ///
+    /// # Returns:
+    ///
+    /// A `Dictionary` if successful. Otherwise, an `io::Error` containing the error information.
+    ///
+    /// # Notes:
+    ///
+    /// The bundle has no links, so `resolve` never finds anything.
+    pub fn load_bundle(path: &Path) -> io::Result<Self> {
+        let bundle: Bundle = read_json(path)?;
+        let mut dict = Dictionary {
+            header: bundle.header,
+            ..Default::default()
+        };
+        for (_, archetype) in bundle.archetypes {
+            dict.add_archetype(archetype);
+        }
+        // keep the root index's order
+        let mut categories = bundle.power_categories;
+        for root_category in &bundle.root {
+            if let Some(name) = &root_category.name {
+                if let Some(category) = categories.remove(name.get()) {
+                    dict.add_power_category(category);
+                }
+            }
+        }
+        for (_, power_set) in bundle.power_sets {
+            dict.add_power_set(power_set);
+        }
+        for (_, power) in bundle.powers {
+            dict.add_power(power);
+        }
+        Ok(dict)
+    }
+
+    /// Finds an archetype by name.
+    pub fn archetype(&self, name: &str) -> Option<&Archetype> {
+        Self::find(&self.archetype_index, &self.archetypes, name)
+    }
+
+    /// Finds a power category by name.
+    pub fn power_category(&self, name: &str) -> Option<&PowerCategory> {
+        Self::find(&self.power_category_index, &self.power_categories, name)
+    }
+
+    /// Finds a power set by full name, e.g. `Blaster_Ranged.Fire_Blast`.
+    pub fn power_set(&self, name: &str) -> Option<&PowerSet> {
+        Self::find(&self.power_set_index, &self.power_sets, name)
+    }
+
+    /// Finds a power by full name, e.g. `Blaster_Ranged.Fire_Blast.Flares`.
+    pub fn power(&self, name: &str) -> Option<&Power> {
+        Self::find(&self.power_index, &self.powers, name)
+    }
+
+    /// Gets the power sets in a power category, in order.
+    pub fn power_sets_in<'a>(
+        &'a self,
+        power_category: &'a PowerCategory,
+    ) -> impl Iterator<Item = &'a PowerSet> + 'a {
+        power_category
+            .power_sets
+            .iter()
+            .filter_map(move |s| self.power_set(s.name.as_ref()?.get()))
+    }
+
+    /// Gets the powers in a power set, in order.
+    pub fn powers_in<'a>(
+        &'a self,
+        power_set: &'a PowerSet,
+    ) -> impl Iterator<Item = &'a Power> + 'a {
+        power_set
+            .ordered_power_names
+            .iter()
+            .filter_map(move |name| self.power(name.get()))
+    }
+
+    /// Gets the power category a power set belongs to.
+    pub fn power_category_of(&self, power_set: &PowerSet) -> Option<&PowerCategory> {
+        let name = power_set.name.as_ref()?;
+        self.power_category(name.split().first()?)
+    }
+
+    /// Gets the power set a power belongs to.
+    pub fn power_set_of(&self, power: &Power) -> Option<&PowerSet> {
+        let parts = power.name.as_ref()?.split();
+        if parts.len() < 2 {
+            return None;
+        }
+        self.power_set(&format!("{}.{}", parts[0], parts[1]))
+    }
+
+    /// Gets the powers that `power` can be redirected to.
+    pub fn redirects_of<'a>(&'a self, power: &'a Power) -> impl Iterator<Item = &'a Power> + 'a {
+        power
+            .redirects
+            .iter()
+            .filter_map(move |r| self.power(r.name.as_ref()?.get()))
+    }
+
+    /// Gets the powers that refer to `power`, along with how they refer to it.
+    pub fn references_to<'a>(
+        &'a self,
+        power: &'a Power,
+    ) -> impl Iterator<Item = (&'a str, &'a Power)> + 'a {
+        power
+            .referenced_by
+            .iter()
+            .filter_map(move |r| Some((&r.relation[..], self.power(r.name.get())?)))
+    }
+
+    /// Finds what a link in one of the loaded files points to.
+    ///
+    /// # Arguments:
+    ///
+    /// * `url` - A `url` field from any of the loaded files.
+    ///
+    /// # Returns:
+    ///
+    /// The `Target` if the link was followed while loading, or points to a loaded power set.
+    /// Otherwise, `None`.
+    ///
+    /// # Notes:
+    ///
+    /// Links from powers to other power sets are relative to the power's file (`../../`) when the tree
+    /// is written without `base_json_url`, so that prefix is dropped before looking them up.
+    pub fn resolve(&self, url: &str) -> Option<Target> {
+        if let Some(target) = self.links.get(url) {
+            return Some(*target);
+        }
+        let mut url = url;
+        while let Some(rest) = url.strip_prefix("../") {
+            url = rest;
+        }
+        self.links.get(url).copied()
+    }
+
+    /// Reads a power category file and queues up its power sets.
+    fn load_power_category(
+        &mut self,
+        resolver: &LinkResolver,
+        category_file: &Path,
+        pending: &mut Vec<(PathBuf, String)>,
+    ) -> io::Result<Target> {
+        let category: PowerCategory = read_json(category_file)?;
+        let category_dir = resolver.relative_dir(category_file);
+        for link in category.power_sets.iter().filter_map(|s| s.url.as_ref()) {
+            pending.push((resolver.resolve(&category_dir, link)?, link.clone()));
+        }
+        Ok(Target::PowerCategory(self.add_power_category(category)))
+    }
+
+    fn find<'a, T>(index: &HashMap<NameKey, usize>, items: &'a [T], name: &str) -> Option<&'a T> {
+        index.get(&NameKey::new(name)).map(|i| &items[*i])
+    }
+
+    fn add_archetype(&mut self, archetype: Archetype) {
+        if let Some(name) = &archetype.name {
+            self.archetype_index
+                .insert(NameKey::new(name.clone()), self.archetypes.len());
+        }
+        self.archetypes.push(archetype);
+    }
+
+    fn add_power_category(&mut self, category: PowerCategory) -> usize {
+        let i = self.power_categories.len();
+        if let Some(name) = &category.name {
+            self.power_category_index.insert(name.clone(), i);
+        }
+        self.power_categories.push(category);
+        i
+    }
+
+    fn add_power_set(&mut self, power_set: PowerSet) -> usize {
+        let i = self.power_sets.len();
+        if let Some(name) = &power_set.name {
+            self.power_set_index.insert(name.clone(), i);
+        }
+        self.power_sets.push(power_set);
+        i
+    }
+
+    fn add_power(&mut self, power: Power) {
+        if let Some(name) = &power.name {
+            self.power_index.insert(name.clone(), self.powers.len());
+        }
+        self.powers.push(power);
+    }
+}
+
+/// Turns the links between files in an output tree into local paths.
+struct LinkResolver<'a> {
+    /// The output path.
+    root: &'a Path,
+    /// The `base_json_url` the tree was written with, if any.
+    base_url: Option<&'a str>,
+}
+
+impl<'a> LinkResolver<'a> {
+    /// Works out how links were written from the root index's link to the archetypes file.
+    fn new(root: &'a Path, archetypes_url: &'a str) -> Self {
+        // with base_json_url, links are absolute and point to directories
+        let base_url = archetypes_url.strip_suffix(ARCHETYPES_DIR);
+        LinkResolver { root, base_url }
+    }
+
+    /// Gets the local path of the file that `url` points to.
+    ///
+    /// # Arguments:
+    ///
+    /// * `dir` - Directory of the file containing the link, relative to the root.
+    /// * `url` - The link.
+    fn resolve(&self, dir: &Path, url: &str) -> io::Result<PathBuf> {
+        let relative = match self.base_url {
+            Some(base_url) => match url.strip_prefix(base_url) {
+                Some(rest) => PathBuf::from(rest).join(JSON_FILE),
+                None => return Err(Error::other(format!("{} is outside of {}", url, base_url))),
+            },
+            None => dir.join(url),
+        };
+        // normalize so links can't escape the root
+        let mut path = PathBuf::new();
+        for component in relative.components() {
+            match component {
+                Component::Normal(part) => path.push(part),
+                Component::ParentDir if path.pop() => (),
+                Component::CurDir => (),
+                _ => {
+                    return Err(Error::other(format!(
+                        "{} points outside of {}",
+                        url,
+                        self.root.display()
+                    )))
+                }
+            }
+        }
+        Ok(self.root.join(path))
+    }
+
+    /// Gets the directory of a local file, relative to the root.
+    fn relative_dir(&self, file: &Path) -> PathBuf {
+        file.parent()
+            .and_then(|dir| dir.strip_prefix(self.root).ok())
+            .map(|dir| dir.to_owned())
+            .unwrap_or_default()
+    }
+
+    /// Gets a local file's path relative to the root, in the form used by relative links.
+    fn root_relative(&self, file: &Path) -> String {
+        file.strip_prefix(self.root)
+            .unwrap_or(file)
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+/// Gets the links from a power to other power sets.
+fn power_links(power: &Power) -> Vec<&str> {
+    fn add_effect_group_links<'a>(effect_group: &'a EffectGroup, links: &mut Vec<&'a str>) {
+        for effect in &effect_group.effects {
+            let refs = match &effect.parameter {
+                Some(AttribModParam::CreateEntity {
+                    powers, redirects, ..
+                }) => powers.iter().chain(redirects).collect(),
+                Some(AttribModParam::Power { powers, .. }) => powers.iter().collect(),
+                _ => Vec::new(),
+            };
+            links.extend(refs.into_iter().filter_map(|r| r.url.as_deref()));
+        }
+        for child in &effect_group.child_effect_groups {
+            add_effect_group_links(child, links);
+        }
+    }
+
+    let mut links: Vec<&str> = power
+        .redirects
+        .iter()
+        .filter_map(|r| r.url.as_deref())
+        .chain(power.referenced_by.iter().filter_map(|r| r.url.as_deref()))
+        .collect();
+    for effect_group in &power.effect_groups {
+        add_effect_group_links(effect_group, &mut links);
+    }
+    links
+}
+
+/// Reads a .json file, decompressing it first if it ends with .gz.
+fn read_json<T: DeserializeOwned>(path: &Path) -> io::Result<T> {
+    let reader = BufReader::new(File::open(path)?);
+    let result = if path.extension().is_some_and(|ext| ext == "gz") {
+        serde_json::from_reader(GzDecoder::new(reader))
+    } else {
+        serde_json::from_reader(reader)
+    };
+    result.map_err(|e| Error::other(format!("{}: {}", path.display(), e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use serde_json::{json, Value};
+    use std::fs;
+    use std::io::Write;
+    use std::process;
+
+    /// A power as it appears in a power set file, with only the fields that are required.
+    fn make_power(name: &str, referenced_by: Value) -> Value {
+        json!({
+            "name": name,
+            "available_at_level": 1,
+            "auto_issue": false,
+            "accuracy": 1.0,
+            "effect_area": {},
+            "requires_line_of_sight": true,
+            "activate": { "recharge_time": 4.0 },
+            "effect_groups": [{ "visible_in_info_window": true, "chance_percent": 100.0, "effects": [] }],
+            "referenced_by": referenced_by,
+        })
+    }
+
+    /// A power set as it appears in its own file, with its powers inline.
+    fn make_power_set(name: &str, powers: Vec<Value>) -> Value {
+        json!({
+            "issue": "Test",
+            "name": name,
+            "show_in_power_management": true,
+            "show_in_power_info": true,
+            "ordered_power_names": powers.iter().map(|p| p["name"].clone()).collect::<Vec<_>>(),
+            "powers": powers,
+        })
+    }
+
+    #[test]
+    fn test_fx_changes() {
+        let custom_fx: CustomFX = serde_json::from_str(
+            r#"{
+                "token": "Blue", "display_name": "Blue Fire",
+                "fx_changes": { "attack_fx": null, "hit_fx": "POWERS/Fire/Hit_Blue.fx", "time_before_hit": 0 }
+            }"#,
+        )
+        .unwrap();
+        let changes = custom_fx.fx_changes.unwrap();
+        assert_eq!(changes.attack_fx, Some(None));
+        assert_eq!(
+            changes.hit_fx,
+            Some(Some(String::from("POWERS/Fire/Hit_Blue.fx")))
+        );
+        assert_eq!(changes.time_before_hit, Some(0.0));
+        assert!(changes.block_fx.is_none());
+        assert!(changes.animation_time.is_none());
+    }
+
+    #[test]
+    fn test_resolve_links() {
+        let root = Path::new("powers");
+        let relative = LinkResolver::new(root, "archetypes/index.json");
+        assert_eq!(
+            relative
+                .resolve(Path::new("ranged"), "fire-blast/index.json")
+                .unwrap(),
+            root.join("ranged/fire-blast/index.json")
+        );
+        assert_eq!(
+            relative
+                .resolve(
+                    Path::new("ranged/fire-blast"),
+                    "../../melee/fiery-melee/index.json"
+                )
+                .unwrap(),
+            root.join("melee/fiery-melee/index.json")
+        );
+        assert!(relative.resolve(Path::new(""), "../../etc/passwd").is_err());
+
+        let absolute = LinkResolver::new(root, "https://example.com/powers/archetypes/");
+        assert_eq!(
+            absolute
+                .resolve(
+                    Path::new(""),
+                    "https://example.com/powers/ranged/fire-blast/"
+                )
+                .unwrap(),
+            root.join("ranged/fire-blast/index.json")
+        );
+        assert!(absolute
+            .resolve(Path::new(""), "https://example.org/ranged/")
+            .is_err());
+    }
+
+    #[test]
+    fn test_load_tree_and_bundle() {
+        let base_path = std::env::temp_dir().join(format!("powers-client-test-{}", process::id()));
+        let _ = fs::remove_dir_all(&base_path);
+        let tree_path = base_path.join("tree");
+        let flares = make_power(
+            "Ranged.Fire_Blast.Flares",
+            json!([{
+                "relation": "GrantedBy",
+                "name": "Pets.Fire_Imps.Ignite",
+                "url": "../../pets/fire-imps/index.json",
+            }]),
+        );
+        let ignite = make_power("Pets.Fire_Imps.Ignite", json!([]));
+        let files = vec![
+            (
+                "index.json",
+                json!({
+                    "issue": "Test",
+                    "archetypes": "archetypes/index.json",
+                    "power_categories": [{ "name": "Ranged", "url": "ranged/index.json" }],
+                }),
+            ),
+            (
+                "archetypes/index.json",
+                json!({
+                    "issue": "Test",
+                    "archetypes": [{ "name": "Class_Blaster" }, { "name": "Class_Tanker" }],
+                }),
+            ),
+            (
+                "ranged/index.json",
+                json!({
+                    "name": "Ranged",
+                    "power_sets": [{ "name": "Ranged.Fire_Blast", "url": "fire-blast/index.json" }],
+                }),
+            ),
+            (
+                "ranged/fire-blast/index.json",
+                make_power_set("Ranged.Fire_Blast", vec![flares.clone()]),
+            ),
+            (
+                "pets/index.json",
+                json!({
+                    "name": "Pets",
+                    "power_sets": [{ "name": "Pets.Fire_Imps", "url": "fire-imps/index.json" }],
+                }),
+            ),
+            (
+                "pets/fire-imps/index.json",
+                make_power_set("Pets.Fire_Imps", vec![ignite]),
+            ),
+        ];
+        for (path, value) in &files {
+            let file = tree_path.join(path);
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(&file, value.to_string()).unwrap();
+        }
+
+        // the bundle keeps the powers apart from their sets and has no links
+        let mut fire_blast = make_power_set("Ranged.Fire_Blast", vec![]);
+        fire_blast["ordered_power_names"] = json!(["Ranged.Fire_Blast.Flares"]);
+        let bundle = json!({
+            "issue": "Test",
+            "root": [{ "name": "Ranged" }],
+            "archetypes": { "Class_Blaster": { "name": "Class_Blaster" } },
+            "power_categories": {
+                "Ranged": { "name": "Ranged", "power_sets": [{ "name": "Ranged.Fire_Blast" }] },
+            },
+            "power_sets": { "Ranged.Fire_Blast": fire_blast },
+            "powers": { "Ranged.Fire_Blast.Flares": flares },
+        });
+        let bundle_file = base_path.join("bundle.json.gz");
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bundle.to_string().as_bytes()).unwrap();
+        fs::write(&bundle_file, encoder.finish().unwrap()).unwrap();
+
+        let tree = Dictionary::load_tree(&tree_path);
+        let bundle = Dictionary::load_bundle(&bundle_file);
+        let _ = fs::remove_dir_all(&base_path);
+
+        let tree = tree.unwrap();
+        assert_eq!(tree.archetypes.len(), 2);
+        assert_eq!(
+            tree.resolve("archetypes/index.json"),
+            Some(Target::Archetypes)
+        );
+        assert_eq!(
+            tree.resolve("ranged/index.json"),
+            Some(Target::PowerCategory(0))
+        );
+        assert_eq!(
+            tree.resolve("../../ranged/fire-blast/index.json"),
+            Some(Target::PowerSet(0))
+        );
+        // pets aren't in the root index, but flares links to them
+        let ignite = tree.power("pets.fire_imps.ignite").unwrap();
+        let pets = tree.power_set_of(ignite).unwrap();
+        assert!(tree.power_category_of(pets).is_some());
+        let flares = tree.power("Ranged.Fire_Blast.Flares").unwrap();
+        let granted_by: Vec<_> = tree.references_to(flares).collect();
+        assert_eq!(granted_by.len(), 1);
+        assert_eq!(granted_by[0].0, "GrantedBy");
+
+        let bundle = bundle.unwrap();
+        assert_eq!(bundle.archetypes.len(), 1);
+        assert!(bundle.resolve("ranged/index.json").is_none());
+        for dict in &[tree, bundle] {
+            assert_eq!(dict.header.issue.as_deref(), Some("Test"));
+            assert!(dict.archetype("class_blaster").is_some());
+            let category = dict.power_category("Ranged").unwrap();
+            let sets: Vec<_> = dict.power_sets_in(category).collect();
+            assert_eq!(sets.len(), 1);
+            let powers: Vec<_> = dict.powers_in(sets[0]).collect();
+            assert_eq!(powers.len(), 1);
+            assert_eq!(powers[0].effect_groups.len(), 1);
+            assert_eq!(powers[0].activate.recharge_time, 4.0);
+            assert_eq!(
+                dict.power_set_of(powers[0]).and_then(|s| s.name.as_ref()),
+                sets[0].name.as_ref()
+            );
+            assert!(dict.power_category_of(sets[0]).is_some());
+        }
+    }
+}
@@ -0,0 +1,538 @@
+//! Owned, deserializable mirrors of the structs in `output::structs`.
+//!
+//! Fields that can be left out of the output are defaulted, so the same types can read both the
+//! hierarchical .json files and the bundle (which has no `url` fields and no per-file headers).
+
+use crate::NameKey;
+use serde::{Deserialize, Deserializer};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Common fields found at the top of each .json file.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Header {
+    pub issue: Option<String>,
+    pub source: Option<String>,
+    pub extract_date: Option<String>,
+}
+
+/// The root index, `/index.json`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Root {
+    #[serde(flatten)]
+    pub header: Header,
+    /// URL of the archetypes file.
+    #[serde(default)]
+    pub archetypes: String,
+    pub power_categories: Vec<RootPowerCategory>,
+}
+
+/// A power category listed in the root index.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RootPowerCategory {
+    pub name: Option<NameKey>,
+    pub display_name: Option<String>,
+    pub archetype: Option<Archetype>,
+    #[serde(default)]
+    pub url: String,
+}
+
+/// The archetypes file, `/archetypes/index.json`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Archetypes {
+    #[serde(flatten)]
+    pub header: Header,
+    pub archetypes: Vec<Archetype>,
+}
+
+/// An archetype. The fields after `primary_or_secondary` are only found in the archetypes file.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Archetype {
+    pub name: Option<String>,
+    pub display_name: Option<String>,
+    pub icon: Option<String>,
+    pub primary_or_secondary: Option<String>,
+    pub display_help: Option<String>,
+    pub display_short_help: Option<String>,
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    #[serde(default)]
+    pub restrictions: Vec<String>,
+    #[serde(default)]
+    pub level_up_respecs: Vec<i32>,
+    pub primary_category: Option<NameKey>,
+    pub secondary_category: Option<NameKey>,
+}
+
+/// A power category, `/(power category name)/index.json`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct PowerCategory {
+    #[serde(flatten)]
+    pub header: Header,
+    pub name: Option<NameKey>,
+    pub archetype: Option<Archetype>,
+    pub power_sets: Vec<PowerSetLink>,
+}
+
+/// A power set listed in a power category.
+#[derive(Clone, Debug, Deserialize)]
+pub struct PowerSetLink {
+    pub name: Option<NameKey>,
+    pub display_name: Option<String>,
+    pub url: Option<String>,
+}
+
+/// A power set, `/(power category name)/(power set name)/index.json`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct PowerSet {
+    #[serde(flatten)]
+    pub header: Header,
+    pub name: Option<NameKey>,
+    pub display_name: Option<String>,
+    pub display_help: Option<String>,
+    pub icon: Option<String>,
+    pub specialize_at_level: Option<i32>,
+    pub specialize_requires: Option<String>,
+    pub show_in_inventory: Option<String>,
+    pub show_in_power_management: bool,
+    pub show_in_power_info: bool,
+    pub set_buy_requires: Option<String>,
+    pub display_set_buy_requires_failed: Option<String>,
+    pub ordered_power_names: Vec<NameKey>,
+    #[serde(default)]
+    pub customization_themes: Vec<CustomizationTheme>,
+    /// The powers in the set. Empty when read from the bundle, which keeps powers separately.
+    #[serde(default)]
+    pub powers: Vec<Power>,
+}
+
+/// A power customization theme offered by the powers in a power set.
+#[derive(Clone, Debug, Deserialize)]
+pub struct CustomizationTheme {
+    pub token: Option<String>,
+    pub display_name: Option<String>,
+    pub category: Option<String>,
+    pub powers: Vec<NameKey>,
+}
+
+/// A power, contained in a power set.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Power {
+    pub name: Option<NameKey>,
+    pub display_name: Option<String>,
+    pub icon: Option<String>,
+    pub display_help: Option<String>,
+    pub display_short_help: Option<String>,
+    #[serde(default)]
+    pub display_info: BTreeMap<String, String>,
+    pub requires: Option<String>,
+    #[serde(default)]
+    pub attack_types: Vec<Option<String>>,
+    #[serde(default)]
+    pub enhancements_allowed: Vec<String>,
+    #[serde(default)]
+    pub enhancement_set_categories_allowed: Vec<String>,
+    pub available_at_level: i32,
+    pub auto_issue: bool,
+    pub power_type: Option<String>,
+    pub accuracy: f32,
+    pub effect_area: EffectArea,
+    #[serde(default)]
+    pub target_type_tags: Vec<String>,
+    #[serde(default)]
+    pub target_type_secondary_tags: Vec<String>,
+    pub display_target_type: Option<String>,
+    pub display_target_type_secondary: Option<String>,
+    #[serde(default)]
+    pub target_auto_hit_tags: Vec<Vec<String>>,
+    #[serde(default)]
+    pub display_target_auto_hit: Vec<String>,
+    pub requires_line_of_sight: bool,
+    #[serde(default)]
+    pub modes_required: Vec<String>,
+    #[serde(default)]
+    pub modes_disallowed: Vec<String>,
+    #[serde(default)]
+    pub status_interaction: StatusOptions,
+    pub activate: Activation,
+    #[serde(default)]
+    pub usage: Usage,
+    pub fx: Option<PowerFX>,
+    #[serde(default)]
+    pub custom_fx: Vec<CustomFX>,
+    pub combat_text: Option<CombatText>,
+    pub effect_groups: Vec<EffectGroup>,
+    #[serde(default)]
+    pub redirects: Vec<PowerRedirect>,
+    #[serde(default)]
+    pub referenced_by: Vec<PowerReference>,
+}
+
+/// Status effects a power can be used through.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct StatusOptions {
+    pub cast_through: Vec<String>,
+    pub toggle_ignores: Vec<String>,
+}
+
+/// The area a power affects.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct EffectArea {
+    pub area: Option<String>,
+    pub max_targets_hit: i32,
+    pub max_targets_expression: Option<String>,
+    pub radius_feet: f32,
+    pub jump_distance_feet: f32,
+    pub arc_degrees: f32,
+    pub chain_delay_time: f32,
+    pub range_feet: f32,
+    pub range_feet_secondary: f32,
+}
+
+/// Activation and recharge times and costs.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Activation {
+    pub cast_time: f32,
+    pub effective_cast_time: f32,
+    pub animation_time: f32,
+    pub animation_time_before_hit: f32,
+    pub recharge_time: f32,
+    pub interrupt_time: f32,
+    pub auto_cast_interval: f32,
+    pub endurance_cost: f32,
+}
+
+/// Limits on how often or how long a power can be used.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Usage {
+    pub remove_on_limit: bool,
+    pub extend_on_additional_grant: bool,
+    pub charges: i32,
+    pub max_charges_on_extend: Option<i32>,
+    pub toggle_usage_time: f32,
+    pub toggle_max_usage_time_on_extend: Option<f32>,
+    pub lifetime: f32,
+    pub max_lifetime_on_extend: Option<f32>,
+    pub in_game_lifetime: f32,
+    pub max_in_game_lifetime_on_extend: Option<f32>,
+}
+
+/// Animation timing and visual effects.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct PowerFX {
+    pub source_file: Option<String>,
+    pub animation_time: f32,
+    pub time_before_hit: f32,
+    pub time_before_secondary_hit: f32,
+    pub time_before_block: f32,
+    pub initial_time_before_hit: f32,
+    pub initial_time_before_block: f32,
+    pub initial_attack_fx_delay: f32,
+    pub delayed_hit: bool,
+    pub projectile_speed: f32,
+    pub secondary_projectile_speed: f32,
+    pub activation_fx: Option<String>,
+    pub deactivation_fx: Option<String>,
+    pub wind_up_fx: Option<String>,
+    pub initial_attack_fx: Option<String>,
+    pub attack_fx: Option<String>,
+    pub secondary_attack_fx: Option<String>,
+    pub hit_fx: Option<String>,
+    pub block_fx: Option<String>,
+    pub death_fx: Option<String>,
+    pub continuing_fx: Vec<String>,
+    pub conditional_fx: Vec<String>,
+    pub mode_bits: Vec<i32>,
+    pub important: bool,
+    pub default_tint_primary: Option<String>,
+    pub default_tint_secondary: Option<String>,
+}
+
+/// An alternate animation theme for customizing a power.
+#[derive(Clone, Debug, Deserialize)]
+pub struct CustomFX {
+    pub token: Option<String>,
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub alt_themes: Vec<String>,
+    pub category: Option<String>,
+    pub palette: Option<String>,
+    /// The fields of `Power::fx` that are different for this theme.
+    pub fx_changes: Option<PowerFXChanges>,
+}
+
+/// The fields of `PowerFX` a customization theme changes. Unchanged fields are `None`, and
+/// `Some(None)` means the theme clears a value the default FX has.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct PowerFXChanges {
+    #[serde(deserialize_with = "present")]
+    pub source_file: Option<Option<String>>,
+    pub animation_time: Option<f32>,
+    pub time_before_hit: Option<f32>,
+    pub time_before_secondary_hit: Option<f32>,
+    pub time_before_block: Option<f32>,
+    pub initial_time_before_hit: Option<f32>,
+    pub initial_time_before_block: Option<f32>,
+    pub initial_attack_fx_delay: Option<f32>,
+    pub delayed_hit: Option<bool>,
+    pub projectile_speed: Option<f32>,
+    pub secondary_projectile_speed: Option<f32>,
+    #[serde(deserialize_with = "present")]
+    pub activation_fx: Option<Option<String>>,
+    #[serde(deserialize_with = "present")]
+    pub deactivation_fx: Option<Option<String>>,
+    #[serde(deserialize_with = "present")]
+    pub wind_up_fx: Option<Option<String>>,
+    #[serde(deserialize_with = "present")]
+    pub initial_attack_fx: Option<Option<String>>,
+    #[serde(deserialize_with = "present")]
+    pub attack_fx: Option<Option<String>>,
+    #[serde(deserialize_with = "present")]
+    pub secondary_attack_fx: Option<Option<String>>,
+    #[serde(deserialize_with = "present")]
+    pub hit_fx: Option<Option<String>>,
+    #[serde(deserialize_with = "present")]
+    pub block_fx: Option<Option<String>>,
+    #[serde(deserialize_with = "present")]
+    pub death_fx: Option<Option<String>>,
+    pub continuing_fx: Option<Vec<String>>,
+    pub conditional_fx: Option<Vec<String>>,
+    pub mode_bits: Option<Vec<i32>>,
+    pub important: Option<bool>,
+    #[serde(deserialize_with = "present")]
+    pub default_tint_primary: Option<Option<String>>,
+    #[serde(deserialize_with = "present")]
+    pub default_tint_secondary: Option<Option<String>>,
+}
+
+/// Combat text shown when a power hits or is rewarded.
+#[derive(Clone, Debug, Deserialize)]
+pub struct CombatText {
+    pub attacker_hit: Option<String>,
+    pub victim_hit: Option<String>,
+    pub float_rewarded: Option<String>,
+}
+
+/// Another power that's used instead of this one when its requirements are met.
+#[derive(Clone, Debug, Deserialize)]
+pub struct PowerRedirect {
+    pub name: Option<NameKey>,
+    pub fallback: bool,
+    pub requires: Option<String>,
+    pub url: Option<String>,
+}
+
+/// Another power that refers to this one.
+#[derive(Clone, Debug, Deserialize)]
+pub struct PowerReference {
+    pub relation: String,
+    pub name: NameKey,
+    pub url: Option<String>,
+}
+
+/// A group of effects that are applied together.
+#[derive(Clone, Debug, Deserialize)]
+pub struct EffectGroup {
+    pub pve_or_pvp: Option<String>,
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+    pub visible_in_info_window: bool,
+    pub chance_percent: f32,
+    #[serde(default)]
+    pub procs_per_minute: f32,
+    pub proc_chance: Option<ProcChance>,
+    #[serde(default)]
+    pub after_delay_seconds: f32,
+    #[serde(default)]
+    pub requires: Vec<String>,
+    #[serde(default)]
+    pub flags: Vec<String>,
+    pub effects: Vec<AttribMod>,
+    #[serde(default)]
+    pub child_effect_groups: Vec<EffectGroup>,
+}
+
+/// The chance of a proc effect group, after accounting for recharge and area.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ProcChance {
+    pub chance_percent: f32,
+    pub min_chance_percent: f32,
+    pub max_chance_percent: f32,
+    pub area_factor: f32,
+}
+
+/// An attribute modifier.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AttribMod {
+    pub attributes: Vec<String>,
+    pub applies_to: Option<String>,
+    pub application_type: Option<String>,
+    pub tick_chance_percent: Option<f32>,
+    pub target_type: Option<String>,
+    pub magnitude: Option<f32>,
+    pub magnitude_expression: Option<String>,
+    pub duration: Option<String>,
+    pub duration_seconds: Option<f32>,
+    pub duration_expression: Option<String>,
+    #[serde(default)]
+    pub after_delay_seconds: f32,
+    #[serde(default)]
+    pub continuous_apply_seconds: f32,
+    pub ticks: Option<i32>,
+    #[serde(default)]
+    pub flags: Vec<String>,
+    pub parameter: Option<AttribModParam>,
+    pub stacking: Option<Stacking>,
+    #[serde(default)]
+    pub suppress: Vec<Suppress>,
+    #[serde(default)]
+    pub cancel_events: Vec<String>,
+    pub messages: Option<AttribModMessages>,
+    #[serde(default)]
+    pub scaled: Vec<AttribModScaled>,
+}
+
+/// Combat text and floaters shown when an attribute modifier goes off.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AttribModMessages {
+    pub attacker_hit: Option<String>,
+    pub victim_hit: Option<String>,
+    pub float: Option<String>,
+    pub defense_float: Option<String>,
+}
+
+/// Keeps an attribute modifier from working for a while after an event.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Suppress {
+    pub event: Option<String>,
+    pub seconds: u32,
+    pub always: bool,
+}
+
+/// How an attribute modifier stacks with itself.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Stacking {
+    pub behavior: String,
+    pub by_caster: bool,
+    pub limit: Option<i32>,
+    pub key: Option<String>,
+}
+
+/// The value of an attribute modifier for one archetype.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AttribModScaled {
+    pub archetype: Option<String>,
+    #[serde(flatten)]
+    pub scaled_effect: ScaledUnit,
+    #[serde(default)]
+    pub average: f32,
+    #[serde(default)]
+    pub per_activation: f32,
+    #[serde(default)]
+    pub per_cast_cycle: f32,
+    #[serde(default)]
+    pub display_info: Vec<String>,
+    pub base_value: f32,
+    pub scale: f32,
+}
+
+/// The kinds of scaled values.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScaledUnit {
+    Damage(f32),
+    Healing(f32),
+    Percent(f32),
+    DurationSeconds(f32),
+    Magnitude(f32),
+    Value(f32),
+    Distance(f32),
+}
+
+/// Extra information for attribute modifiers that do more than change a number.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttribModParam {
+    Costume {
+        costume_name: Option<String>,
+    },
+    Effect {
+        tags: Vec<String>,
+    },
+    CreateEntity {
+        name: Option<NameKey>,
+        display_name: Option<String>,
+        #[serde(default)]
+        powers: Vec<AttribModParamPowerRef>,
+        #[serde(default)]
+        power_names: Vec<NameKey>,
+        #[serde(default)]
+        redirects: Vec<AttribModParamPowerRef>,
+    },
+    Phase {
+        exclusive_vision_phase: i32,
+        combat_phases: Vec<i32>,
+        vision_phases: Vec<i32>,
+    },
+    Power {
+        count: Option<i32>,
+        powers: Vec<AttribModParamPowerRef>,
+    },
+    Reward {
+        rewards: Vec<String>,
+    },
+    Teleport {
+        destination: Option<String>,
+    },
+    Token {
+        tokens: Vec<String>,
+    },
+    Behavior {
+        behaviors: Vec<String>,
+    },
+    ScriptValue {
+        values: Vec<AttribModParamScriptValue>,
+    },
+}
+
+/// A power referred to by an attribute modifier parameter.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AttribModParamPowerRef {
+    pub name: Option<NameKey>,
+    pub display_name: Option<String>,
+    pub url: Option<String>,
+}
+
+/// A script value set by an attribute modifier.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AttribModParamScriptValue {
+    pub id: Option<String>,
+    pub value: Option<String>,
+}
+
+/// The whole dictionary in one file, as written by the bundle output.
+#[derive(Debug, Deserialize)]
+pub struct Bundle {
+    #[serde(flatten)]
+    pub header: Header,
+    pub root: Vec<RootPowerCategory>,
+    pub archetypes: BTreeMap<String, Archetype>,
+    pub power_categories: BTreeMap<String, PowerCategory>,
+    pub power_sets: BTreeMap<String, PowerSet>,
+    pub powers: BTreeMap<String, Power>,
+}
+
+/// Reads a field that's present as `Some`, even if it's `null`, so a `null` field can be told
+/// apart from a missing one (which is `None` through `#[serde(default)]`).
+fn present<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
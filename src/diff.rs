@@ -0,0 +1,668 @@
+use crate::output::structs::{PowerCategoryOutput, PowerSetOutput};
+use crate::output::JSON_FILE;
+use crate::structs::config::PowersConfig;
+use crate::structs::PowersDictionary;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::io::prelude::*;
+use std::io::{BufReader, Result};
+use std::path::Path;
+
+/// Default names for the diff files.
+const DIFF_FILE: &'static str = "diff.json";
+const CHANGELOG_FILE: &'static str = "changelog.md";
+
+/// Fields that describe the extraction rather than the data, or that depend on the config used
+/// to write the output, so they aren't compared.
+const IGNORED_FIELDS: &[&str] = &["issue", "source", "extract_date", "url"];
+
+/// Fields that uniquely identify the objects in an array, in order of preference. Arrays of
+/// objects with one of these are compared by key instead of by position.
+const ARRAY_KEYS: &[&str] = &["name", "archetype"];
+
+/// Everything in a powers dictionary that can be compared, keyed by lowercase full name.
+#[derive(Debug, Default)]
+pub struct DictionarySnapshot {
+    /// Describes where the dictionary came from (e.g. the issue).
+    pub label: String,
+    pub power_categories: BTreeMap<String, Value>,
+    pub power_sets: BTreeMap<String, Value>,
+    pub powers: BTreeMap<String, Value>,
+}
+
+impl DictionarySnapshot {
+    /// Creates a snapshot from a loaded powers dictionary.
+    ///
+    /// # Arguments:
+    ///
+    /// * `powers_dict` - A `PowersDictionary` containing a hierarchy of categories, power sets, and powers.
+    /// * `label` - Describes where the dictionary came from.
+    /// * `config` - Configuration information.
+    ///
+    /// # Returns:
+    ///
+    /// A `DictionarySnapshot` containing the same data that would be written to the .json files.
+    pub fn from_powers_dictionary(
+        powers_dict: &PowersDictionary,
+        label: String,
+        config: &PowersConfig,
+    ) -> Result<Self> {
+        let mut snapshot = DictionarySnapshot {
+            label,
+            ..Default::default()
+        };
+        for category in &powers_dict.power_categories {
+            if !category.include_in_output || category.pch_name.is_none() {
+                continue;
+            }
+            let pcat = PowerCategoryOutput::from_power_category(category, config);
+            snapshot.add_power_category(serde_json::to_value(&pcat)?);
+            for set in &category.pp_power_sets {
+                if set.include_in_output {
+                    let pset =
+                        PowerSetOutput::from_base_power_set(set, &powers_dict.attrib_names, config);
+                    snapshot.add_power_set(serde_json::to_value(&pset)?);
+                }
+            }
+        }
+        Ok(snapshot)
+    }
+
+    /// Creates a snapshot from a tree of .json files previously written by this program.
+    ///
+    /// # Arguments:
+    ///
+    /// * `path` - The root of the output tree (the directory containing the root `index.json`).
+    ///
+    /// # Returns:
+    ///
+    /// If successful, a `DictionarySnapshot`. Otherwise, a `std::io::Error`.
+    pub fn from_output_tree(path: &Path) -> Result<Self> {
+        let mut snapshot = DictionarySnapshot {
+            label: path.display().to_string(),
+            ..Default::default()
+        };
+        let root = read_json(&path.join(JSON_FILE))?;
+        if let Some(Value::String(issue)) = root.get("issue") {
+            snapshot.label = issue.clone();
+        }
+        snapshot.add_output_dir(path)?;
+        Ok(snapshot)
+    }
+
+    /// Tests if `path` looks like an output tree rather than a directory of .bin files.
+    pub fn is_output_tree(path: &Path) -> bool {
+        path.join(JSON_FILE).is_file()
+    }
+
+    /// Recursively adds the categories and power sets in the subdirectories of `path`.
+    fn add_output_dir(&mut self, path: &Path) -> Result<()> {
+        let mut dirs: Vec<_> = fs::read_dir(path)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect();
+        dirs.sort();
+        for dir in dirs {
+            let json_file = dir.join(JSON_FILE);
+            if json_file.is_file() {
+                let value = read_json(&json_file)?;
+                if value.get("powers").is_some() {
+                    self.add_power_set(value);
+                } else if value.get("power_sets").is_some() {
+                    self.add_power_category(value);
+                }
+            }
+            self.add_output_dir(&dir)?;
+        }
+        Ok(())
+    }
+
+    fn add_power_category(&mut self, pcat: Value) {
+        if let Some(key) = get_key(&pcat) {
+            self.power_categories.insert(key, pcat);
+        }
+    }
+
+    /// Adds a power set, splitting its powers out so they're compared individually.
+    fn add_power_set(&mut self, mut pset: Value) {
+        if let Some(Value::Array(powers)) = pset.as_object_mut().and_then(|o| o.remove("powers")) {
+            for power in powers {
+                if let Some(key) = get_key(&power) {
+                    self.powers.insert(key, power);
+                }
+            }
+        }
+        if let Some(key) = get_key(&pset) {
+            self.power_sets.insert(key, pset);
+        }
+    }
+}
+
+/// Reads a .json file.
+fn read_json(path: &Path) -> Result<Value> {
+    let file = fs::File::open(path)?;
+    Ok(serde_json::from_reader(BufReader::new(file))?)
+}
+
+/// Gets the lowercase name of a category, power set, or power to use as a key.
+fn get_key(value: &Value) -> Option<String> {
+    value
+        .get("name")
+        .and_then(|name| name.as_str())
+        .map(|name| name.to_ascii_lowercase())
+}
+
+/// A single field that changed. Fields in nested objects are separated with `.` and array
+/// elements are identified by `[key]` or `[index]`.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    /// `None` if the field was added.
+    pub old: Option<Value>,
+    /// `None` if the field was removed.
+    pub new: Option<Value>,
+}
+
+/// A category, power set, or power that exists in both dictionaries but changed.
+#[derive(Debug, Serialize)]
+pub struct ChangedItem {
+    pub name: String,
+    pub display_name: Option<String>,
+    pub changes: Vec<FieldChange>,
+}
+
+/// An added or removed category, power set, or power.
+#[derive(Debug, Serialize)]
+pub struct Item {
+    pub name: String,
+    pub display_name: Option<String>,
+}
+
+/// The differences for one kind of item.
+#[derive(Debug, Default, Serialize)]
+pub struct SectionDiff {
+    pub added: Vec<Item>,
+    pub removed: Vec<Item>,
+    pub changed: Vec<ChangedItem>,
+}
+
+impl SectionDiff {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// The differences between two powers dictionaries. Displays as a Markdown changelog.
+#[derive(Debug, Serialize)]
+pub struct DictionaryDiff {
+    pub old: String,
+    pub new: String,
+    pub power_categories: SectionDiff,
+    pub power_sets: SectionDiff,
+    pub powers: SectionDiff,
+}
+
+impl DictionaryDiff {
+    /// Tests if nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.power_categories.is_empty() && self.power_sets.is_empty() && self.powers.is_empty()
+    }
+}
+
+impl fmt::Display for DictionaryDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_item_name(
+            f: &mut fmt::Formatter<'_>,
+            name: &str,
+            display_name: &Option<String>,
+        ) -> fmt::Result {
+            match display_name {
+                Some(display_name) => write!(f, "{} (`{}`)", display_name, name),
+                None => write!(f, "`{}`", name),
+            }
+        }
+        fn write_value(f: &mut fmt::Formatter<'_>, value: &Option<Value>) -> fmt::Result {
+            match value {
+                Some(value) => write!(f, "`{}`", value),
+                None => write!(f, "*none*"),
+            }
+        }
+        fn write_section(
+            f: &mut fmt::Formatter<'_>,
+            title: &str,
+            section: &SectionDiff,
+        ) -> fmt::Result {
+            if section.is_empty() {
+                return Ok(());
+            }
+            writeln!(f, "## {}", title)?;
+            writeln!(f)?;
+            if !section.added.is_empty() {
+                writeln!(f, "### Added")?;
+                writeln!(f)?;
+                for item in &section.added {
+                    write!(f, "* ")?;
+                    write_item_name(f, &item.name, &item.display_name)?;
+                    writeln!(f)?;
+                }
+                writeln!(f)?;
+            }
+            if !section.removed.is_empty() {
+                writeln!(f, "### Removed")?;
+                writeln!(f)?;
+                for item in &section.removed {
+                    write!(f, "* ")?;
+                    write_item_name(f, &item.name, &item.display_name)?;
+                    writeln!(f)?;
+                }
+                writeln!(f)?;
+            }
+            if !section.changed.is_empty() {
+                writeln!(f, "### Changed")?;
+                writeln!(f)?;
+                for item in &section.changed {
+                    write!(f, "#### ")?;
+                    write_item_name(f, &item.name, &item.display_name)?;
+                    writeln!(f)?;
+                    writeln!(f)?;
+                    for change in &item.changes {
+                        write!(f, "* `{}`: ", change.field)?;
+                        write_value(f, &change.old)?;
+                        write!(f, " → ")?;
+                        write_value(f, &change.new)?;
+                        writeln!(f)?;
+                    }
+                    writeln!(f)?;
+                }
+            }
+            Ok(())
+        }
+
+        writeln!(f, "# Changes from {} to {}", self.old, self.new)?;
+        writeln!(f)?;
+        if self.is_empty() {
+            writeln!(f, "No changes.")?;
+            return Ok(());
+        }
+        write_section(f, "Power Categories", &self.power_categories)?;
+        write_section(f, "Power Sets", &self.power_sets)?;
+        write_section(f, "Powers", &self.powers)?;
+        Ok(())
+    }
+}
+
+/// Compares two powers dictionaries.
+///
+/// # Arguments:
+///
+/// * `old` - The snapshot of the older dictionary.
+/// * `new` - The snapshot of the newer dictionary.
+///
+/// # Returns:
+///
+/// A `DictionaryDiff` with the added, removed, and changed categories, power sets, and powers.
+///
+/// # Notes:
+///
+/// Fields are compared as they appear in the .json output, so scaled values are compared per
+/// archetype and effect groups are compared field by field. Arrays of objects with a `name` or
+/// `archetype` are matched by that field, other arrays of objects by position.
+pub fn diff_dictionaries(old: &DictionarySnapshot, new: &DictionarySnapshot) -> DictionaryDiff {
+    DictionaryDiff {
+        old: old.label.clone(),
+        new: new.label.clone(),
+        power_categories: diff_section(&old.power_categories, &new.power_categories),
+        power_sets: diff_section(&old.power_sets, &new.power_sets),
+        powers: diff_section(&old.powers, &new.powers),
+    }
+}
+
+/// Compares one kind of item between the dictionaries.
+fn diff_section(old: &BTreeMap<String, Value>, new: &BTreeMap<String, Value>) -> SectionDiff {
+    fn make_item(value: &Value) -> Item {
+        Item {
+            name: value["name"].as_str().unwrap_or_default().to_owned(),
+            display_name: value["display_name"].as_str().map(|s| s.to_owned()),
+        }
+    }
+    let mut section = SectionDiff::default();
+    for (key, old_value) in old {
+        match new.get(key) {
+            Some(new_value) => {
+                let mut changes = Vec::new();
+                diff_values("", old_value, new_value, &mut changes);
+                if !changes.is_empty() {
+                    let item = make_item(new_value);
+                    section.changed.push(ChangedItem {
+                        name: item.name,
+                        display_name: item.display_name,
+                        changes,
+                    });
+                }
+            }
+            None => section.removed.push(make_item(old_value)),
+        }
+    }
+    for (key, new_value) in new {
+        if !old.contains_key(key) {
+            section.added.push(make_item(new_value));
+        }
+    }
+    section
+}
+
+/// Writes a diff as .json and as a Markdown changelog.
+///
+/// # Arguments:
+///
+/// * `diff` - The differences to write.
+/// * `output_path` - Directory to write the files to. It's created if needed.
+///
+/// # Returns:
+///
+/// Nothing if the operation was successful. Otherwise, an `io::Error` containing the error information.
+pub fn write_diff(diff: &DictionaryDiff, output_path: &Path) -> Result<()> {
+    fs::create_dir_all(output_path)?;
+    let diff_file = output_path.join(DIFF_FILE);
+    println!("Writing: {} ...", diff_file.display());
+    let mut f = fs::File::create(&diff_file)?;
+    serde_json::to_writer_pretty(&mut f, diff)?;
+
+    let changelog_file = output_path.join(CHANGELOG_FILE);
+    println!("Writing: {} ...", changelog_file.display());
+    let mut f = fs::File::create(&changelog_file)?;
+    write!(f, "{}", diff)?;
+    Ok(())
+}
+
+/// Recursively compares two values, adding anything that changed to `changes`.
+fn diff_values(field: &str, old: &Value, new: &Value, changes: &mut Vec<FieldChange>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => diff_objects(field, old, new, changes),
+        (Value::Array(old_array), Value::Array(new_array)) => {
+            if let Some(key) = find_array_key(old_array, new_array) {
+                let to_map = |array: &Vec<Value>| -> Map<String, Value> {
+                    array
+                        .iter()
+                        .map(|v| (key_string(&v[key]), v.clone()))
+                        .collect()
+                };
+                diff_objects_keyed(field, &to_map(old_array), &to_map(new_array), changes);
+            } else if old_array
+                .iter()
+                .chain(new_array.iter())
+                .all(|v| v.is_object())
+            {
+                for i in 0..old_array.len().max(new_array.len()) {
+                    let element = format!("{}[{}]", field, i);
+                    match (old_array.get(i), new_array.get(i)) {
+                        (Some(o), Some(n)) => diff_values(&element, o, n, changes),
+                        (o, n) => changes.push(FieldChange {
+                            field: element,
+                            old: o.cloned(),
+                            new: n.cloned(),
+                        }),
+                    }
+                }
+            } else if old_array != new_array {
+                changes.push(FieldChange {
+                    field: field.to_owned(),
+                    old: Some(old.clone()),
+                    new: Some(new.clone()),
+                });
+            }
+        }
+        _ => {
+            if old != new {
+                changes.push(FieldChange {
+                    field: field.to_owned(),
+                    old: Some(old.clone()),
+                    new: Some(new.clone()),
+                });
+            }
+        }
+    }
+}
+
+/// Compares the fields of two objects.
+fn diff_objects(
+    field: &str,
+    old: &Map<String, Value>,
+    new: &Map<String, Value>,
+    changes: &mut Vec<FieldChange>,
+) {
+    let keys: BTreeSet<_> = old
+        .keys()
+        .chain(new.keys())
+        .filter(|key| !IGNORED_FIELDS.contains(&&key[..]))
+        .collect();
+    for key in keys {
+        let child = if field.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", field, key)
+        };
+        diff_entry(child, old.get(key), new.get(key), changes);
+    }
+}
+
+/// Compares the elements of two arrays that were keyed by `find_array_key`.
+fn diff_objects_keyed(
+    field: &str,
+    old: &Map<String, Value>,
+    new: &Map<String, Value>,
+    changes: &mut Vec<FieldChange>,
+) {
+    let keys: BTreeSet<_> = old.keys().chain(new.keys()).collect();
+    for key in keys {
+        diff_entry(
+            format!("{}[{}]", field, key),
+            old.get(key),
+            new.get(key),
+            changes,
+        );
+    }
+}
+
+/// Compares a field that may be missing on either side.
+fn diff_entry(
+    field: String,
+    old: Option<&Value>,
+    new: Option<&Value>,
+    changes: &mut Vec<FieldChange>,
+) {
+    match (old, new) {
+        (Some(o), Some(n)) => diff_values(&field, o, n, changes),
+        (o, n) => changes.push(FieldChange {
+            field,
+            old: o.cloned(),
+            new: n.cloned(),
+        }),
+    }
+}
+
+/// Finds a field that uniquely identifies every element of both arrays.
+fn find_array_key(old: &Vec<Value>, new: &Vec<Value>) -> Option<&'static str> {
+    ARRAY_KEYS.iter().copied().find(|key| {
+        [old, new].iter().all(|array| {
+            let mut seen = BTreeSet::new();
+            array.iter().all(|v| match v.get(*key) {
+                Some(k) if !k.is_null() && !k.is_array() && !k.is_object() => {
+                    seen.insert(key_string(k))
+                }
+                _ => false,
+            })
+        }) && !(old.is_empty() && new.is_empty())
+    })
+}
+
+/// Converts a key value to a string without the quotes JSON strings would have.
+fn key_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::tests::{make_powers_dict, TestOutput};
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_values() {
+        let old = json!({
+            "name": "Blaster_Ranged.Fire_Blast.Flares",
+            "issue": "I26",
+            "activate": { "recharge_time": 4.0, "endurance_cost": 5.2 },
+            "attack_types": ["Ranged", "Fire"],
+            "effect_groups": [{
+                "effects": [{
+                    "scaled": [
+                        { "archetype": "Blaster", "average": 62.56 },
+                        { "archetype": "Corruptor", "average": 46.92 },
+                    ]
+                }]
+            }],
+        });
+        let new = json!({
+            "name": "Blaster_Ranged.Fire_Blast.Flares",
+            "issue": "I27",
+            "activate": { "recharge_time": 3.0, "endurance_cost": 5.2 },
+            "attack_types": ["Ranged", "Fire"],
+            "effect_groups": [{
+                "effects": [{
+                    "scaled": [
+                        { "archetype": "Corruptor", "average": 46.92 },
+                        { "archetype": "Blaster", "average": 70.0 },
+                        { "archetype": "Defender", "average": 40.0 },
+                    ]
+                }]
+            }],
+        });
+        let mut changes = Vec::new();
+        diff_values("", &old, &new, &mut changes);
+        assert_eq!(
+            changes,
+            vec![
+                FieldChange {
+                    field: String::from("activate.recharge_time"),
+                    old: Some(json!(4.0)),
+                    new: Some(json!(3.0)),
+                },
+                FieldChange {
+                    field: String::from("effect_groups[0].effects[0].scaled[Blaster].average"),
+                    old: Some(json!(62.56)),
+                    new: Some(json!(70.0)),
+                },
+                FieldChange {
+                    field: String::from("effect_groups[0].effects[0].scaled[Defender]"),
+                    old: None,
+                    new: Some(json!({ "archetype": "Defender", "average": 40.0 })),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_changelog() {
+        let mut old = DictionarySnapshot {
+            label: String::from("I26"),
+            ..Default::default()
+        };
+        old.add_power_set(json!({
+            "name": "Ranged.Fire_Blast",
+            "display_name": "Fire Blast",
+            "powers": [
+                { "name": "Ranged.Fire_Blast.Flares", "display_name": "Flares", "accuracy": 1.0 },
+                { "name": "Ranged.Fire_Blast.Blaze", "display_name": "Blaze" },
+            ],
+        }));
+        let mut new = DictionarySnapshot {
+            label: String::from("I27"),
+            ..Default::default()
+        };
+        new.add_power_set(json!({
+            "name": "Ranged.Fire_Blast",
+            "display_name": "Fire Blast",
+            "powers": [
+                { "name": "Ranged.Fire_Blast.Flares", "display_name": "Flares", "accuracy": 1.2 },
+                { "name": "Ranged.Fire_Blast.Rain_of_Fire" },
+            ],
+        }));
+        let diff = diff_dictionaries(&old, &new);
+        assert!(diff.power_categories.is_empty());
+        assert!(diff.power_sets.is_empty());
+        assert_eq!(
+            diff.to_string(),
+            "# Changes from I26 to I27\n\
+             \n\
+             ## Powers\n\
+             \n\
+             ### Added\n\
+             \n\
+             * `Ranged.Fire_Blast.Rain_of_Fire`\n\
+             \n\
+             ### Removed\n\
+             \n\
+             * Blaze (`Ranged.Fire_Blast.Blaze`)\n\
+             \n\
+             ### Changed\n\
+             \n\
+             #### Flares (`Ranged.Fire_Blast.Flares`)\n\
+             \n\
+             * `accuracy`: `1.0` → `1.2`\n\
+             \n"
+        );
+
+        let unchanged = diff_dictionaries(&new, &new);
+        assert!(unchanged.is_empty());
+        assert_eq!(
+            unchanged.to_string(),
+            "# Changes from I27 to I27\n\nNo changes.\n"
+        );
+    }
+
+    #[test]
+    fn test_from_output_tree() {
+        let output = TestOutput::new("diff");
+        output.write(make_powers_dict()).unwrap();
+        assert!(DictionarySnapshot::is_output_tree(&output.path));
+        let diff_path = output.path.join("diff");
+        assert!(!DictionarySnapshot::is_output_tree(&diff_path));
+
+        let from_tree = DictionarySnapshot::from_output_tree(&output.path).unwrap();
+        let from_dict = DictionarySnapshot::from_powers_dictionary(
+            &make_powers_dict(),
+            String::from("dict"),
+            &output.config,
+        )
+        .unwrap();
+        assert_eq!(
+            from_tree.power_categories.keys().collect::<Vec<_>>(),
+            vec!["ranged"]
+        );
+        assert_eq!(
+            from_tree.power_sets.keys().collect::<Vec<_>>(),
+            vec!["ranged.fire_blast"]
+        );
+        assert_eq!(
+            from_tree.powers.keys().collect::<Vec<_>>(),
+            vec!["ranged.fire_blast.flares"]
+        );
+        let diff = diff_dictionaries(&from_tree, &from_dict);
+        assert!(diff.is_empty(), "{}", diff);
+
+        // the diff goes where it's told to, not into the current directory
+        write_diff(&diff, &diff_path).unwrap();
+        let changelog = fs::read_to_string(diff_path.join(CHANGELOG_FILE)).unwrap();
+        assert_eq!(changelog, diff.to_string());
+        let diff_json = read_json(&diff_path.join(DIFF_FILE)).unwrap();
+        assert_eq!(diff_json["new"], json!("dict"));
+    }
+}
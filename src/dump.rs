@@ -0,0 +1,98 @@
+use crate::load::{self, RawBins};
+use crate::structs::Keyed;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Writes the contents of each .bin file to a .json file of the same name in `output_path`.
+///
+/// # Arguments:
+///
+/// * `bins` - The parsed .bin files.
+/// * `output_path` - Directory to write the .json files to. It's created if needed.
+///
+/// # Returns:
+///
+/// Nothing if successful. Otherwise, an `io::Error` containing the error information.
+///
+/// # Notes:
+///
+/// Every field read from the bins is written out as-is, without the filtering and conversion
+/// done for the regular output. The bins are read into maps keyed by name, so their entries are
+/// sorted by name rather than kept in file order. Fields that are only filled in when the bins are
+/// merged (e.g. `include_in_output`, `archetypes`, and the links between categories, power sets, and
+/// powers) are `#[serde(skip)]` and left out.
+pub fn write_raw_bins(bins: &RawBins, output_path: &Path) -> io::Result<()> {
+    fs::create_dir_all(output_path)?;
+    write_bin(output_path, load::MESSAGESTORE_BIN, &bins.messages)?;
+    write_bin(output_path, load::ATTRIB_NAMES_BIN, &bins.attrib_names)?;
+    write_bin(output_path, load::CLASSES_BIN, &sorted(&bins.archetypes))?;
+    write_bin(output_path, load::BOOST_SETS_BIN, &sorted(&bins.boost_sets))?;
+    write_bin(
+        output_path,
+        load::VILLAIN_CLASSES_BIN,
+        &sorted(&bins.villain_archetypes),
+    )?;
+    write_bin(output_path, load::VILLAIN_DEF_BIN, &sorted(&bins.villains))?;
+    write_bin(
+        output_path,
+        load::POWER_CATEGORIES_BIN,
+        &sorted(&bins.power_categories),
+    )?;
+    write_bin(output_path, load::POWER_SETS_BIN, &sorted(&bins.power_sets))?;
+    write_bin(output_path, load::POWERS_BIN, &sorted(&bins.powers))
+}
+
+/// Writes `value` to the .json file named after `bin_name`.
+fn write_bin<T: Serialize>(output_path: &Path, bin_name: &str, value: &T) -> io::Result<()> {
+    let path = output_path.join(Path::new(bin_name).with_extension("json"));
+    println!("Writing: {} ...", path.display());
+    let mut writer = BufWriter::new(File::create(&path)?);
+    serde_json::to_writer_pretty(&mut writer, value)?;
+    writer.flush()
+}
+
+/// Orders the entries of `keyed` by name so the dumps can be compared between patches.
+pub fn sorted<T>(keyed: &Keyed<T>) -> BTreeMap<&str, &T> {
+    keyed
+        .iter()
+        .map(|(name, value)| (name.get(), value.as_ref()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::*;
+    use std::process;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_write_raw_bins() {
+        let mut power = BasePower::new();
+        power.pch_full_name = Some(NameKey::new("Ranged.Fire_Blast.Flares"));
+        power.pi_unknown = vec![1, 2];
+        let mut powers = Keyed::new();
+        powers.insert(NameKey::new("Ranged.Fire_Blast.Flares"), Rc::new(power));
+        let bins = RawBins {
+            powers,
+            ..Default::default()
+        };
+        let output_path = std::env::temp_dir().join(format!("powers-test-dump-{}", process::id()));
+        write_raw_bins(&bins, &output_path).unwrap();
+
+        let powers: serde_json::Value =
+            serde_json::from_slice(&fs::read(output_path.join("powers.json")).unwrap()).unwrap();
+        let flares = &powers["Ranged.Fire_Blast.Flares"];
+        assert_eq!(flares["pch_full_name"], "Ranged.Fire_Blast.Flares");
+        assert_eq!(flares["pi_unknown"], serde_json::json!([1, 2]));
+        // merge-time fields aren't in the bins
+        assert!(flares.get("include_in_output").is_none());
+        assert!(flares.get("archetypes").is_none());
+        assert!(flares.get("referenced_by").is_none());
+        assert!(output_path.join("clientmessages-en.json").exists());
+        let _ = fs::remove_dir_all(&output_path);
+    }
+}
@@ -0,0 +1,15 @@
+//! Types for reading the .json files written by the `powers` program. See the `client` module.
+//!
+//! Only flate2, serde, and serde_json are needed for this. Turn off the default `cli` feature to
+//! leave out everything else the program depends on.
+
+extern crate flate2;
+#[cfg(feature = "schema")]
+extern crate schemars;
+extern crate serde;
+extern crate serde_json;
+
+pub mod client;
+mod namekey;
+
+pub use namekey::NameKey;
@@ -8,15 +8,15 @@ use std::rc::Rc;
 use std::time::Instant;
 
 /// Default names for the bin files.
-const ATTRIB_NAMES_BIN: &'static str = "attrib_names.bin";
-const MESSAGESTORE_BIN: &'static str = "clientmessages-en.bin";
-const BOOST_SETS_BIN: &'static str = "boostsets.bin";
-const CLASSES_BIN: &'static str = "classes.bin";
-const POWER_CATEGORIES_BIN: &'static str = "powercats.bin";
-const POWER_SETS_BIN: &'static str = "powersets.bin";
-const POWERS_BIN: &'static str = "powers.bin";
-const VILLAIN_CLASSES_BIN: &'static str = "villain_classes.bin";
-const VILLAIN_DEF_BIN: &'static str = "villaindef.bin";
+pub const ATTRIB_NAMES_BIN: &'static str = "attrib_names.bin";
+pub const MESSAGESTORE_BIN: &'static str = "clientmessages-en.bin";
+pub const BOOST_SETS_BIN: &'static str = "boostsets.bin";
+pub const CLASSES_BIN: &'static str = "classes.bin";
+pub const POWER_CATEGORIES_BIN: &'static str = "powercats.bin";
+pub const POWER_SETS_BIN: &'static str = "powersets.bin";
+pub const POWERS_BIN: &'static str = "powers.bin";
+pub const VILLAIN_CLASSES_BIN: &'static str = "villain_classes.bin";
+pub const VILLAIN_DEF_BIN: &'static str = "villaindef.bin";
 
 pub struct ErrContext {
     pub message: Cow<'static, str>,
@@ -52,7 +52,10 @@ fn match_archetypes_to_power_categories(
     config: &PowersConfig,
     power_categories: &mut Keyed<PowerCategory>,
 ) {
-    for a in archetypes.values() {
+    // sorted so the archetypes are always assigned in the same order
+    let mut archetypes: Vec<_> = archetypes.values().collect();
+    archetypes.sort_by(|a, b| a.pch_name.cmp(&b.pch_name));
+    for a in archetypes {
         if let Some(pcat) = find_power_category(power_categories, a.pch_primary_category.as_ref()) {
             println!(
                 "Matched {} to primary {}",
@@ -106,9 +109,11 @@ fn match_archetypes_to_power_categories(
 }
 
 /// Copies references to the `powers` used by `entcreate` into the param itself
-/// and marks those powers to be included in the data set.
+/// and marks those powers to be included in the data set. `source` is the power
+/// that creates the entity.
 fn copy_powers_to_entcreate(
     entcreate: &mut AttribModParam_EntCreate,
+    source: Option<&NameKey>,
     villain_archetypes: &Keyed<Archetype>,
     power_cats: &mut Keyed<PowerCategory>,
     power_sets: &mut Keyed<BasePowerSet>,
@@ -154,26 +159,45 @@ fn copy_powers_to_entcreate(
         }
         // now mark all of the powers for inclusion
         for power_name in &entcreate.power_refs {
-            mark_power_for_inclusion(power_name, &archetypes, power_cats, power_sets, powers);
+            mark_power_for_inclusion(
+                power_name,
+                &archetypes,
+                source,
+                PowerRelation::SummonedBy,
+                power_cats,
+                power_sets,
+                powers,
+            );
         }
     }
 }
 
 /// Marks references to the `powers` used by `power_param` to be included in the output.
+/// `source` is the power that grants them.
 fn mark_powers_in_power_param(
     power_param: &AttribModParam_Power,
     archetypes: &Vec<Rc<Archetype>>,
+    source: Option<&NameKey>,
     power_cats: &mut Keyed<PowerCategory>,
     power_sets: &mut Keyed<BasePowerSet>,
     powers: &mut Keyed<BasePower>,
 ) {
     // the power categories and sets are never used, everything is flattened into the power name
     for power_name in &power_param.ppch_power_names {
-        mark_power_for_inclusion(power_name, archetypes, power_cats, power_sets, powers);
+        mark_power_for_inclusion(
+            power_name,
+            archetypes,
+            source,
+            PowerRelation::GrantedBy,
+            power_cats,
+            power_sets,
+            powers,
+        );
     }
 }
 
 /// Assigns entity defs in `villains` to `powers` based on the EntCreate and Power attrib mod parameters.
+/// Also records the reverse references on the powers that are summoned, granted, or redirected to.
 fn resolve_entity_defs_and_power_grants(
     villains: &Keyed<VillainDef>,
     villain_archetypes: &Keyed<Archetype>,
@@ -192,6 +216,7 @@ fn resolve_entity_defs_and_power_grants(
         for power in (*p_powers.get()).values_mut() {
             if power.include_in_output {
                 let power = Rc::get_mut_unchecked(power);
+                let source_name = power.pch_full_name.clone();
                 // check effect groups for attrib mod params we're interested in
                 for egroup in &mut power.pp_effects {
                     let egroup = Rc::get_mut_unchecked(egroup);
@@ -206,6 +231,8 @@ fn resolve_entity_defs_and_power_grants(
                                             mark_power_for_inclusion(
                                                 redirect_def_name,
                                                 &power.archetypes,
+                                                source_name.as_ref(),
+                                                PowerRelation::RedirectedFrom,
                                                 power_cats,
                                                 power_sets,
                                                 *p_powers.get(),
@@ -218,6 +245,7 @@ fn resolve_entity_defs_and_power_grants(
                                             // copy villain's powers into the mod param
                                             copy_powers_to_entcreate(
                                                 e,
+                                                source_name.as_ref(),
                                                 &villain_archetypes,
                                                 power_cats,
                                                 power_sets,
@@ -233,6 +261,7 @@ fn resolve_entity_defs_and_power_grants(
                                     mark_powers_in_power_param(
                                         p,
                                         &power.archetypes,
+                                        source_name.as_ref(),
                                         power_cats,
                                         power_sets,
                                         *p_powers.get(),
@@ -252,10 +281,13 @@ fn resolve_entity_defs_and_power_grants(
 }
 
 /// Mark the three parts represented by `power_ref` (category, set, power) to be included
-/// in the output set.
+/// in the output set. If `referrer` is provided, the power also records a reverse reference
+/// to it using `relation`.
 fn mark_power_for_inclusion(
     power_ref: &NameKey,
     archetypes: &Vec<Rc<Archetype>>,
+    referrer: Option<&NameKey>,
+    relation: PowerRelation,
     power_cats: &mut Keyed<PowerCategory>,
     power_sets: &mut Keyed<BasePowerSet>,
     powers: &mut Keyed<BasePower>,
@@ -292,6 +324,19 @@ fn mark_power_for_inclusion(
                 power2.archetypes.push(Rc::clone(at));
             }
         }
+        // remember who linked to this power
+        if let Some(referrer) = referrer {
+            if !power2
+                .referenced_by
+                .iter()
+                .any(|r| r.relation == relation && &r.power_name == referrer)
+            {
+                power2.referenced_by.push(PowerReference {
+                    relation,
+                    power_name: referrer.clone(),
+                });
+            }
+        }
     }
 }
 
@@ -317,6 +362,8 @@ fn resolve_power_redirects(
                         mark_power_for_inclusion(
                             &power_name,
                             &power.archetypes,
+                            power.pch_full_name.as_ref(),
+                            PowerRelation::RedirectedFrom,
                             power_cats,
                             power_sets,
                             *p_powers.get(),
@@ -331,6 +378,38 @@ fn resolve_power_redirects(
     count_resolved
 }
 
+/// Follows entity defs, power grants, and redirects from the included powers until
+/// nothing new is found, marking every power they reach to be included in the output.
+fn resolve_power_references(
+    villains: &Keyed<VillainDef>,
+    villain_archetypes: &Keyed<Archetype>,
+    power_cats: &mut Keyed<PowerCategory>,
+    power_sets: &mut Keyed<BasePowerSet>,
+    powers: &mut Keyed<BasePower>,
+) {
+    loop {
+        // copy pet entity defs into powers
+        let mut count = resolve_entity_defs_and_power_grants(
+            villains,
+            villain_archetypes,
+            power_cats,
+            power_sets,
+            powers,
+        );
+        // look for redirects and make sure the referenced powers are included in the output data
+        count += resolve_power_redirects(powers, power_cats, power_sets);
+        if count == 0 {
+            break;
+        }
+    }
+
+    // archetypes copied from referencing powers arrive in whatever order the powers were resolved
+    for power in powers.values_mut() {
+        let power = unsafe { Rc::get_mut_unchecked(power) };
+        power.archetypes.sort_by(|a, b| a.pch_name.cmp(&b.pch_name));
+    }
+}
+
 fn match_enh_categories_to_powers(boost_sets: &Keyed<BoostSet>, powers: &mut Keyed<BasePower>) {
     for boost_set in boost_sets.values() {
         if let Some(category_name) = &boost_set.pch_group_name {
@@ -346,6 +425,36 @@ fn match_enh_categories_to_powers(boost_sets: &Keyed<BoostSet>, powers: &mut Key
     }
 }
 
+/// The contents of each .bin file, as parsed and before anything is merged or filtered.
+#[derive(Default)]
+pub struct RawBins {
+    pub messages: MessageStore,
+    pub attrib_names: AttribNames,
+    pub archetypes: Keyed<Archetype>,
+    pub boost_sets: Keyed<BoostSet>,
+    pub villain_archetypes: Keyed<Archetype>,
+    pub villains: Keyed<VillainDef>,
+    pub power_categories: Keyed<PowerCategory>,
+    pub power_sets: Keyed<BasePowerSet>,
+    pub powers: Keyed<BasePower>,
+}
+
+/// Read all .bin files without merging or filtering anything.
+pub fn load_raw_bins(config: &PowersConfig) -> Result<RawBins, ErrContext> {
+    let messages = read_client_messages(config)?;
+    Ok(RawBins {
+        attrib_names: read_attributes(config, &messages)?,
+        archetypes: read_classes_bin(config, &messages)?,
+        boost_sets: read_boostsets_bin(config, &messages)?,
+        villain_archetypes: read_villain_classes_bin(config, &messages)?,
+        villains: read_villaindef_bin(config, &messages)?,
+        power_categories: read_powercats_bin(config, &messages)?,
+        power_sets: read_powersets_bin(config, &messages)?,
+        powers: read_powers_bin(config, &messages)?,
+        messages,
+    })
+}
+
 /// Read all .bin files and merge them into a single powers dictionary.
 pub fn load_powers_dictionary(config: &PowersConfig) -> Result<PowersDictionary, ErrContext> {
     let begin_time = Instant::now();
@@ -399,11 +508,15 @@ pub fn load_powers_dictionary(config: &PowersConfig) -> Result<PowersDictionary,
         }
     }
 
-    // Reduce the power categories to a vector
+    // Reduce the power categories to a vector, sorted so the output is always in the same order
     let mut power_categories_returned: Vec<_> = power_categories
         .values()
         .map(|pcat| Rc::clone(pcat))
         .collect();
+    power_categories_returned.sort_by(|a, b| {
+        let a = a.pch_name.as_ref().map(|n| n.get().to_ascii_lowercase());
+        a.cmp(&b.pch_name.as_ref().map(|n| n.get().to_ascii_lowercase()))
+    });
 
     // automatically include all power sets and powers linked to the top level
     // also does a sanity check and excludes any that have no powers/power sets
@@ -426,21 +539,13 @@ pub fn load_powers_dictionary(config: &PowersConfig) -> Result<PowersDictionary,
     });
 
     println!("Resolving entity defs, power grants, and redirects ...");
-    loop {
-        // copy pet entity defs into powers
-        let mut count = resolve_entity_defs_and_power_grants(
-            &villains,
-            &villain_archetypes,
-            &mut power_categories,
-            &mut power_sets,
-            &mut powers,
-        );
-        // look for redirects and make sure the referenced powers are included in the output data
-        count += resolve_power_redirects(&mut powers, &mut power_categories, &mut power_sets);
-        if count == 0 {
-            break;
-        }
-    }
+    resolve_power_references(
+        &villains,
+        &villain_archetypes,
+        &mut power_categories,
+        &mut power_sets,
+        &mut powers,
+    );
 
     let elapsed = Instant::now().duration_since(begin_time);
     println!("Done.");
@@ -448,6 +553,7 @@ pub fn load_powers_dictionary(config: &PowersConfig) -> Result<PowersDictionary,
     Ok(PowersDictionary {
         power_categories: power_categories_returned,
         archetypes,
+        boost_sets,
         attrib_names,
     })
 }
@@ -630,3 +736,118 @@ fn read_boostsets_bin(
     println!("Read {} boost sets.", boost_sets.len());
     Ok(boost_sets)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_power(full_name: &str, include_in_output: bool) -> BasePower {
+        let mut power = BasePower::new();
+        power.pch_full_name = Some(NameKey::new(full_name));
+        power.include_in_output = include_in_output;
+        power
+    }
+
+    /// Adds an effect group with a single attrib mod that uses `param`.
+    fn add_param(power: &mut BasePower, param: AttribModParam) {
+        let mut attrib_mod = AttribModTemplate::new();
+        attrib_mod.p_params = Some(param);
+        let mut effect_group = EffectGroup::new();
+        effect_group.pp_templates.push(attrib_mod);
+        power.pp_effects.push(Rc::new(effect_group));
+    }
+
+    fn grant(power_name: &str) -> AttribModParam {
+        let mut param = AttribModParam_Power::new();
+        param.ppch_power_names.push(NameKey::new(power_name));
+        AttribModParam::Power(param)
+    }
+
+    fn references(powers: &Keyed<BasePower>, power_name: &str) -> Vec<(PowerRelation, String)> {
+        powers[&NameKey::new(power_name)]
+            .referenced_by
+            .iter()
+            .map(|r| (r.relation, r.power_name.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_reverse_references() {
+        let mut powers = Keyed::new();
+        // Nova grants Flares twice and redirects to Fire_Blast, which redirects back to it
+        let mut nova = make_power("Ranged.Fire_Blast.Nova", true);
+        add_param(&mut nova, grant("Ranged.Fire_Blast.Flares"));
+        add_param(&mut nova, grant("Ranged.Fire_Blast.Flares"));
+        let mut redirect = PowerRedirect::new();
+        redirect.pch_name = Some(NameKey::new("Ranged.Fire_Blast.Fire_Blast"));
+        nova.pp_redirect.push(redirect);
+        // Rain_of_Fire summons an entity that uses Flares
+        let mut entcreate = AttribModParam_EntCreate::new();
+        entcreate.pch_entity_def = Some(NameKey::new("Pets_RainOfFire"));
+        let mut rain = make_power("Ranged.Fire_Blast.Rain_of_Fire", true);
+        add_param(&mut rain, AttribModParam::EntCreate(entcreate));
+        let mut fire_blast = make_power("Ranged.Fire_Blast.Fire_Blast", false);
+        let mut redirect = PowerRedirect::new();
+        redirect.pch_name = Some(NameKey::new("Ranged.Fire_Blast.Nova"));
+        fire_blast.pp_redirect.push(redirect);
+        for power in vec![
+            nova,
+            rain,
+            fire_blast,
+            make_power("Ranged.Fire_Blast.Flares", false),
+        ] {
+            powers.insert(power.pch_full_name.clone().unwrap(), Rc::new(power));
+        }
+
+        let mut villains = Keyed::new();
+        let mut villain = VillainDef::new();
+        villain.name = Some(NameKey::new("Pets_RainOfFire"));
+        let mut power_ref = PowerNameRef::new();
+        power_ref.power_category = Some(NameKey::new("Ranged"));
+        power_ref.power_set = Some(NameKey::new("Fire_Blast"));
+        power_ref.power = Some(NameKey::new("Flares"));
+        villain.powers.push(power_ref);
+        villains.insert(NameKey::new("Pets_RainOfFire"), Rc::new(villain));
+
+        resolve_power_references(
+            &villains,
+            &Keyed::new(),
+            &mut Keyed::new(),
+            &mut Keyed::new(),
+            &mut powers,
+        );
+
+        let mut flares = references(&powers, "Ranged.Fire_Blast.Flares");
+        flares.sort_by(|a, b| a.1.cmp(&b.1));
+        assert_eq!(
+            flares,
+            vec![
+                (
+                    PowerRelation::GrantedBy,
+                    String::from("Ranged.Fire_Blast.Nova")
+                ),
+                (
+                    PowerRelation::SummonedBy,
+                    String::from("Ranged.Fire_Blast.Rain_of_Fire")
+                ),
+            ]
+        );
+        assert_eq!(
+            references(&powers, "Ranged.Fire_Blast.Fire_Blast"),
+            vec![(
+                PowerRelation::RedirectedFrom,
+                String::from("Ranged.Fire_Blast.Nova")
+            )]
+        );
+        // the reached power is resolved too, so its own redirect is recorded on Nova
+        assert!(powers[&NameKey::new("Ranged.Fire_Blast.Fire_Blast")].include_in_output);
+        assert_eq!(
+            references(&powers, "Ranged.Fire_Blast.Nova"),
+            vec![(
+                PowerRelation::RedirectedFrom,
+                String::from("Ranged.Fire_Blast.Fire_Blast")
+            )]
+        );
+        assert!(references(&powers, "Ranged.Fire_Blast.Rain_of_Fire").is_empty());
+    }
+}
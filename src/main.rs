@@ -3,37 +3,103 @@
 #[macro_use]
 extern crate bitflags;
 extern crate chrono;
+extern crate flate2;
 extern crate md5;
 extern crate num_enum;
+extern crate rusqlite;
+extern crate schemars;
 extern crate serde;
 extern crate serde_json;
 extern crate toml;
 
 mod bin_parse;
+mod build;
+mod diff;
+mod dump;
 mod load;
+mod mbd;
 mod output;
+mod query;
+mod serve;
+mod sim;
 mod structs;
+mod unknowns;
 
 use bin_parse::{ParseError, ParseErrorKind};
+use build::Build;
+use diff::DictionarySnapshot;
+use mbd::MidsBuild;
+use query::{Query, QueryFormat};
+use serve::Server;
+use sim::AttackChain;
 use std::borrow::Cow;
 use std::env;
 use std::ffi::OsString;
 use std::io::{Error, ErrorKind};
-use std::path::PathBuf;
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
 use std::process;
 use std::time::Instant;
-use structs::config::PowersConfig;
+use structs::config::{OutputPolicyConfig, PowersConfig};
+use structs::PowersDictionary;
+use unknowns::UnknownFieldsReport;
 
 /// Default name for the config file.
 const CONFIG_FILE: &'static str = "PowersConfig.toml";
 
+/// Subcommand names.
+const SIMULATE_COMMAND: &'static str = "simulate";
+const EVALUATE_COMMAND: &'static str = "evaluate";
+const VALIDATE_COMMAND: &'static str = "validate";
+const DIFF_COMMAND: &'static str = "diff";
+const SERVE_COMMAND: &'static str = "serve";
+const QUERY_COMMAND: &'static str = "query";
+const DUMP_COMMAND: &'static str = "dump";
+const UNKNOWNS_COMMAND: &'static str = "unknowns";
+
+/// Options for the query subcommand.
+const FIELDS_OPTION: &'static str = "--fields";
+const FORMAT_OPTION: &'static str = "--format";
+
+/// Command line option that overrides `output_policy` in the config file.
+const OUTPUT_POLICY_OPTION: &'static str = "--output-policy";
+
+/// What to do. Most commands work on the powers dictionary, but some read the files directly.
+enum Command {
+    /// Load the powers dictionary, then run the command.
+    Dictionary(DictionaryCommand),
+    /// Compare the dictionaries at the first two paths (.bin directories or output trees) and
+    /// write the differences to the third.
+    Diff(PathBuf, PathBuf, PathBuf),
+    /// Write the raw contents of each .bin file as .json to the path.
+    Dump(PathBuf),
+    /// Report the values found in the fields the parsers don't understand yet.
+    Unknowns,
+}
+
+/// What to do once the powers dictionary is loaded.
+enum DictionaryCommand {
+    /// Write the powers dictionary out as .json files. (The default.)
+    Write,
+    /// Simulate the attack chain described in the file at the path.
+    Simulate(PathBuf),
+    /// Evaluate the build described in the file at the path.
+    Evaluate(PathBuf),
+    /// Validate the Mids' Reborn build file at the path.
+    Validate(PathBuf),
+    /// Serve the powers dictionary over HTTP on the address.
+    Serve(String),
+    /// Print the powers matching a name pattern.
+    Query(Query),
+}
+
 /// Program entry point.
 fn main() {
-    // get path to configuration
-    let config_path = get_config_path();
+    // figure out what we're doing and get path to configuration
+    let (command, config_path, output_policy) = parse_command_line();
 
     // load configuration
-    let config = PowersConfig::load(&config_path).unwrap_or_else(|e| {
+    let mut config = PowersConfig::load(&config_path).unwrap_or_else(|e| {
         println!(
             "Unable to load {}. {}",
             config_path.display(),
@@ -41,8 +107,20 @@ fn main() {
         );
         process::exit(1);
     });
+    if let Some(output_policy) = output_policy {
+        config.output_policy = output_policy;
+    }
     println!("Configuration loaded.");
 
+    let command = match command {
+        Command::Dictionary(command) => command,
+        Command::Diff(old_path, new_path, output_path) => {
+            return diff(&old_path, &new_path, &output_path, &mut config)
+        }
+        Command::Dump(output_path) => return dump(&output_path, &config),
+        Command::Unknowns => return unknowns(&config),
+    };
+
     // parse the powers dictionary
     let powers_dict = load::load_powers_dictionary(&config).unwrap_or_else(|context| {
         println!("{} {}.", context.message, get_error(&context.error));
@@ -50,9 +128,20 @@ fn main() {
     });
     println!("Powers dictionary loaded.");
 
-    // write output files
+    match command {
+        DictionaryCommand::Write => write_output(powers_dict, &config),
+        DictionaryCommand::Simulate(chain_path) => simulate(&chain_path, &powers_dict, &config),
+        DictionaryCommand::Evaluate(build_path) => evaluate(&build_path, &powers_dict),
+        DictionaryCommand::Validate(build_path) => validate(&build_path, &powers_dict),
+        DictionaryCommand::Serve(address) => serve(&address, &powers_dict, &mut config),
+        DictionaryCommand::Query(query) => run_query(&query, &powers_dict, &config),
+    }
+}
+
+/// Writes the output files.
+fn write_output(powers_dict: PowersDictionary, config: &PowersConfig) {
     let begin_time = Instant::now();
-    if let Err(e) = output::write_powers_dictionary(powers_dict, &config) {
+    if let Err(e) = output::write_powers_dictionary(powers_dict, config) {
         println!("Unable to write ouput files! {}", get_io_error(&e));
         process::exit(1);
     }
@@ -60,11 +149,318 @@ fn main() {
     println!("Files written in {} seconds.", elapsed.as_secs());
 }
 
-/// Optionally read path to config file from command line. Otherwise use
+/// Runs the attack chain simulator and prints the results.
+fn simulate(chain_path: &Path, powers_dict: &PowersDictionary, config: &PowersConfig) {
+    let chain = AttackChain::load(chain_path).unwrap_or_else(|e| {
+        println!(
+            "Unable to load {}. {}",
+            chain_path.display(),
+            get_io_error(&e)
+        );
+        process::exit(1);
+    });
+    match sim::simulate_attack_chain(&chain, powers_dict, config) {
+        Ok(result) => print!("{}", result),
+        Err(e) => {
+            println!("Unable to simulate attack chain! {}", get_io_error(&e));
+            process::exit(1);
+        }
+    }
+}
+
+/// Runs the build evaluator and prints the totals.
+fn evaluate(build_path: &Path, powers_dict: &PowersDictionary) {
+    let build = Build::load(build_path).unwrap_or_else(|e| {
+        println!(
+            "Unable to load {}. {}",
+            build_path.display(),
+            get_io_error(&e)
+        );
+        process::exit(1);
+    });
+    match build::evaluate_build(&build, powers_dict) {
+        Ok(totals) => print!("{}", totals),
+        Err(e) => {
+            println!("Unable to evaluate build! {}", get_io_error(&e));
+            process::exit(1);
+        }
+    }
+}
+
+/// Validates a Mids' Reborn build file, printing any problems found and saving a corrected
+/// copy of the build if anything was renamed.
+fn validate(build_path: &Path, powers_dict: &PowersDictionary) {
+    let mut build = MidsBuild::load(build_path).unwrap_or_else(|e| {
+        println!(
+            "Unable to load {}. {}",
+            build_path.display(),
+            get_io_error(&e)
+        );
+        process::exit(1);
+    });
+    let validation = mbd::validate_build(&mut build, powers_dict);
+    print!("{}", validation);
+    if validation.corrected {
+        let corrected_path = mbd::corrected_path(build_path);
+        if let Err(e) = build.save(&corrected_path) {
+            println!("Unable to save corrected build! {}", get_io_error(&e));
+            process::exit(1);
+        }
+        println!("Corrected build written to {}.", corrected_path.display());
+    }
+}
+
+/// Serves the .json files and query endpoints over HTTP until the program is stopped. Links in
+/// the .json files point back to the server rather than `base_json_url`. They're written without a
+/// host, so they work with whatever address the client used (e.g. when listening on `0.0.0.0`).
+fn serve(address: &str, powers_dict: &PowersDictionary, config: &mut PowersConfig) {
+    config.base_json_url = Some(String::from("/"));
+    let server = Server::new(powers_dict, config).unwrap_or_else(|e| {
+        println!("Unable to prepare the output! {}", get_io_error(&e));
+        process::exit(1);
+    });
+    if let Err(e) = server.run(address) {
+        println!("Unable to serve on {}! {}", address, get_io_error(&e));
+        process::exit(1);
+    }
+}
+
+/// Runs a query and prints the matching powers.
+fn run_query(query: &Query, powers_dict: &PowersDictionary, config: &PowersConfig) {
+    match query.run(powers_dict, config) {
+        Ok(results) => print!("{}", results),
+        Err(e) => {
+            println!("Unable to run query! {}", get_io_error(&e));
+            process::exit(1);
+        }
+    }
+}
+
+/// Writes every parsed .bin file out as .json without merging or filtering anything.
+fn dump(output_path: &Path, config: &PowersConfig) {
+    let bins = load::load_raw_bins(config).unwrap_or_else(|context| {
+        println!("{} {}.", context.message, get_error(&context.error));
+        process::exit(1);
+    });
+    if let Err(e) = dump::write_raw_bins(&bins, output_path) {
+        println!("Unable to write dump files! {}", get_io_error(&e));
+        process::exit(1);
+    }
+}
+
+/// Prints the distribution of values in every unknown field of the .bin files.
+fn unknowns(config: &PowersConfig) {
+    let bins = load::load_raw_bins(config).unwrap_or_else(|context| {
+        println!("{} {}.", context.message, get_error(&context.error));
+        process::exit(1);
+    });
+    match UnknownFieldsReport::from_raw_bins(&bins) {
+        Ok(report) => print!("{}", report),
+        Err(e) => {
+            println!("Unable to collect unknown fields! {}", get_io_error(&e));
+            process::exit(1);
+        }
+    }
+}
+
+/// Compares two dictionaries and writes the differences to `output_path` as .json and as a
+/// Markdown changelog.
+fn diff(old_path: &Path, new_path: &Path, output_path: &Path, config: &mut PowersConfig) {
+    let old = load_snapshot(old_path, config);
+    let new = load_snapshot(new_path, config);
+    let diff = diff::diff_dictionaries(&old, &new);
+    if let Err(e) = diff::write_diff(&diff, output_path) {
+        println!("Unable to write diff files! {}", get_io_error(&e));
+        process::exit(1);
+    }
+    println!(
+        "{} power categories, {} power sets, and {} powers changed.",
+        diff.power_categories.changed.len(),
+        diff.power_sets.changed.len(),
+        diff.powers.changed.len()
+    );
+}
+
+/// Loads a dictionary to compare from an output tree or from a directory of .bin files.
+/// .bin files are loaded using `config`, with its `input_path` replaced by `path`.
+fn load_snapshot(path: &Path, config: &mut PowersConfig) -> DictionarySnapshot {
+    let snapshot = if DictionarySnapshot::is_output_tree(path) {
+        DictionarySnapshot::from_output_tree(path)
+    } else {
+        config.input_path = path.to_string_lossy().into_owned();
+        let powers_dict = load::load_powers_dictionary(config).unwrap_or_else(|context| {
+            println!("{} {}.", context.message, get_error(&context.error));
+            process::exit(1);
+        });
+        DictionarySnapshot::from_powers_dictionary(&powers_dict, path.display().to_string(), config)
+    };
+    snapshot.unwrap_or_else(|e| {
+        println!("Unable to load {}. {}", path.display(), get_io_error(&e));
+        process::exit(1);
+    })
+}
+
+/// Reads an optional subcommand and its arguments from the command line, followed by
+/// the optional path to the config file. The `--output-policy` option can appear anywhere.
+fn parse_command_line() -> (Command, PathBuf, Option<OutputPolicyConfig>) {
+    let (args, output_policy) = get_output_policy(env::args_os().skip(1));
+    let mut args = args.into_iter().peekable();
+    let command = match args.peek().and_then(|arg| arg.to_str()) {
+        Some(SIMULATE_COMMAND) => {
+            args.next();
+            if let Some(chain_path) = args.next() {
+                Command::Dictionary(DictionaryCommand::Simulate(PathBuf::from(chain_path)))
+            } else {
+                println!(
+                    "Usage: powers {} <attack chain .toml> [config]",
+                    SIMULATE_COMMAND
+                );
+                process::exit(1);
+            }
+        }
+        Some(EVALUATE_COMMAND) => {
+            args.next();
+            if let Some(build_path) = args.next() {
+                Command::Dictionary(DictionaryCommand::Evaluate(PathBuf::from(build_path)))
+            } else {
+                println!("Usage: powers {} <build .toml> [config]", EVALUATE_COMMAND);
+                process::exit(1);
+            }
+        }
+        Some(DIFF_COMMAND) => {
+            args.next();
+            match (args.next(), args.next(), args.next()) {
+                (Some(old_path), Some(new_path), Some(output_path)) => Command::Diff(
+                    PathBuf::from(old_path),
+                    PathBuf::from(new_path),
+                    PathBuf::from(output_path),
+                ),
+                _ => {
+                    println!(
+                        "Usage: powers {} <old path> <new path> <output path> [config]",
+                        DIFF_COMMAND
+                    );
+                    process::exit(1);
+                }
+            }
+        }
+        Some(VALIDATE_COMMAND) => {
+            args.next();
+            if let Some(build_path) = args.next() {
+                Command::Dictionary(DictionaryCommand::Validate(PathBuf::from(build_path)))
+            } else {
+                println!("Usage: powers {} <build .mbd> [config]", VALIDATE_COMMAND);
+                process::exit(1);
+            }
+        }
+        Some(SERVE_COMMAND) => {
+            args.next();
+            if let Some(address) = args.next().and_then(|a| a.into_string().ok()) {
+                Command::Dictionary(DictionaryCommand::Serve(address))
+            } else {
+                println!("Usage: powers {} <address:port> [config]", SERVE_COMMAND);
+                process::exit(1);
+            }
+        }
+        Some(DUMP_COMMAND) => {
+            args.next();
+            if let Some(output_path) = args.next() {
+                Command::Dump(PathBuf::from(output_path))
+            } else {
+                println!("Usage: powers {} <output path> [config]", DUMP_COMMAND);
+                process::exit(1);
+            }
+        }
+        Some(UNKNOWNS_COMMAND) => {
+            args.next();
+            Command::Unknowns
+        }
+        Some(QUERY_COMMAND) => {
+            args.next();
+            match parse_query(&mut args) {
+                Some(query) => Command::Dictionary(DictionaryCommand::Query(query)),
+                None => {
+                    println!(
+                        "Usage: powers {} <name pattern> [{} <field,...>] [{} <table|json>] [config]",
+                        QUERY_COMMAND, FIELDS_OPTION, FORMAT_OPTION
+                    );
+                    process::exit(1);
+                }
+            }
+        }
+        _ => Command::Dictionary(DictionaryCommand::Write),
+    };
+    (command, get_config_path(args), output_policy)
+}
+
+/// Reads the name pattern for the query subcommand, followed by its optional `--fields` and
+/// `--format` options.
+fn parse_query<I>(args: &mut Peekable<I>) -> Option<Query>
+where
+    I: Iterator<Item = OsString>,
+{
+    let pattern = args.next()?.into_string().ok()?;
+    let mut query = Query {
+        pattern,
+        fields: Vec::new(),
+        format: QueryFormat::Table,
+    };
+    loop {
+        match args.peek().and_then(|arg| arg.to_str()) {
+            Some(FIELDS_OPTION) => {
+                args.next();
+                let fields = args.next()?.into_string().ok()?;
+                query.fields = fields
+                    .split(',')
+                    .filter(|f| !f.is_empty())
+                    .map(String::from)
+                    .collect();
+            }
+            Some(FORMAT_OPTION) => {
+                args.next();
+                let format = args.next()?;
+                query.format = QueryFormat::from_name(format.to_str()?)?;
+            }
+            _ => return Some(query),
+        }
+    }
+}
+
+/// Removes the `--output-policy <policy>` option from `args`, if present.
+fn get_output_policy<I>(mut args: I) -> (Vec<OsString>, Option<OutputPolicyConfig>)
+where
+    I: Iterator<Item = OsString>,
+{
+    let mut remaining = Vec::new();
+    let mut output_policy = None;
+    while let Some(arg) = args.next() {
+        if arg != OUTPUT_POLICY_OPTION {
+            remaining.push(arg);
+            continue;
+        }
+        let policy = args
+            .next()
+            .and_then(|name| name.to_str().and_then(OutputPolicyConfig::from_name));
+        if policy.is_none() {
+            println!(
+                "Usage: powers {} <overwrite|fail-if-exists|merge> [config]",
+                OUTPUT_POLICY_OPTION
+            );
+            process::exit(1);
+        }
+        output_policy = policy;
+    }
+    (remaining, output_policy)
+}
+
+/// Optionally read path to config file from the remaining command line `args`. Otherwise use
 /// `CONFIG_FILE` in the current directory.
-fn get_config_path() -> PathBuf {
+fn get_config_path<I>(args: I) -> PathBuf
+where
+    I: Iterator<Item = OsString>,
+{
     let mut config_path: Option<OsString> = None;
-    for arg in env::args_os().skip(1) {
+    for arg in args {
         if config_path.is_none() {
             config_path = Some(arg);
         } else {
@@ -0,0 +1,808 @@
+use crate::structs::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, Result};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// Power category containing the enhancements.
+const BOOSTS_CATEGORY: &'static str = "Boosts";
+
+/// Extension used for corrected build files.
+const CORRECTED_EXTENSION: &'static str = "corrected.mbd";
+
+/// An enhancement slotted in a Mids' Reborn build.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MidsEnhancement {
+    /// Name of the enhancement. Usually the name of the boost power, but can also be the full name.
+    pub uid: String,
+    /// Level of the enhancement (0-based), if it's an invention.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub io_level: Option<i32>,
+    /// Fields that aren't validated are kept as-is.
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// A slot in a Mids' Reborn build.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MidsSlotEntry {
+    /// The slotted enhancement. Empty slots don't have one.
+    #[serde(default)]
+    pub enhancement: Option<MidsEnhancement>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// A power picked in a Mids' Reborn build.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MidsPowerEntry {
+    /// The full name of the power (category, power set, and power). Empty if no power was picked.
+    #[serde(default)]
+    pub power_name: String,
+    /// Level the power was picked at (0-based).
+    #[serde(default)]
+    pub level: i32,
+    #[serde(default)]
+    pub slot_entries: Vec<MidsSlotEntry>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// A Mids' Reborn build (.mbd) file.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MidsBuild {
+    /// Internal name of the archetype (e.g. `Class_Tanker`).
+    pub class: String,
+    /// Full names of the power sets (category and set) used by the build.
+    #[serde(default)]
+    pub power_sets: Vec<String>,
+    /// The powers picked in the build. Unused entries may be `null`.
+    #[serde(default)]
+    pub power_entries: Vec<Option<MidsPowerEntry>>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+impl MidsBuild {
+    /// Parses a .mbd file to create a `MidsBuild`.
+    ///
+    /// # Arguments:
+    ///
+    /// * `path` - The path to the .mbd file to parse.
+    ///
+    /// # Returns:
+    ///
+    /// If successful, a `MidsBuild`. Otherwise, a `std::io::Error`.
+    pub fn load(path: &Path) -> Result<MidsBuild> {
+        let file = File::open(path)?;
+        let build: MidsBuild = serde_json::from_reader(BufReader::new(file))?;
+        Ok(build)
+    }
+
+    /// Writes the build to a .mbd file.
+    ///
+    /// # Arguments:
+    ///
+    /// * `path` - The path to the .mbd file to write.
+    ///
+    /// # Returns:
+    ///
+    /// Nothing if the operation was successful. Otherwise, a `std::io::Error`.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut file = File::create(path)?;
+        serde_json::to_writer_pretty(&mut file, self)?;
+        Ok(())
+    }
+}
+
+/// Gets the path used to save the corrected version of the build at `path`.
+pub fn corrected_path(path: &Path) -> PathBuf {
+    path.with_extension(CORRECTED_EXTENSION)
+}
+
+/// Problems found when validating a build.
+#[derive(Debug, PartialEq)]
+pub enum BuildIssueKind {
+    /// The build's archetype doesn't exist.
+    ArchetypeNotFound,
+    /// The name no longer exists, but unambiguously matches something else.
+    Renamed(String),
+    /// The name no longer exists and nothing matches it.
+    Removed,
+    /// The name no longer exists and more than one thing matches it.
+    Ambiguous(Vec<String>),
+    /// The power can't be picked by the build's archetype.
+    WrongArchetype,
+    /// The power was picked before it's available, or the enhancement's level is outside of
+    /// its set's levels. Levels are 1-based.
+    WrongLevel { picked: i32, min: i32, max: i32 },
+    /// The enhancement's set category can't be slotted in the power.
+    DisallowedSetCategory(String),
+}
+
+/// A problem found when validating a build.
+#[derive(Debug)]
+pub struct BuildIssue {
+    /// The name of the archetype, power set, power, or enhancement with the problem.
+    pub name: String,
+    /// For enhancements, the power the enhancement is slotted in.
+    pub slotted_in: Option<String>,
+    pub kind: BuildIssueKind,
+}
+
+impl fmt::Display for BuildIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(power_name) = &self.slotted_in {
+            write!(f, " (in {})", power_name)?;
+        }
+        match &self.kind {
+            BuildIssueKind::ArchetypeNotFound => write!(f, ": archetype not found"),
+            BuildIssueKind::Renamed(new_name) => write!(f, ": renamed to {}", new_name),
+            BuildIssueKind::Removed => write!(f, ": not found"),
+            BuildIssueKind::Ambiguous(candidates) => {
+                write!(f, ": not found, could be any of {}", candidates.join(", "))
+            }
+            BuildIssueKind::WrongArchetype => write!(f, ": not available to the archetype"),
+            BuildIssueKind::WrongLevel { picked, min, max } if min == max => {
+                write!(f, ": level {} but must be {}", picked, min)
+            }
+            BuildIssueKind::WrongLevel { picked, min, max } if *max >= 50 => {
+                write!(f, ": level {} but must be at least {}", picked, min)
+            }
+            BuildIssueKind::WrongLevel { picked, min, max } => write!(
+                f,
+                ": level {} but must be between {} and {}",
+                picked, min, max
+            ),
+            BuildIssueKind::DisallowedSetCategory(category) => {
+                write!(f, ": set category {} can't be slotted here", category)
+            }
+        }
+    }
+}
+
+/// The results of validating a build.
+#[derive(Debug)]
+pub struct BuildValidation {
+    /// Everything wrong with the build.
+    pub issues: Vec<BuildIssue>,
+    /// `true` if any renames were applied to the build.
+    pub corrected: bool,
+}
+
+impl fmt::Display for BuildValidation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.issues.is_empty() {
+            return writeln!(f, "No problems found.");
+        }
+        writeln!(f, "{} problem(s) found:", self.issues.len())?;
+        for issue in &self.issues {
+            writeln!(f, "\t{}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+/// Where a power can be found in the dictionary.
+struct PowerLocation<'a> {
+    power: &'a Rc<BasePower>,
+    /// Level the power becomes available (0-based).
+    available: i32,
+}
+
+/// Lookups used to resolve the names in a build.
+struct BuildLookup<'a> {
+    power_sets: HashMap<&'a NameKey, &'a Rc<BasePowerSet>>,
+    powers: HashMap<&'a NameKey, PowerLocation<'a>>,
+    boosts: Vec<&'a Rc<BasePower>>,
+    boost_sets: HashMap<&'a NameKey, &'a BoostSet>,
+}
+
+impl<'a> BuildLookup<'a> {
+    fn new(powers_dict: &'a PowersDictionary) -> Self {
+        let mut lookup = BuildLookup {
+            power_sets: HashMap::new(),
+            powers: HashMap::new(),
+            boosts: Vec::new(),
+            boost_sets: HashMap::new(),
+        };
+        for pcat in &powers_dict.power_categories {
+            let is_boosts = matches!(&pcat.pch_name, Some(name) if name == BOOSTS_CATEGORY);
+            for pset in &pcat.pp_power_sets {
+                if let Some(set_name) = &pset.pch_full_name {
+                    lookup.power_sets.insert(set_name, pset);
+                }
+                for (i, power) in pset.pp_powers.iter().enumerate() {
+                    if is_boosts {
+                        lookup.boosts.push(power);
+                    } else if let Some(power_name) = &power.pch_full_name {
+                        lookup.powers.insert(
+                            power_name,
+                            PowerLocation {
+                                power,
+                                available: pset.pi_available.get(i).copied().unwrap_or(0),
+                            },
+                        );
+                    }
+                }
+            }
+        }
+        for boost_set in powers_dict.boost_sets.values() {
+            for boost in boost_set
+                .pp_boost_lists
+                .iter()
+                .flat_map(|l| l.ppch_boosts.iter())
+            {
+                lookup.boost_sets.insert(boost, &**boost_set);
+            }
+        }
+        lookup
+    }
+
+    /// Finds a boost power by its full name or its internal name.
+    fn find_boost(&self, uid: &str) -> Option<&'a Rc<BasePower>> {
+        self.boosts.iter().copied().find(|boost| {
+            matches!(&boost.pch_full_name, Some(name) if name == uid)
+                || matches!(&boost.pch_name, Some(name) if name.eq_ignore_ascii_case(uid))
+        })
+    }
+}
+
+/// Validates a Mids' Reborn build against the powers dictionary, correcting any renamed power
+/// sets, powers, and enhancements in place.
+///
+/// # Arguments:
+///
+/// * `build` - The build to validate. Names that are unambiguously renamed are updated.
+/// * `powers_dict` - The powers dictionary to validate against.
+///
+/// # Returns:
+///
+/// A `BuildValidation` describing the problems found.
+///
+/// # Notes:
+///
+/// A missing power set or power is considered renamed if exactly one power set or power in the
+/// dictionary has the same internal name, or a display name that matches the old internal name
+/// (e.g. `Hand_Clap` and "Hand Clap"). When there's more than one, the candidates in the same
+/// category or in one of the build's power sets are preferred. Enhancements are matched by their
+/// full or internal name, and a missing enhancement is considered renamed (and corrected to the
+/// boost's full name) using the same rules. Anything other than a rename is reported but left alone.
+pub fn validate_build(build: &mut MidsBuild, powers_dict: &PowersDictionary) -> BuildValidation {
+    let lookup = BuildLookup::new(powers_dict);
+    let mut issues = Vec::new();
+    let mut corrected = false;
+
+    let archetype = powers_dict.find_archetype(&NameKey::new(build.class.clone()));
+    if archetype.is_none() {
+        issues.push(BuildIssue {
+            name: build.class.clone(),
+            slotted_in: None,
+            kind: BuildIssueKind::ArchetypeNotFound,
+        });
+    }
+
+    // power sets
+    for set_name in &mut build.power_sets {
+        if set_name.is_empty()
+            || lookup
+                .power_sets
+                .contains_key(&NameKey::new(set_name.clone()))
+        {
+            continue;
+        }
+        let candidates: Vec<_> = lookup
+            .power_sets
+            .iter()
+            .map(|(name, pset)| (name.get(), pset.pch_display_name.as_deref()))
+            .collect();
+        let kind = resolve_rename(set_name, &candidates, &[]);
+        let old_name = set_name.clone();
+        if let BuildIssueKind::Renamed(new_name) = &kind {
+            *set_name = new_name.clone();
+            corrected = true;
+        }
+        issues.push(BuildIssue {
+            name: old_name,
+            slotted_in: None,
+            kind,
+        });
+    }
+
+    // powers
+    let build_sets: Vec<_> = build.power_sets.iter().map(|s| s.as_str()).collect();
+    let boost_names: Vec<_> = lookup
+        .boosts
+        .iter()
+        .filter_map(|boost| {
+            let name = boost.pch_full_name.as_ref()?;
+            Some((name.get(), boost.pch_display_name.as_deref()))
+        })
+        .collect();
+    for entry in build.power_entries.iter_mut().flatten() {
+        if entry.power_name.is_empty() {
+            continue;
+        }
+        let mut location = lookup.powers.get(&NameKey::new(entry.power_name.clone()));
+        if location.is_none() {
+            let candidates: Vec<_> = lookup
+                .powers
+                .iter()
+                .map(|(name, l)| (name.get(), l.power.pch_display_name.as_deref()))
+                .collect();
+            let kind = resolve_rename(&entry.power_name, &candidates, &build_sets);
+            let old_name = entry.power_name.clone();
+            if let BuildIssueKind::Renamed(new_name) = &kind {
+                location = lookup.powers.get(&NameKey::new(new_name.clone()));
+                entry.power_name = new_name.clone();
+                corrected = true;
+            }
+            issues.push(BuildIssue {
+                name: old_name,
+                slotted_in: None,
+                kind,
+            });
+        }
+        if let Some(location) = location {
+            check_power(&mut issues, entry, location, archetype);
+        }
+
+        // enhancements
+        for slot in &mut entry.slot_entries {
+            let enhancement = match &mut slot.enhancement {
+                Some(enhancement) => enhancement,
+                None => continue,
+            };
+            let mut boost = lookup.find_boost(&enhancement.uid);
+            if boost.is_none() {
+                let kind = resolve_rename(&enhancement.uid, &boost_names, &[]);
+                let old_name = enhancement.uid.clone();
+                if let BuildIssueKind::Renamed(new_name) = &kind {
+                    boost = lookup.find_boost(new_name);
+                    enhancement.uid = new_name.clone();
+                    corrected = true;
+                }
+                issues.push(BuildIssue {
+                    name: old_name,
+                    slotted_in: Some(entry.power_name.clone()),
+                    kind,
+                });
+            }
+            if let Some(boost) = boost {
+                check_enhancement(
+                    &mut issues,
+                    &entry.power_name,
+                    enhancement,
+                    boost,
+                    location.map(|l| &**l.power),
+                    &lookup,
+                );
+            }
+        }
+    }
+
+    BuildValidation { issues, corrected }
+}
+
+/// Looks for a replacement for `old_name` among `candidates` (full names and display names).
+/// A candidate matches if the last part of its name (the internal name of the power set or
+/// power) is the same, or if its display name reads the same as the old internal name.
+fn resolve_rename(
+    old_name: &str,
+    candidates: &[(&str, Option<&str>)],
+    build_sets: &[&str],
+) -> BuildIssueKind {
+    let old_key = NameKey::new(old_name);
+    let old_parts = old_key.split();
+    let short_name = match old_parts.last() {
+        Some(short_name) => short_name.to_ascii_lowercase(),
+        None => return BuildIssueKind::Removed,
+    };
+    let display_name = short_name.replace('_', " ");
+    let mut matches: Vec<_> = candidates
+        .iter()
+        .filter(|(name, display)| {
+            matches!(name.rsplit('.').next(), Some(n) if n.eq_ignore_ascii_case(&short_name))
+                || matches!(display, Some(d) if d.trim().eq_ignore_ascii_case(&display_name))
+        })
+        .map(|(name, _)| *name)
+        .collect();
+    matches.sort();
+    if matches.len() > 1 {
+        // prefer the same category, then the build's power sets
+        let category = old_parts[0];
+        let same_category: Vec<_> = matches
+            .iter()
+            .copied()
+            .filter(|name| matches!(name.split('.').next(), Some(c) if c.eq_ignore_ascii_case(category)))
+            .collect();
+        let in_build: Vec<_> = matches
+            .iter()
+            .copied()
+            .filter(|name| {
+                build_sets.iter().any(|set| {
+                    name.len() > set.len()
+                        && name[..set.len()].eq_ignore_ascii_case(set)
+                        && name[set.len()..].starts_with('.')
+                })
+            })
+            .collect();
+        if same_category.len() == 1 {
+            matches = same_category;
+        } else if in_build.len() == 1 {
+            matches = in_build;
+        }
+    }
+    match matches.len() {
+        0 => BuildIssueKind::Removed,
+        1 => BuildIssueKind::Renamed(String::from(matches[0])),
+        _ => BuildIssueKind::Ambiguous(matches.iter().map(|s| String::from(*s)).collect()),
+    }
+}
+
+/// Checks that the power can be picked by the archetype at the level it was picked.
+fn check_power(
+    issues: &mut Vec<BuildIssue>,
+    entry: &MidsPowerEntry,
+    location: &PowerLocation<'_>,
+    archetype: Option<&Rc<Archetype>>,
+) {
+    if let Some(archetype) = archetype {
+        if !power_allowed_for_archetype(location.power, archetype) {
+            issues.push(BuildIssue {
+                name: entry.power_name.clone(),
+                slotted_in: None,
+                kind: BuildIssueKind::WrongArchetype,
+            });
+        }
+    }
+    // inherent powers are granted at level 1, which Mids stores as -1 or 0
+    if entry.level >= 0 && entry.level < location.available {
+        issues.push(BuildIssue {
+            name: entry.power_name.clone(),
+            slotted_in: None,
+            kind: BuildIssueKind::WrongLevel {
+                picked: entry.level + 1,
+                min: location.available + 1,
+                max: 50,
+            },
+        });
+    }
+}
+
+/// Checks whether `archetype` can pick `power`, using the same rules as the JSON output.
+fn power_allowed_for_archetype(power: &BasePower, archetype: &Rc<Archetype>) -> bool {
+    if !power.archetypes.is_empty() && !power.archetypes.iter().any(|at| Rc::ptr_eq(at, archetype))
+    {
+        return false;
+    }
+    if power
+        .ppch_buy_requires
+        .iter()
+        .any(|rule| rule == "$archetype")
+    {
+        if let Some(class_key) = &archetype.class_key {
+            return power
+                .ppch_buy_requires
+                .iter()
+                .any(|rule| class_key == &rule[..]);
+        }
+        return false;
+    }
+    true
+}
+
+/// Checks that the enhancement's level and set category are allowed.
+fn check_enhancement(
+    issues: &mut Vec<BuildIssue>,
+    power_name: &str,
+    enhancement: &MidsEnhancement,
+    boost: &BasePower,
+    power: Option<&BasePower>,
+    lookup: &BuildLookup<'_>,
+) {
+    let boost_set = boost
+        .pch_full_name
+        .as_ref()
+        .and_then(|name| lookup.boost_sets.get(name));
+    let boost_set = match boost_set {
+        Some(boost_set) => boost_set,
+        None => return,
+    };
+    if let Some(io_level) = enhancement.io_level {
+        if io_level < boost_set.i_min_level || io_level > boost_set.i_max_level {
+            issues.push(BuildIssue {
+                name: enhancement.uid.clone(),
+                slotted_in: Some(power_name.to_owned()),
+                kind: BuildIssueKind::WrongLevel {
+                    picked: io_level + 1,
+                    min: boost_set.i_min_level + 1,
+                    max: boost_set.i_max_level + 1,
+                },
+            });
+        }
+    }
+    if let (Some(power), Some(category)) = (power, &boost_set.pch_group_name) {
+        if !power.enhancement_set_categories_allowed.contains(category) {
+            issues.push(BuildIssue {
+                name: enhancement.uid.clone(),
+                slotted_in: Some(power_name.to_owned()),
+                kind: BuildIssueKind::DisallowedSetCategory(category.clone()),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn make_power(full_name: &str) -> BasePower {
+        let mut power = BasePower::new();
+        power.pch_name = full_name.rsplit('.').next().map(String::from);
+        power.pch_full_name = Some(NameKey::new(full_name));
+        power
+    }
+
+    fn make_powers_dict() -> PowersDictionary {
+        let tanker = Rc::new(Archetype::new());
+        let mut brute = Archetype::new();
+        brute.pch_name = Some(String::from("Class_Brute"));
+        let brute = Rc::new(brute);
+        let mut archetypes = Keyed::new();
+        archetypes.insert(NameKey::new("Class_Tanker"), tanker);
+        archetypes.insert(NameKey::new("Class_Brute"), brute.clone());
+
+        let mut jab = make_power("Tanker_Melee.Super_Strength.Jab");
+        jab.enhancement_set_categories_allowed
+            .insert(String::from("Melee Damage"));
+        let mut foot_stomp = make_power("Tanker_Melee.Super_Strength.Foot_Stomp");
+        foot_stomp.archetypes.push(brute);
+        let mut pset = BasePowerSet::new();
+        pset.pch_full_name = Some(NameKey::new("Tanker_Melee.Super_Strength"));
+        let mut knockout = make_power("Tanker_Melee.Super_Strength.Knockout");
+        knockout.pch_display_name = Some(String::from("Knockout Blow"));
+        pset.pp_powers = vec![
+            Rc::new(jab),
+            Rc::new(make_power("Tanker_Melee.Super_Strength.Haymaker")),
+            Rc::new(foot_stomp),
+            Rc::new(knockout),
+        ];
+        pset.pi_available = vec![0, 5, 0, 21];
+        let mut pcat = PowerCategory::new();
+        pcat.pch_name = Some(NameKey::new("Tanker_Melee"));
+        pcat.pp_power_sets.push(Rc::new(pset));
+
+        let mut boost_pset = BasePowerSet::new();
+        boost_pset.pch_full_name = Some(NameKey::new("Boosts.Crafted_Mako"));
+        boost_pset
+            .pp_powers
+            .push(Rc::new(make_power("Boosts.Crafted_Mako.Crafted_Mako_A")));
+        let mut boost_pcat = PowerCategory::new();
+        boost_pcat.pch_name = Some(NameKey::new(BOOSTS_CATEGORY));
+        boost_pcat.pp_power_sets.push(Rc::new(boost_pset));
+
+        let mut boost_set = BoostSet::new();
+        boost_set.pch_group_name = Some(String::from("Melee Damage"));
+        boost_set.i_min_level = 9;
+        boost_set.i_max_level = 49;
+        let mut boost_list = BoostList::new();
+        boost_list
+            .ppch_boosts
+            .push(NameKey::new("Boosts.Crafted_Mako.Crafted_Mako_A"));
+        boost_set.pp_boost_lists.push(boost_list);
+        let mut boost_sets = Keyed::new();
+        boost_sets.insert(NameKey::new("Makos_Bite"), Rc::new(boost_set));
+
+        PowersDictionary {
+            power_categories: vec![Rc::new(pcat), Rc::new(boost_pcat)],
+            archetypes,
+            boost_sets,
+            attrib_names: AttribNames::new(),
+        }
+    }
+
+    fn issue_kinds(validation: &BuildValidation) -> Vec<(&str, &BuildIssueKind)> {
+        validation
+            .issues
+            .iter()
+            .map(|issue| (issue.name.as_str(), &issue.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_validate_build_renames() {
+        let mut build: MidsBuild = serde_json::from_value(json!({
+            "Class": "Class_Tanker",
+            "PowerSets": ["Melee.Super_Strength"],
+            "PowerEntries": [
+                {
+                    "PowerName": "Melee.Super_Strength.Jab",
+                    "Level": 0,
+                    "SlotEntries": [
+                        { "Enhancement": { "Uid": "Boosts.Old_Mako.Crafted_Mako_A", "IoLevel": 24 } },
+                        { "Enhancement": { "Uid": "Crafted_Mako_A" } },
+                        { "Enhancement": null }
+                    ]
+                },
+                null,
+                { "PowerName": "Tanker_Melee.Super_Strength.Knockout_Blow", "Level": 21 }
+            ]
+        }))
+        .unwrap();
+        let validation = validate_build(&mut build, &make_powers_dict());
+        assert_eq!(
+            issue_kinds(&validation),
+            vec![
+                (
+                    "Melee.Super_Strength",
+                    &BuildIssueKind::Renamed(String::from("Tanker_Melee.Super_Strength"))
+                ),
+                (
+                    "Melee.Super_Strength.Jab",
+                    &BuildIssueKind::Renamed(String::from("Tanker_Melee.Super_Strength.Jab"))
+                ),
+                (
+                    "Boosts.Old_Mako.Crafted_Mako_A",
+                    &BuildIssueKind::Renamed(String::from("Boosts.Crafted_Mako.Crafted_Mako_A"))
+                ),
+                (
+                    "Tanker_Melee.Super_Strength.Knockout_Blow",
+                    &BuildIssueKind::Renamed(String::from("Tanker_Melee.Super_Strength.Knockout"))
+                ),
+            ]
+        );
+        assert!(validation.corrected);
+        assert_eq!(build.power_sets, vec!["Tanker_Melee.Super_Strength"]);
+        let entry = build.power_entries[0].as_ref().unwrap();
+        assert_eq!(entry.power_name, "Tanker_Melee.Super_Strength.Jab");
+        let uids: Vec<_> = entry
+            .slot_entries
+            .iter()
+            .filter_map(|slot| slot.enhancement.as_ref())
+            .map(|enhancement| enhancement.uid.as_str())
+            .collect();
+        assert_eq!(
+            uids,
+            vec!["Boosts.Crafted_Mako.Crafted_Mako_A", "Crafted_Mako_A"]
+        );
+        assert_eq!(
+            build.power_entries[2].as_ref().unwrap().power_name,
+            "Tanker_Melee.Super_Strength.Knockout"
+        );
+    }
+
+    #[test]
+    fn test_validate_build_invalid_picks() {
+        let mut build: MidsBuild = serde_json::from_value(json!({
+            "Class": "Class_Tanker",
+            "PowerSets": ["Tanker_Melee.Super_Strength"],
+            "PowerEntries": [
+                {
+                    "PowerName": "Tanker_Melee.Super_Strength.Haymaker",
+                    "Level": 1,
+                    "SlotEntries": [
+                        { "Enhancement": { "Uid": "Crafted_Mako_A", "IoLevel": 4 } },
+                        { "Enhancement": { "Uid": "Crafted_Gone_A" } }
+                    ]
+                },
+                { "PowerName": "Tanker_Melee.Super_Strength.Foot_Stomp", "Level": 1 },
+                { "PowerName": "Tanker_Melee.Super_Strength.Hand_Clap", "Level": 1 }
+            ]
+        }))
+        .unwrap();
+        let validation = validate_build(&mut build, &make_powers_dict());
+        assert_eq!(
+            issue_kinds(&validation),
+            vec![
+                (
+                    "Tanker_Melee.Super_Strength.Haymaker",
+                    &BuildIssueKind::WrongLevel {
+                        picked: 2,
+                        min: 6,
+                        max: 50
+                    }
+                ),
+                (
+                    "Crafted_Mako_A",
+                    &BuildIssueKind::WrongLevel {
+                        picked: 5,
+                        min: 10,
+                        max: 50
+                    }
+                ),
+                (
+                    "Crafted_Mako_A",
+                    &BuildIssueKind::DisallowedSetCategory(String::from("Melee Damage"))
+                ),
+                ("Crafted_Gone_A", &BuildIssueKind::Removed),
+                (
+                    "Tanker_Melee.Super_Strength.Foot_Stomp",
+                    &BuildIssueKind::WrongArchetype
+                ),
+                (
+                    "Tanker_Melee.Super_Strength.Hand_Clap",
+                    &BuildIssueKind::Removed
+                ),
+            ]
+        );
+        assert!(!validation.corrected);
+        assert_eq!(
+            validation.issues[3].to_string(),
+            "Crafted_Gone_A (in Tanker_Melee.Super_Strength.Haymaker): not found"
+        );
+    }
+
+    #[test]
+    fn test_resolve_rename() {
+        let candidates = [
+            ("Tanker_Melee.Super_Strength.Jab", Some("Jab")),
+            ("Brute_Melee.Super_Strength.Jab", Some("Jab")),
+            (
+                "Tanker_Defense.Invulnerability.Resist_Physical_Damage",
+                None,
+            ),
+            ("Tanker_Melee.Super_Strength.Rage_v2", Some("Rage")),
+            (
+                "Tanker_Melee.Super_Strength.Knockout",
+                Some("Knockout Blow"),
+            ),
+        ];
+        // only one power with the same name
+        assert_eq!(
+            resolve_rename(
+                "Tanker_Defense.Invulnerability_Old.Resist_Physical_Damage",
+                &candidates,
+                &[]
+            ),
+            BuildIssueKind::Renamed(String::from(
+                "Tanker_Defense.Invulnerability.Resist_Physical_Damage"
+            ))
+        );
+        // same category wins
+        assert_eq!(
+            resolve_rename("Tanker_Melee.Strength.Jab", &candidates, &[]),
+            BuildIssueKind::Renamed(String::from("Tanker_Melee.Super_Strength.Jab"))
+        );
+        // then the build's power sets
+        assert_eq!(
+            resolve_rename(
+                "Melee.Strength.Jab",
+                &candidates,
+                &["Brute_Melee.Super_Strength"]
+            ),
+            BuildIssueKind::Renamed(String::from("Brute_Melee.Super_Strength.Jab"))
+        );
+        assert!(matches!(
+            resolve_rename("Melee.Strength.Jab", &candidates, &[]),
+            BuildIssueKind::Ambiguous(_)
+        ));
+        // the short name changed, but the display name still matches
+        assert_eq!(
+            resolve_rename("Tanker_Melee.Super_Strength.Rage", &candidates, &[]),
+            BuildIssueKind::Renamed(String::from("Tanker_Melee.Super_Strength.Rage_v2"))
+        );
+        assert_eq!(
+            resolve_rename(
+                "Tanker_Melee.Super_Strength.Knockout_Blow",
+                &candidates,
+                &[]
+            ),
+            BuildIssueKind::Renamed(String::from("Tanker_Melee.Super_Strength.Knockout"))
+        );
+        assert_eq!(
+            resolve_rename("Tanker_Melee.Super_Strength.Haymaker", &candidates, &[]),
+            BuildIssueKind::Removed
+        );
+    }
+}
@@ -1,4 +1,8 @@
+#[cfg(feature = "schema")]
+use schemars::{JsonSchema, Schema, SchemaGenerator};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+#[cfg(feature = "schema")]
+use std::borrow::Cow;
 use std::fmt;
 use std::hash::{Hash, Hasher};
 
@@ -44,6 +48,21 @@ impl NameKey {
             .contains(&other.to_ascii_lowercase())
     }
 
+    /// Tests if this `NameKey` matches `pattern`, a full name where any part can be a wildcard
+    /// (e.g. `Tanker_Melee.*.*`). A pattern with only one part is a `partial_match`.
+    pub fn pattern_match(&self, pattern: &str) -> bool {
+        if !pattern.contains(SEPARATOR) && pattern != WILDCARD {
+            return self.partial_match(pattern);
+        }
+        let parts = self.split();
+        let pattern_parts: Vec<_> = pattern.split(SEPARATOR).collect();
+        parts.len() == pattern_parts.len()
+            && parts
+                .iter()
+                .zip(pattern_parts)
+                .all(|(part, p)| p == WILDCARD || part.eq_ignore_ascii_case(p))
+    }
+
     /// Returns a collection of slices over the `NameKey`, based
     /// on seperating it using the default character (`.`).
     pub fn split(&self) -> Vec<&str>
@@ -58,6 +77,12 @@ impl From<String> for NameKey {
     }
 }
 
+impl AsRef<str> for NameKey {
+    fn as_ref(&self) -> &str {
+        self.get()
+    }
+}
+
 impl Eq for NameKey {}
 
 impl PartialEq for NameKey {
@@ -109,7 +134,8 @@ impl<'de> Deserialize<'de> for NameKey {
     where
         D: Deserializer<'de>,
     {
-        let s: &str = Deserialize::deserialize(deserializer)?;
+        // owned, since names may contain escapes when read from .json
+        let s: String = Deserialize::deserialize(deserializer)?;
         Ok(NameKey::new(s))
     }
 }
@@ -122,3 +148,35 @@ impl Serialize for NameKey {
         serializer.serialize_str(self.get())
     }
 }
+
+#[cfg(feature = "schema")]
+impl JsonSchema for NameKey {
+    fn inline_schema() -> bool {
+        true
+    }
+
+    fn schema_name() -> Cow<'static, str> {
+        Cow::Borrowed("NameKey")
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        // serialized as a plain string
+        String::json_schema(gen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_match() {
+        let name = NameKey::new("Tanker_Melee.Super_Strength.Jab");
+        assert!(name.pattern_match("Tanker_Melee.*.*"));
+        assert!(name.pattern_match("tanker_melee.super_strength.JAB"));
+        assert!(name.pattern_match("*.*.Jab"));
+        assert!(name.pattern_match("strength"));
+        assert!(!name.pattern_match("Tanker_Melee.*"));
+        assert!(!name.pattern_match("Tanker_Melee.*.Haymaker"));
+    }
+}
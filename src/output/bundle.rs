@@ -0,0 +1,227 @@
+use super::manifest::OutputFiles;
+use super::structs::*;
+use crate::structs::config::{BundleConfig, PowersConfig};
+use crate::structs::*;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+
+/// Extension added to the bundle file name when it's gzipped.
+const GZIP_EXTENSION: &'static str = ".gz";
+
+/// Fields holding links to other files in the hierarchical output, which are dropped from the
+/// bundle since everything can be found by key instead.
+const URL_FIELDS: &[&str] = &["url"];
+
+/// Header fields repeated in every file of the hierarchical output. The bundle only has them once.
+const HEADER_FIELDS: &[&str] = &["issue", "source", "extract_date"];
+
+/// Serializable representation of the whole dictionary in a single file.
+#[derive(Serialize)]
+struct BundleOutput {
+    #[serde(flatten)]
+    header: HeaderOutput,
+    /// The top level power categories, as listed in the root index.
+    root: Value,
+    /// Archetypes, keyed by name.
+    archetypes: BTreeMap<String, Value>,
+    /// Power categories, keyed by name.
+    power_categories: BTreeMap<String, Value>,
+    /// Power sets, keyed by full name.
+    power_sets: BTreeMap<String, Value>,
+    /// Powers, keyed by full name.
+    powers: BTreeMap<String, Value>,
+}
+
+/// Writes the entire powers dictionary as a single bundle file.
+///
+/// # Arguments:
+///
+/// * `files` - The output files being written.
+/// * `powers_dict` - A `PowersDictionary` containing a hierarchy of categories, power sets, and powers.
+/// * `bundle_config` - Bundle configuration information.
+/// * `config` - Configuration information.
+///
+/// # Returns:
+///
+/// Nothing if the operation was successful. Otherwise, an `io::Error` containing the error information.
+///
+/// # Notes:
+///
+/// The bundle holds the same data as the hierarchical .json files, but power categories, power sets,
+/// and powers are stored in maps keyed by name. Power sets list their powers by name in
+/// `ordered_power_names` rather than containing them, and the `url` fields linking files together are
+/// left out.
+pub fn write_bundle(
+    files: &mut OutputFiles,
+    powers_dict: &PowersDictionary,
+    bundle_config: &BundleConfig,
+    config: &PowersConfig,
+) -> io::Result<()> {
+    let bundle = BundleOutput::from_powers_dictionary(powers_dict, config)?;
+    let contents = super::to_json(&bundle, config)?;
+    if bundle_config.gzip {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&contents)?;
+        let file_name = format!("{}{}", bundle_config.file, GZIP_EXTENSION);
+        files.write(Path::new(&file_name), &encoder.finish()?)
+    } else {
+        files.write(Path::new(&bundle_config.file), &contents)
+    }
+}
+
+impl BundleOutput {
+    /// Collects the output for every category, power set, and power that would be written to the
+    /// hierarchical output.
+    fn from_powers_dictionary(
+        powers_dict: &PowersDictionary,
+        config: &PowersConfig,
+    ) -> io::Result<Self> {
+        let mut root = to_bundle_value(RootOutput::from_power_categories(
+            &powers_dict.power_categories,
+            config,
+        ))?;
+        let mut bundle = BundleOutput {
+            header: HeaderOutput::from_config(config),
+            root: root
+                .as_object_mut()
+                .and_then(|o| o.remove("power_categories"))
+                .unwrap_or(Value::Null),
+            archetypes: BTreeMap::new(),
+            power_categories: BTreeMap::new(),
+            power_sets: BTreeMap::new(),
+            powers: BTreeMap::new(),
+        };
+
+        let mut ats = to_bundle_value(ArchetypesOutput::from_archetypes(
+            &powers_dict.archetypes,
+            config,
+        ))?;
+        if let Some(Value::Array(ats)) = ats.as_object_mut().and_then(|o| o.remove("archetypes")) {
+            for at in ats {
+                insert_by_name(&mut bundle.archetypes, at);
+            }
+        }
+
+        for category in &powers_dict.power_categories {
+            if !category.include_in_output || category.pch_name.is_none() {
+                continue;
+            }
+            insert_by_name(
+                &mut bundle.power_categories,
+                to_bundle_value(PowerCategoryOutput::from_power_category(category, config))?,
+            );
+            for set in &category.pp_power_sets {
+                if !set.include_in_output {
+                    continue;
+                }
+                let mut pset = to_bundle_value(PowerSetOutput::from_base_power_set(
+                    set,
+                    &powers_dict.attrib_names,
+                    config,
+                ))?;
+                if let Some(Value::Array(powers)) =
+                    pset.as_object_mut().and_then(|o| o.remove("powers"))
+                {
+                    for power in powers {
+                        insert_by_name(&mut bundle.powers, power);
+                    }
+                }
+                insert_by_name(&mut bundle.power_sets, pset);
+            }
+        }
+        Ok(bundle)
+    }
+}
+
+/// Converts an output struct to a `Value`, dropping its header and any links to other files.
+fn to_bundle_value<T: Serialize>(output: T) -> io::Result<Value> {
+    let mut value = serde_json::to_value(output)?;
+    if let Some(object) = value.as_object_mut() {
+        for field in HEADER_FIELDS {
+            object.remove(*field);
+        }
+    }
+    remove_urls(&mut value);
+    Ok(value)
+}
+
+/// Recursively removes the `URL_FIELDS` from `value`.
+fn remove_urls(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for field in URL_FIELDS {
+                object.remove(*field);
+            }
+            object.values_mut().for_each(remove_urls);
+        }
+        Value::Array(values) => values.iter_mut().for_each(remove_urls),
+        _ => (),
+    }
+}
+
+/// Adds `value` to `map` using its `name` field as the key. Values without a name are skipped.
+fn insert_by_name(map: &mut BTreeMap<String, Value>, value: Value) {
+    if let Some(Value::String(name)) = value.get("name") {
+        map.insert(name.clone(), value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::tests::{make_powers_dict, TestOutput};
+    use flate2::read::GzDecoder;
+
+    #[test]
+    fn test_remove_urls() {
+        let mut value = serde_json::json!({
+            "name": "Ranged.Fire_Blast",
+            "url": "../../ranged/fire-blast/index.json",
+            "redirect": [{ "name": "Ranged.Fire_Blast.Flares", "url": "x" }],
+            "icon": "https://example.com/flares.png",
+        });
+        remove_urls(&mut value);
+        let expected = serde_json::json!({
+            "name": "Ranged.Fire_Blast",
+            "redirect": [{ "name": "Ranged.Fire_Blast.Flares" }],
+            "icon": "https://example.com/flares.png",
+        });
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn test_bundle() {
+        let mut output = TestOutput::new("bundle");
+        output.config.bundle = Some(BundleConfig {
+            gzip: true,
+            ..Default::default()
+        });
+        output.write(make_powers_dict()).unwrap();
+        let first = output.read("bundle.json.gz");
+
+        // only the date changed, so the bundle is left alone
+        output.config.extract_date = Some(chrono::Local::now() + chrono::Duration::days(1));
+        output.write(make_powers_dict()).unwrap();
+        assert!(output.read("bundle.json.gz") == first);
+
+        let mut contents = String::new();
+        GzDecoder::new(&first[..])
+            .read_to_string(&mut contents)
+            .unwrap();
+        let bundle: Value = serde_json::from_str(&contents).unwrap();
+        let pset = &bundle["power_sets"]["Ranged.Fire_Blast"];
+        assert!(pset.get("powers").is_none());
+        assert_eq!(pset["ordered_power_names"][0], "Ranged.Fire_Blast.Flares");
+        assert!(bundle["powers"]["Ranged.Fire_Blast.Flares"].is_object());
+        assert!(bundle["power_categories"]["Ranged"]["power_sets"][0]
+            .get("url")
+            .is_none());
+        assert!(bundle["archetypes"]["Class_Blaster"].is_object());
+    }
+}
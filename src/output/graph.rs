@@ -0,0 +1,593 @@
+use super::manifest::OutputFiles;
+use crate::structs::config::{GraphConfig, GraphFormatConfig};
+use crate::structs::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io;
+use std::io::prelude::*;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+/// Default names for the graph files.
+const DOT_FILE: &'static str = "graph.dot";
+const GRAPHML_FILE: &'static str = "graph.graphml";
+
+/// The kinds of things that show up as nodes in the graph.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum NodeType {
+    Category,
+    PowerSet,
+    Power,
+    Entity,
+    BoostSet,
+}
+
+impl NodeType {
+    fn get_string(&self) -> &'static str {
+        match self {
+            NodeType::Category => "Category",
+            NodeType::PowerSet => "PowerSet",
+            NodeType::Power => "Power",
+            NodeType::Entity => "Entity",
+            NodeType::BoostSet => "BoostSet",
+        }
+    }
+
+    /// Graphviz shape used to tell the node types apart.
+    fn get_shape(&self) -> &'static str {
+        match self {
+            NodeType::Category => "folder",
+            NodeType::PowerSet => "tab",
+            NodeType::Power => "box",
+            NodeType::Entity => "ellipse",
+            NodeType::BoostSet => "hexagon",
+        }
+    }
+}
+
+/// The kinds of relationships between nodes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+enum EdgeType {
+    /// Category -> power set, power set -> power.
+    Contains,
+    /// Power -> power granted through an `AttribModParam::Power` parameter.
+    Grants,
+    /// Power -> power it redirects to.
+    RedirectsTo,
+    /// Power -> entity created through an `AttribModParam::EntCreate` parameter.
+    Creates,
+    /// Entity -> power used by the entity.
+    HasPower,
+    /// Power -> power named by `pch_chain_into_power_name`.
+    ChainsInto,
+    /// Boost set -> power listed in the set.
+    Member,
+    /// Boost set -> power granted as a set bonus.
+    Bonus,
+}
+
+impl EdgeType {
+    fn get_string(&self) -> &'static str {
+        match self {
+            EdgeType::Contains => "Contains",
+            EdgeType::Grants => "Grants",
+            EdgeType::RedirectsTo => "RedirectsTo",
+            EdgeType::Creates => "Creates",
+            EdgeType::HasPower => "HasPower",
+            EdgeType::ChainsInto => "ChainsInto",
+            EdgeType::Member => "Member",
+            EdgeType::Bonus => "Bonus",
+        }
+    }
+}
+
+struct Node {
+    node_type: NodeType,
+    name: String,
+    label: Option<String>,
+}
+
+struct Edge {
+    from: usize,
+    to: usize,
+    edge_type: EdgeType,
+}
+
+/// A directed graph of the relationships in a `PowersDictionary`.
+struct PowerGraph {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+    /// Looks up nodes by type and lowercase name.
+    index: HashMap<(&'static str, String), usize>,
+    /// Used to skip duplicate edges.
+    edge_set: HashSet<(usize, usize, EdgeType)>,
+}
+
+impl PowerGraph {
+    fn new() -> Self {
+        PowerGraph {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            index: HashMap::new(),
+            edge_set: HashSet::new(),
+        }
+    }
+
+    /// Builds the graph from everything marked for output in `powers_dict`.
+    fn from_powers_dictionary(powers_dict: &PowersDictionary) -> Self {
+        let mut graph = PowerGraph::new();
+        for pcat in &powers_dict.power_categories {
+            if !pcat.include_in_output {
+                continue;
+            }
+            let pcat_name = match &pcat.pch_name {
+                Some(name) => name,
+                None => continue,
+            };
+            let pcat_node = graph.add_node(
+                NodeType::Category,
+                pcat_name.get(),
+                pcat.pch_display_name.as_ref(),
+            );
+            for pset in &pcat.pp_power_sets {
+                if !pset.include_in_output {
+                    continue;
+                }
+                let pset_name = match &pset.pch_full_name {
+                    Some(name) => name,
+                    None => continue,
+                };
+                let pset_node = graph.add_node(
+                    NodeType::PowerSet,
+                    pset_name.get(),
+                    pset.pch_display_name.as_ref(),
+                );
+                graph.add_edge(pcat_node, pset_node, EdgeType::Contains);
+                for power in &pset.pp_powers {
+                    if power.include_in_output {
+                        graph.add_power(pset_node, power);
+                    }
+                }
+            }
+        }
+        // boost sets only link to powers that are already in the graph
+        let mut boost_sets: Vec<_> = powers_dict.boost_sets.values().collect();
+        boost_sets.sort_by(|a, b| {
+            let a = a.pch_name.as_ref().map(|n| n.get());
+            a.cmp(&b.pch_name.as_ref().map(|n| n.get()))
+        });
+        for boost_set in boost_sets {
+            graph.add_boost_set(boost_set);
+        }
+        graph
+    }
+
+    /// Adds `power` and all of its outgoing links.
+    fn add_power(&mut self, pset_node: usize, power: &BasePower) {
+        let power_name = match &power.pch_full_name {
+            Some(name) => name,
+            None => return,
+        };
+        let power_node = self.add_node(
+            NodeType::Power,
+            power_name.get(),
+            power.pch_display_name.as_ref(),
+        );
+        self.add_edge(pset_node, power_node, EdgeType::Contains);
+        for redirect in &power.pp_redirect {
+            if let Some(target) = &redirect.pch_name {
+                self.add_power_edge(power_node, target.get(), EdgeType::RedirectsTo);
+            }
+        }
+        if let Some(target) = &power.pch_chain_into_power_name {
+            self.add_power_edge(power_node, target, EdgeType::ChainsInto);
+        }
+        for effect_group in &power.pp_effects {
+            self.add_effect_group(power_node, effect_group);
+        }
+    }
+
+    /// Adds links from the attrib mod parameters in `effect_group` (and its children).
+    fn add_effect_group(&mut self, power_node: usize, effect_group: &EffectGroup) {
+        for template in &effect_group.pp_templates {
+            match &template.p_params {
+                Some(AttribModParam::Power(p)) => {
+                    for target in &p.ppch_power_names {
+                        self.add_power_edge(power_node, target.get(), EdgeType::Grants);
+                    }
+                }
+                Some(AttribModParam::EntCreate(e)) => {
+                    for target in &e.redirects {
+                        self.add_power_edge(power_node, target.get(), EdgeType::RedirectsTo);
+                    }
+                    if let Some(entity_def_name) = &e.pch_entity_def {
+                        let entity_node = self.add_node(
+                            NodeType::Entity,
+                            entity_def_name.get(),
+                            e.pch_display_name.as_ref(),
+                        );
+                        self.add_edge(power_node, entity_node, EdgeType::Creates);
+                        for target in &e.power_refs {
+                            self.add_power_edge(entity_node, target.get(), EdgeType::HasPower);
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+        for child in &effect_group.pp_effects {
+            self.add_effect_group(power_node, child);
+        }
+    }
+
+    /// Adds a boost set if any of the powers it refers to are in the graph.
+    fn add_boost_set(&mut self, boost_set: &BoostSet) {
+        let boost_set_name = match &boost_set.pch_name {
+            Some(name) => name,
+            None => return,
+        };
+        let mut targets = Vec::new();
+        for power_name in &boost_set.ppch_powers {
+            if let Some(i) = self.find_node(NodeType::Power, power_name.get()) {
+                targets.push((i, EdgeType::Member));
+            }
+        }
+        for bonus in &boost_set.pp_bonuses {
+            for power_name in bonus.ppch_auto_powers.iter().chain(&bonus.pch_bonus_power) {
+                if let Some(i) = self.find_node(NodeType::Power, power_name.get()) {
+                    targets.push((i, EdgeType::Bonus));
+                }
+            }
+        }
+        if targets.is_empty() {
+            return;
+        }
+        let boost_set_node = self.add_node(
+            NodeType::BoostSet,
+            boost_set_name.get(),
+            boost_set.pch_display_name.as_ref(),
+        );
+        for (target, edge_type) in targets {
+            self.add_edge(boost_set_node, target, edge_type);
+        }
+    }
+
+    /// Gets the index of an existing node.
+    fn find_node(&self, node_type: NodeType, name: &str) -> Option<usize> {
+        self.index
+            .get(&(node_type.get_string(), name.to_ascii_lowercase()))
+            .copied()
+    }
+
+    /// Gets the index of a node, adding it if it doesn't exist yet. Labels are
+    /// filled in later if the node was first seen as the target of an edge.
+    fn add_node(&mut self, node_type: NodeType, name: &str, label: Option<&String>) -> usize {
+        if let Some(i) = self.find_node(node_type, name) {
+            if self.nodes[i].label.is_none() {
+                self.nodes[i].label = label.cloned();
+            }
+            return i;
+        }
+        let i = self.nodes.len();
+        self.nodes.push(Node {
+            node_type,
+            name: name.to_owned(),
+            label: label.cloned(),
+        });
+        self.index
+            .insert((node_type.get_string(), name.to_ascii_lowercase()), i);
+        i
+    }
+
+    /// Adds an edge from `from` to a power called `target`.
+    fn add_power_edge(&mut self, from: usize, target: &str, edge_type: EdgeType) {
+        let to = self.add_node(NodeType::Power, target, None);
+        self.add_edge(from, to, edge_type);
+    }
+
+    /// Adds an edge, ignoring duplicates.
+    fn add_edge(&mut self, from: usize, to: usize, edge_type: EdgeType) {
+        if self.edge_set.insert((from, to, edge_type)) {
+            self.edges.push(Edge {
+                from,
+                to,
+                edge_type,
+            });
+        }
+    }
+
+    /// Reduces the graph to the nodes reachable from `root` (a category, power set,
+    /// or power name).
+    ///
+    /// # Returns:
+    ///
+    /// The subgraph, or `None` if `root` isn't in the graph.
+    fn subgraph(self, root: &NameKey) -> Option<PowerGraph> {
+        let root_node = [NodeType::Category, NodeType::PowerSet, NodeType::Power]
+            .iter()
+            .find_map(|t| self.find_node(*t, root.get()))?;
+        // breadth-first search following outgoing edges
+        let mut outgoing = vec![Vec::new(); self.nodes.len()];
+        for edge in &self.edges {
+            outgoing[edge.from].push(edge.to);
+        }
+        let mut reachable = vec![false; self.nodes.len()];
+        let mut queue = VecDeque::new();
+        reachable[root_node] = true;
+        queue.push_back(root_node);
+        while let Some(i) = queue.pop_front() {
+            for &to in &outgoing[i] {
+                if !reachable[to] {
+                    reachable[to] = true;
+                    queue.push_back(to);
+                }
+            }
+        }
+        // copy over what we found, renumbering the nodes as we go
+        let mut sub = PowerGraph::new();
+        let mut renumber = vec![0; self.nodes.len()];
+        for (i, node) in self.nodes.into_iter().enumerate() {
+            if reachable[i] {
+                renumber[i] = sub.add_node(node.node_type, &node.name, node.label.as_ref());
+            }
+        }
+        for edge in self.edges {
+            if reachable[edge.from] && reachable[edge.to] {
+                sub.add_edge(renumber[edge.from], renumber[edge.to], edge.edge_type);
+            }
+        }
+        Some(sub)
+    }
+
+    /// Unique identifier for a node in the output files.
+    fn node_id(&self, i: usize) -> String {
+        let node = &self.nodes[i];
+        format!("{}:{}", node.node_type.get_string(), node.name)
+    }
+
+    /// Writes the graph in Graphviz DOT format.
+    fn write_dot<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(w, "digraph powers {{")?;
+        writeln!(w, "    rankdir=LR;")?;
+        for (i, node) in self.nodes.iter().enumerate() {
+            writeln!(
+                w,
+                "    \"{}\" [label=\"{}\", shape={}, type=\"{}\"];",
+                escape_dot(&self.node_id(i)),
+                escape_dot(node.label.as_ref().unwrap_or(&node.name)),
+                node.node_type.get_shape(),
+                node.node_type.get_string(),
+            )?;
+        }
+        for edge in &self.edges {
+            writeln!(
+                w,
+                "    \"{}\" -> \"{}\" [label=\"{}\"];",
+                escape_dot(&self.node_id(edge.from)),
+                escape_dot(&self.node_id(edge.to)),
+                edge.edge_type.get_string(),
+            )?;
+        }
+        writeln!(w, "}}")?;
+        Ok(())
+    }
+
+    /// Writes the graph in GraphML format.
+    fn write_graphml<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(w, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        writeln!(
+            w,
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">"
+        )?;
+        writeln!(
+            w,
+            "  <key id=\"type\" for=\"node\" attr.name=\"type\" attr.type=\"string\"/>"
+        )?;
+        writeln!(
+            w,
+            "  <key id=\"name\" for=\"node\" attr.name=\"name\" attr.type=\"string\"/>"
+        )?;
+        writeln!(
+            w,
+            "  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>"
+        )?;
+        writeln!(
+            w,
+            "  <key id=\"relation\" for=\"edge\" attr.name=\"relation\" attr.type=\"string\"/>"
+        )?;
+        writeln!(w, "  <graph id=\"powers\" edgedefault=\"directed\">")?;
+        for (i, node) in self.nodes.iter().enumerate() {
+            writeln!(w, "    <node id=\"{}\">", escape_xml(&self.node_id(i)))?;
+            writeln!(
+                w,
+                "      <data key=\"type\">{}</data>",
+                node.node_type.get_string()
+            )?;
+            writeln!(
+                w,
+                "      <data key=\"name\">{}</data>",
+                escape_xml(&node.name)
+            )?;
+            if let Some(label) = &node.label {
+                writeln!(w, "      <data key=\"label\">{}</data>", escape_xml(label))?;
+            }
+            writeln!(w, "    </node>")?;
+        }
+        for edge in &self.edges {
+            writeln!(
+                w,
+                "    <edge source=\"{}\" target=\"{}\">",
+                escape_xml(&self.node_id(edge.from)),
+                escape_xml(&self.node_id(edge.to)),
+            )?;
+            writeln!(
+                w,
+                "      <data key=\"relation\">{}</data>",
+                edge.edge_type.get_string()
+            )?;
+            writeln!(w, "    </edge>")?;
+        }
+        writeln!(w, "  </graph>")?;
+        writeln!(w, "</graphml>")?;
+        Ok(())
+    }
+}
+
+/// Escapes a string for use inside a quoted DOT identifier.
+fn escape_dot(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Escapes a string for use inside XML text or attributes.
+fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Writes the relationships between categories, power sets, powers, entities, and boost sets
+/// as graph files.
+///
+/// # Arguments:
+///
+/// * `files` - The output files being written.
+/// * `powers_dict` - A `PowersDictionary` containing a hierarchy of categories, power sets, and powers.
+/// * `graph_config` - Graph configuration information.
+///
+/// # Returns:
+///
+/// Nothing if the operation was successful. Otherwise, an `io::Error` containing the error information.
+pub fn write_graph(
+    files: &mut OutputFiles,
+    powers_dict: &PowersDictionary,
+    graph_config: &GraphConfig,
+) -> io::Result<()> {
+    let mut graph = PowerGraph::from_powers_dictionary(powers_dict);
+    if let Some(root) = &graph_config.root {
+        graph = graph.subgraph(root).ok_or_else(|| {
+            Error::new(
+                ErrorKind::Other,
+                format!("Graph root {} was not found in the powers dictionary", root),
+            )
+        })?;
+    }
+    for format in &graph_config.formats {
+        let mut buf = Vec::new();
+        let output_file = match format {
+            GraphFormatConfig::Dot => {
+                graph.write_dot(&mut buf)?;
+                DOT_FILE
+            }
+            GraphFormatConfig::Graphml => {
+                graph.write_graphml(&mut buf)?;
+                GRAPHML_FILE
+            }
+        };
+        files.write(Path::new(output_file), &buf)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::tests::make_powers_dict;
+    use std::rc::Rc;
+
+    /// The shared dictionary, with Flares granting a temp power that's outside of any
+    /// category in the output, and a second power set.
+    fn make_graph_dict() -> PowersDictionary {
+        let mut powers_dict = make_powers_dict();
+        let pcat = Rc::get_mut(&mut powers_dict.power_categories[0]).unwrap();
+        let pset = Rc::get_mut(&mut pcat.pp_power_sets[0]).unwrap();
+        let flares = Rc::get_mut(&mut pset.pp_powers[0]).unwrap();
+        flares.pch_display_name = Some(String::from("Say \"Flares\" & <Burn>"));
+        let mut grant = AttribModParam_Power::new();
+        grant
+            .ppch_power_names
+            .push(NameKey::new("Temporary_Powers.Temporary_Powers.Burning"));
+        let mut template = AttribModTemplate::new();
+        template.p_params = Some(AttribModParam::Power(grant));
+        Rc::get_mut(&mut flares.pp_effects[0])
+            .unwrap()
+            .pp_templates
+            .push(template);
+
+        let mut ice_bolt = BasePower::new();
+        ice_bolt.pch_full_name = Some(NameKey::new("Ranged.Ice_Blast.Ice_Bolt"));
+        ice_bolt.pch_display_name = Some(String::from("Ice Bolt"));
+        ice_bolt.include_in_output = true;
+        let mut ice_blast = BasePowerSet::new();
+        ice_blast.pch_full_name = Some(NameKey::new("Ranged.Ice_Blast"));
+        ice_blast.include_in_output = true;
+        ice_blast.pp_powers.push(Rc::new(ice_bolt));
+        pcat.pp_power_sets.push(Rc::new(ice_blast));
+        powers_dict
+    }
+
+    fn node_names(graph: &PowerGraph) -> Vec<&str> {
+        graph.nodes.iter().map(|n| n.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_subgraph() {
+        let graph = PowerGraph::from_powers_dictionary(&make_graph_dict());
+        assert_eq!(graph.nodes.len(), 6);
+
+        let sub = graph.subgraph(&NameKey::new("ranged.fire_blast")).unwrap();
+        assert_eq!(
+            node_names(&sub),
+            vec![
+                "Ranged.Fire_Blast",
+                "Ranged.Fire_Blast.Flares",
+                "Temporary_Powers.Temporary_Powers.Burning",
+            ]
+        );
+        let edges: Vec<_> = sub
+            .edges
+            .iter()
+            .map(|e| (e.from, e.to, e.edge_type))
+            .collect();
+        assert_eq!(
+            edges,
+            vec![(0, 1, EdgeType::Contains), (1, 2, EdgeType::Grants)]
+        );
+
+        let graph = PowerGraph::from_powers_dictionary(&make_graph_dict());
+        assert!(graph.subgraph(&NameKey::new("Melee")).is_none());
+    }
+
+    #[test]
+    fn test_write_dot_escapes_names() {
+        let graph = PowerGraph::from_powers_dictionary(&make_graph_dict());
+        let mut buf = Vec::new();
+        graph.write_dot(&mut buf).unwrap();
+        let dot = String::from_utf8(buf).unwrap();
+        assert!(dot.contains(
+            "\"Power:Ranged.Fire_Blast.Flares\" [label=\"Say \\\"Flares\\\" & <Burn>\", shape=box, type=\"Power\"];"
+        ));
+        assert_eq!(escape_dot("a\\b\nc"), "a\\\\b\\nc");
+    }
+
+    #[test]
+    fn test_write_graphml_escapes_names() {
+        let graph = PowerGraph::from_powers_dictionary(&make_graph_dict());
+        let mut buf = Vec::new();
+        graph.write_graphml(&mut buf).unwrap();
+        let graphml = String::from_utf8(buf).unwrap();
+        assert!(graphml
+            .contains("<data key=\"label\">Say &quot;Flares&quot; &amp; &lt;Burn&gt;</data>"));
+        assert!(!graphml.contains("& <"));
+        assert_eq!(escape_xml("'a'"), "&apos;a&apos;");
+    }
+}
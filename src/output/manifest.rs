@@ -0,0 +1,361 @@
+use super::structs::HeaderOutput;
+use super::JSON_FILE;
+use crate::structs::config::{OutputPolicyConfig, PowersConfig};
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+/// Name of the manifest file, written to the root of the output path.
+pub const MANIFEST_FILE: &'static str = "manifest.json";
+
+/// Header fields that change on every run. If these are the only differences from the file
+/// already on disk, the file is left alone.
+const VOLATILE_FIELDS: &[&str] = &["extract_date"];
+
+/// A file listed in the manifest.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ManifestEntry {
+    /// Path of the file relative to the output path, using `/` as the separator.
+    pub path: String,
+    /// MD5 hash of the file's contents, as lowercase hex.
+    pub md5: String,
+    /// Size of the file in bytes.
+    pub size: u64,
+}
+
+/// The manifest of every file in the output path.
+#[derive(Deserialize, Serialize)]
+pub struct Manifest {
+    #[serde(flatten, skip_deserializing)]
+    pub header: Option<HeaderOutput>,
+    pub files: Vec<ManifestEntry>,
+}
+
+/// Writes files to a staging directory, keeping the existing copies of any that haven't changed
+/// since the last run and keeping track of everything written in a manifest.
+pub struct OutputFiles {
+    /// The output path, where the files from the last run are.
+    live: PathBuf,
+    /// Where the files for this run are written.
+    staging: PathBuf,
+    /// Files listed in the manifest from the previous run, keyed by path. Without a manifest, these
+    /// are the `index.json` files found in the output path instead.
+    previous: HashMap<String, ManifestEntry>,
+    /// Files written (or left alone) during this run, keyed by path.
+    entries: BTreeMap<String, ManifestEntry>,
+    written: usize,
+    unchanged: usize,
+}
+
+impl OutputFiles {
+    /// Prepares to write files to `staging`, reading the manifest from the previous run in `live`
+    /// if there is one.
+    ///
+    /// # Arguments:
+    ///
+    /// * `live` - The output path.
+    /// * `staging` - The staging directory.
+    ///
+    /// # Returns:
+    ///
+    /// An `OutputFiles` if successful. Otherwise, an `io::Error` containing the error information.
+    ///
+    /// # Notes:
+    ///
+    /// Output written before manifests existed has nothing to say which files came from this tool,
+    /// so every `index.json` in the output path is treated as part of the previous run. That way
+    /// categories and power sets that no longer exist are still dropped under `merge`.
+    pub fn open(live: &Path, staging: &Path) -> io::Result<Self> {
+        let mut previous = HashMap::new();
+        let manifest_file = live.join(MANIFEST_FILE);
+        if manifest_file.is_file() {
+            let manifest: Manifest = serde_json::from_slice(&fs::read(&manifest_file)?)?;
+            for entry in manifest.files {
+                previous.insert(entry.path.clone(), entry);
+            }
+        } else if live.is_dir() {
+            let mut files = Vec::new();
+            find_files(live, &mut files)?;
+            for path in files {
+                if path.file_name().map_or(true, |name| name != JSON_FILE) {
+                    continue;
+                }
+                let contents = fs::read(&path)?;
+                let key = manifest_path(path.strip_prefix(live).unwrap_or(&path));
+                previous.insert(
+                    key.clone(),
+                    ManifestEntry {
+                        path: key,
+                        md5: format!("{:x}", md5::compute(&contents)),
+                        size: contents.len() as u64,
+                    },
+                );
+            }
+        }
+        Ok(OutputFiles {
+            live: live.to_owned(),
+            staging: staging.to_owned(),
+            previous,
+            entries: BTreeMap::new(),
+            written: 0,
+            unchanged: 0,
+        })
+    }
+
+    /// Writes `contents` to `path` (relative to the output path) in the staging directory. If the file
+    /// in the output path has the same contents, it's linked instead.
+    ///
+    /// # Arguments:
+    ///
+    /// * `path` - Path of the file relative to the output path.
+    /// * `contents` - The contents of the file.
+    ///
+    /// # Returns:
+    ///
+    /// Nothing if the operation was successful. Otherwise, an `io::Error` containing the error information.
+    ///
+    /// # Notes:
+    ///
+    /// .json files (gzipped or not) that only differ in their `extract_date` are considered unchanged,
+    /// so the existing file (and its date) is kept. Unchanged files are hard linked into the staging
+    /// directory rather than rewritten, so their modification times don't change either.
+    pub fn write(&mut self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let output_file = self.staging.join(path);
+        let key = manifest_path(path);
+        let mut entry = ManifestEntry {
+            path: key.clone(),
+            md5: format!("{:x}", md5::compute(contents)),
+            size: contents.len() as u64,
+        };
+        if let Some(parent) = output_file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        match fs::read(self.live.join(path)) {
+            Ok(existing) if is_unchanged(path, &existing, contents) => {
+                entry.md5 = format!("{:x}", md5::compute(&existing));
+                entry.size = existing.len() as u64;
+                link_or_copy(&self.live.join(path), &output_file)?;
+                self.unchanged += 1;
+            }
+            _ => {
+                println!("Writing: {} ...", self.live.join(path).display());
+                fs::write(&output_file, contents)?;
+                self.written += 1;
+            }
+        }
+        self.entries.insert(key, entry);
+        Ok(())
+    }
+
+    /// Drops files that were listed in the previous manifest but weren't written this time, then
+    /// writes the new manifest.
+    ///
+    /// # Arguments:
+    ///
+    /// * `config` - Configuration information.
+    ///
+    /// # Returns:
+    ///
+    /// Nothing if the operation was successful. Otherwise, an `io::Error` containing the error information.
+    ///
+    /// # Notes:
+    ///
+    /// With the `merge` policy, any other files in the output path (ones that were never listed in a
+    /// manifest) are linked into the staging directory as well.
+    pub fn finish(self, config: &PowersConfig) -> io::Result<()> {
+        // stale files are left out of the staging directory
+        let mut stale: Vec<_> = self
+            .previous
+            .keys()
+            .filter(|path| !self.entries.contains_key(*path))
+            .collect();
+        stale.sort();
+        for path in &stale {
+            println!("Removing: {} ...", self.live.join(path).display());
+        }
+
+        // bring along anything else that was in the output path
+        if config.output_policy == OutputPolicyConfig::Merge && self.live.is_dir() {
+            self.copy_other_files(&self.live)?;
+        }
+
+        // write the manifest
+        let manifest = Manifest {
+            header: Some(HeaderOutput::from_config(config)),
+            files: self.entries.into_iter().map(|(_, entry)| entry).collect(),
+        };
+        println!("Writing: {} ...", self.live.join(MANIFEST_FILE).display());
+        fs::write(
+            self.staging.join(MANIFEST_FILE),
+            serde_json::to_vec_pretty(&manifest)?,
+        )?;
+        println!(
+            "{} files written, {} unchanged, {} removed.",
+            self.written,
+            self.unchanged,
+            stale.len()
+        );
+        Ok(())
+    }
+
+    /// Links the files under `dir` in the output path that aren't part of the output into the
+    /// staging directory.
+    fn copy_other_files(&self, dir: &Path) -> io::Result<()> {
+        let mut files = Vec::new();
+        find_files(dir, &mut files)?;
+        for path in files {
+            let relative_path = path.strip_prefix(&self.live).unwrap_or(&path);
+            let key = manifest_path(relative_path);
+            if key == MANIFEST_FILE
+                || self.entries.contains_key(&key)
+                || self.previous.contains_key(&key)
+            {
+                continue;
+            }
+            let output_file = self.staging.join(relative_path);
+            if let Some(parent) = output_file.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            link_or_copy(&path, &output_file)?;
+        }
+        Ok(())
+    }
+}
+
+/// Recursively lists the files under `dir`.
+fn find_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Hard links `from` to `to`, so the file keeps its contents and modification time without being
+/// rewritten. Falls back to copying if the file system can't link them.
+fn link_or_copy(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::hard_link(from, to).is_err() {
+        fs::copy(from, to)?;
+    }
+    Ok(())
+}
+
+/// Decompresses gzipped `bytes`.
+fn gunzip(bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    GzDecoder::new(bytes).read_to_end(&mut decompressed).ok()?;
+    Some(decompressed)
+}
+
+/// Converts a relative path to the form used in the manifest.
+pub(super) fn manifest_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Tests if the `existing` contents of the file at `path` are the same as the new `contents`.
+fn is_unchanged(path: &Path, existing: &[u8], contents: &[u8]) -> bool {
+    if existing == contents {
+        return true;
+    }
+    if path.extension().map_or(false, |ext| ext == "gz") {
+        // compare what's inside instead
+        return match (gunzip(existing), gunzip(contents)) {
+            (Some(a), Some(b)) => is_unchanged(&path.with_extension(""), &a, &b),
+            _ => false,
+        };
+    }
+    if path.extension().map_or(true, |ext| ext != "json") {
+        return false;
+    }
+    let parse = |bytes: &[u8]| -> Option<Value> {
+        let mut value: Value = serde_json::from_slice(bytes).ok()?;
+        if let Some(object) = value.as_object_mut() {
+            for field in VOLATILE_FIELDS {
+                object.remove(*field);
+            }
+        }
+        Some(value)
+    };
+    match (parse(existing), parse(contents)) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::tests::make_config;
+    use std::process;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_is_unchanged() {
+        let path = Path::new("blaster-ranged/index.json");
+        let old = br#"{"issue":"I27","extract_date":"2020-01-01T00:00:00+00:00","name":"A"}"#;
+        let new = br#"{"issue":"I27","extract_date":"2020-02-01T00:00:00+00:00","name":"A"}"#;
+        assert!(is_unchanged(path, old, old));
+        assert!(is_unchanged(path, old, new));
+        let changed = br#"{"issue":"I27","extract_date":"2020-02-01T00:00:00+00:00","name":"B"}"#;
+        assert!(!is_unchanged(path, old, changed));
+        // only .json files are compared as json
+        assert!(!is_unchanged(Path::new("graph.dot"), old, new));
+    }
+
+    #[test]
+    fn test_without_manifest() {
+        let base_path =
+            std::env::temp_dir().join(format!("powers-test-manifest-{}", process::id()));
+        let _ = fs::remove_dir_all(&base_path);
+        let live = base_path.join("live");
+        let staging = base_path.join("staging");
+        let kept_file = Path::new("ranged/fire-blast/index.json");
+        let stale_file = Path::new("ranged/old-set/index.json");
+        let other_file = Path::new("ranged/notes.txt");
+        for path in &[kept_file, stale_file, other_file] {
+            fs::create_dir_all(live.join(path).parent().unwrap()).unwrap();
+            fs::write(live.join(path), "{}").unwrap();
+        }
+        fs::create_dir_all(&staging).unwrap();
+
+        // make the existing files old enough that rewriting them would show
+        let old_time = SystemTime::now() - Duration::from_secs(24 * 60 * 60);
+        for path in &[kept_file, other_file] {
+            let f = fs::File::options()
+                .write(true)
+                .open(live.join(path))
+                .unwrap();
+            f.set_modified(old_time).unwrap();
+        }
+
+        let mut files = OutputFiles::open(&live, &staging).unwrap();
+        assert_eq!(files.previous.len(), 2);
+        files.write(kept_file, b"{}").unwrap();
+        let mut config = make_config(&live);
+        config.output_policy = OutputPolicyConfig::Merge;
+        files.finish(&config).unwrap();
+
+        let stale_exists = staging.join(stale_file).exists();
+        let mtimes: Vec<_> = [kept_file, other_file]
+            .iter()
+            .map(|path| fs::metadata(staging.join(path)).and_then(|m| m.modified()))
+            .collect();
+        let _ = fs::remove_dir_all(&base_path);
+        assert!(!stale_exists);
+        for mtime in mtimes {
+            assert_eq!(mtime.unwrap(), old_time);
+        }
+    }
+}
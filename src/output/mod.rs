@@ -1,19 +1,28 @@
-mod structs;
+mod bundle;
+mod graph;
+mod manifest;
+mod publish;
+mod schema;
+mod sqlite;
+pub mod structs;
+mod tabular;
 
-use crate::structs::config::{OutputStyleConfig, PowersConfig};
+use crate::structs::config::{OutputPolicyConfig, OutputStyleConfig, PowersConfig};
 use crate::structs::{
     Archetype, AttribNames, BasePowerSet, Keyed, PowerCategory, PowersDictionary,
 };
-use std::fs;
+use manifest::OutputFiles;
+use schema::{OutputSchema, Schemas};
+use serde::Serialize;
+use std::collections::BTreeMap;
 use std::io;
-use std::io::prelude::*;
 use std::io::{Error, ErrorKind};
 use std::path::Path;
 use std::rc::Rc;
 use structs::*;
 
 /// Default name for the .json files.
-const JSON_FILE: &'static str = "index.json";
+pub(crate) use powers::client::JSON_FILE;
 
 /// Begins the process of writing the entire powers dictionary to disk as .json files.
 ///
@@ -33,133 +42,286 @@ const JSON_FILE: &'static str = "index.json";
 /// using a URL that looks like this:
 ///
 /// `http://myserver/powers/tanker-melee/super-strength/`
+///
+/// Everything is written to a staging directory next to the output path, which is swapped into place once
+/// all of the files have been written. If anything goes wrong, the output path is left untouched. The swap
+/// isn't atomic, so an output path left moved aside by an interrupted run is put back before starting. What
+/// happens to the files already in the output path depends on `output_policy`.
+///
+/// Files whose contents haven't changed since the last run keep their existing copies, files from categories
+/// and power sets that no longer exist are dropped, and a `manifest.json` listing every file with its MD5
+/// hash and size is written to the root of the output path.
+///
+/// If `schema` is configured, JSON Schema documents generated from the output structs are written as well,
+/// and every .json file is checked against its schema before it's written.
 pub fn write_powers_dictionary(
     powers_dict: PowersDictionary,
     config: &PowersConfig,
 ) -> io::Result<()> {
-    // setup the output directory
     let output_path = Path::new(&config.output_path);
-    fs::create_dir_all(&output_path)?;
-    if output_path.read_dir()?.count() > 0 {
-        print!(
-            "WARNING! The output path {} is not empty. Overwrite? (y/n)",
-            output_path.display()
-        );
-        io::stdout().flush()?;
-        //TODO: better input handling
-        for c in io::stdin().lock().bytes() {
-            match c? {
-                b'y' | b'Y' => break,
-                b'n' | b'N' => return Err(Error::from(ErrorKind::Interrupted)),
-                _ => (),
-            }
+    publish::recover_backup(output_path)?;
+    if config.output_policy == OutputPolicyConfig::FailIfExists
+        && publish::is_non_empty_dir(output_path)?
+    {
+        return Err(Error::new(
+            ErrorKind::Other,
+            format!(
+                "The output path {} is not empty and the output policy is {}",
+                output_path.display(),
+                config.output_policy.get_string()
+            ),
+        ));
+    }
+
+    // write everything to the staging directory, then swap it into place
+    let staging = publish::StagingDir::create(output_path)?;
+    match write_staged(&powers_dict, output_path, staging.path(), config) {
+        Ok(()) => staging.publish(),
+        Err(e) => {
+            staging.discard();
+            Err(e)
         }
-        println!();
     }
+}
 
+/// Writes all of the output files to the staging directory.
+fn write_staged(
+    powers_dict: &PowersDictionary,
+    output_path: &Path,
+    staging_path: &Path,
+    config: &PowersConfig,
+) -> io::Result<()> {
+    let mut files = OutputFiles::open(output_path, staging_path)?;
+
+    // generate the schemas so every .json file can be checked as it's written
+    let schemas = match &config.schema {
+        Some(schema_config) => Some(Schemas::generate(schema_config)?),
+        None => None,
+    };
+
+    // write the root, archetypes, categories, and power sets
+    write_json_tree(&mut files, powers_dict, schemas.as_ref(), config)?;
+
+    // write the schemas
+    if let (Some(schema_config), Some(schemas)) = (&config.schema, &schemas) {
+        schemas.write(&mut files, schema_config, config)?;
+    }
+
+    // write the relationship graph
+    if let Some(graph_config) = &config.graph {
+        graph::write_graph(&mut files, powers_dict, graph_config)?;
+    }
+
+    // write the single-file bundle
+    if let Some(bundle_config) = &config.bundle {
+        bundle::write_bundle(&mut files, powers_dict, bundle_config, config)?;
+    }
+
+    // write the SQLite database
+    if let Some(sqlite_config) = &config.sqlite {
+        sqlite::write_sqlite_database(&mut files, powers_dict, sqlite_config, config)?;
+    }
+
+    // write the spreadsheet tables
+    if let Some(tables_config) = &config.tables {
+        tabular::write_tables(&mut files, powers_dict, tables_config, config)?;
+    }
+
+    // remove stale files and write the manifest
+    files.finish(config)
+}
+
+/// Renders the .json hierarchy in memory instead of writing it to disk.
+///
+/// # Arguments:
+///
+/// * `powers_dict` - A `PowersDictionary` containing a hierarchy of categories, power sets, and powers.
+/// * `config` - Configuration information.
+///
+/// # Returns:
+///
+/// The contents of each .json file keyed by its path relative to the output path (e.g.
+/// `ranged/fire-blast/index.json`) if successful. Otherwise, an `io::Error` containing the error information.
+///
+/// # Notes:
+///
+/// Only the root, archetypes, power category, and power set files are rendered. They're checked against
+/// their schemas if `schema` is configured, the same as when they're written to disk.
+pub fn render_json_tree(
+    powers_dict: &PowersDictionary,
+    config: &PowersConfig,
+) -> io::Result<BTreeMap<String, Vec<u8>>> {
+    let schemas = match &config.schema {
+        Some(schema_config) => Some(Schemas::generate(schema_config)?),
+        None => None,
+    };
+    let mut files = BTreeMap::new();
+    write_json_tree(&mut files, powers_dict, schemas.as_ref(), config)?;
+    Ok(files)
+}
+
+/// Somewhere the .json files can be written.
+trait FileSink {
+    fn write(&mut self, path: &Path, contents: &[u8]) -> io::Result<()>;
+}
+
+impl FileSink for OutputFiles {
+    fn write(&mut self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        OutputFiles::write(self, path, contents)
+    }
+}
+
+impl FileSink for BTreeMap<String, Vec<u8>> {
+    fn write(&mut self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.insert(manifest::manifest_path(path), contents.to_vec());
+        Ok(())
+    }
+}
+
+/// Writes the root, archetypes, power category, and power set .json files.
+fn write_json_tree<F: FileSink>(
+    files: &mut F,
+    powers_dict: &PowersDictionary,
+    schemas: Option<&Schemas>,
+    config: &PowersConfig,
+) -> io::Result<()> {
     // write the root file
-    write_root(&powers_dict.power_categories, config)?;
+    write_root(files, &powers_dict.power_categories, schemas, config)?;
 
     // write archetypes
-    write_archetypes(&powers_dict.archetypes, config)?;
+    write_archetypes(files, &powers_dict.archetypes, schemas, config)?;
 
     // write all of the categories
     for category in &powers_dict.power_categories {
         if !category.include_in_output {
             continue;
         }
-        write_power_category(category, config)?;
+        write_power_category(files, category, schemas, config)?;
 
         if let Some(pcat_name) = category.pch_name.as_ref() {
             // write the category's power sets
             for set in &category.pp_power_sets {
                 if set.include_in_output {
                     write_power_set(
+                        files,
                         Some(pcat_name.get_string()),
                         set,
                         &powers_dict.attrib_names,
+                        schemas,
                         config,
                     )?;
                 }
             }
         }
     }
-
     Ok(())
 }
 
 /// Writes the root .json file.
-fn write_root(power_categories: &Vec<Rc<PowerCategory>>, config: &PowersConfig) -> io::Result<()> {
-    let output_file = config.join_to_output_path(JSON_FILE);
-    println!("Writing: {} ...", output_file.display());
-    let mut f = fs::File::create(output_file)?;
+fn write_root<F: FileSink>(
+    files: &mut F,
+    power_categories: &Vec<Rc<PowerCategory>>,
+    schemas: Option<&Schemas>,
+    config: &PowersConfig,
+) -> io::Result<()> {
     let root = RootOutput::from_power_categories(power_categories, config);
-    match config.output_style {
-        OutputStyleConfig::Pretty => serde_json::to_writer_pretty(&mut f, &root)?,
-        OutputStyleConfig::Compact => serde_json::to_writer(&mut f, &root)?,
-    }
-    Ok(())
+    write_json(
+        files,
+        Path::new(JSON_FILE),
+        &root,
+        OutputSchema::Root,
+        schemas,
+        config,
+    )
 }
 
 /// Writes the archetypes .json file.
-fn write_archetypes(archetypes: &Keyed<Archetype>, config: &PowersConfig) -> io::Result<()> {
-    let output_path = config.join_to_output_path("archetypes");
-    fs::create_dir_all(&output_path)?;
-    let output_file = output_path.join(JSON_FILE);
-    println!("Writing: {} ...", output_file.display());
-    let mut f = fs::File::create(output_file)?;
+fn write_archetypes<F: FileSink>(
+    files: &mut F,
+    archetypes: &Keyed<Archetype>,
+    schemas: Option<&Schemas>,
+    config: &PowersConfig,
+) -> io::Result<()> {
     let ats = ArchetypesOutput::from_archetypes(archetypes, config);
-    match config.output_style {
-        OutputStyleConfig::Pretty => serde_json::to_writer_pretty(&mut f, &ats)?,
-        OutputStyleConfig::Compact => serde_json::to_writer(&mut f, &ats)?,
-    }
-    Ok(())
+    write_json(
+        files,
+        &Path::new("archetypes").join(JSON_FILE),
+        &ats,
+        OutputSchema::Archetypes,
+        schemas,
+        config,
+    )
 }
 
 /// Writes all of the power category .json files to individual directories.
-fn write_power_category(power_category: &PowerCategory, config: &PowersConfig) -> io::Result<()> {
+fn write_power_category<F: FileSink>(
+    files: &mut F,
+    power_category: &PowerCategory,
+    schemas: Option<&Schemas>,
+    config: &PowersConfig,
+) -> io::Result<()> {
     if let Some(category_name) = &power_category.pch_name {
-        let output_path = config.join_to_output_path(&make_file_name(category_name.get()));
-        fs::create_dir_all(&output_path)?;
-        let output_file = output_path.join(JSON_FILE);
-        println!("Writing: {} ...", output_file.display());
-        let mut f = fs::File::create(output_file)?;
-
         let pcat = PowerCategoryOutput::from_power_category(power_category, config);
-        match config.output_style {
-            OutputStyleConfig::Pretty => serde_json::to_writer_pretty(&mut f, &pcat)?,
-            OutputStyleConfig::Compact => serde_json::to_writer(&mut f, &pcat)?,
-        }
+        write_json(
+            files,
+            &Path::new(&make_file_name(category_name.get())).join(JSON_FILE),
+            &pcat,
+            OutputSchema::PowerCategory,
+            schemas,
+            config,
+        )?;
     }
     Ok(())
 }
 
 /// Writes all of the power set .json files to individual directories beneath the power categories.
-fn write_power_set(
+fn write_power_set<F: FileSink>(
+    files: &mut F,
     category_name: Option<&String>,
     power_set: &BasePowerSet,
     attrib_names: &AttribNames,
+    schemas: Option<&Schemas>,
     config: &PowersConfig,
 ) -> io::Result<()> {
-    let output_path = config
-        .join_to_output_path(&make_file_name_opt(category_name))
-        .join(&make_file_name_opt(power_set.pch_name.as_ref()));
-    fs::create_dir_all(&output_path)?;
-    let output_file = output_path.join(JSON_FILE);
-    println!("\tWriting: {} ...", output_file.display());
-    let mut f = fs::File::create(output_file)?;
-
     let pset = PowerSetOutput::from_base_power_set(
         power_set,
         attrib_names,
         config,
     );
-    match config.output_style {
-        OutputStyleConfig::Pretty => serde_json::to_writer_pretty(&mut f, &pset)?,
-        OutputStyleConfig::Compact => serde_json::to_writer(&mut f, &pset)?,
+    write_json(
+        files,
+        &Path::new(&make_file_name_opt(category_name))
+            .join(&make_file_name_opt(power_set.pch_name.as_ref()))
+            .join(JSON_FILE),
+        &pset,
+        OutputSchema::PowerSet,
+        schemas,
+        config,
+    )
+}
+
+/// Serializes `value` and writes it to `path`, first checking it against its schema if there is one.
+fn write_json<F: FileSink, T: Serialize>(
+    files: &mut F,
+    path: &Path,
+    value: &T,
+    kind: OutputSchema,
+    schemas: Option<&Schemas>,
+    config: &PowersConfig,
+) -> io::Result<()> {
+    let contents = to_json(value, config)?;
+    if let Some(schemas) = schemas {
+        schemas.validate(kind, path, &contents)?;
     }
+    files.write(path, &contents)
+}
 
-    Ok(())
+/// Serializes `value` to .json using the configured output style.
+fn to_json<T: Serialize>(value: &T, config: &PowersConfig) -> io::Result<Vec<u8>> {
+    Ok(match config.output_style {
+        OutputStyleConfig::Pretty => serde_json::to_vec_pretty(value)?,
+        OutputStyleConfig::Compact => serde_json::to_vec(value)?,
+    })
 }
 
 /// Takes a string of arbitrary data and attempts to create a representation suitable for use
@@ -174,7 +336,7 @@ fn make_file_name_opt(string: Option<&String>) -> String {
 
 /// Takes a string of arbitrary data and attempts to create a representation suitable for use
 /// as a file name.
-fn make_file_name(string: &str) -> String {
+pub(crate) fn make_file_name(string: &str) -> String {
     let mut s = String::new();
     for c in string.chars() {
         if c.is_alphanumeric() {
@@ -187,3 +349,221 @@ fn make_file_name(string: &str) -> String {
     }
     s
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::structs::*;
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::process;
+
+    pub(crate) fn make_config(output_path: &Path) -> PowersConfig {
+        let mut config: PowersConfig = toml::from_str(&format!(
+            r#"
+            issue = "Test"
+            source = "Unit test"
+            at_level = 50
+            input_path = "input"
+            output_path = {:?}
+            power_categories = []
+            global_categories = []
+            filter_powersets = []
+            "#,
+            output_path.display().to_string()
+        ))
+        .unwrap();
+        config.extract_date = Some(chrono::Local::now());
+        config
+    }
+
+    pub(crate) fn make_powers_dict() -> PowersDictionary {
+        let mut archetypes = Keyed::new();
+        for name in &[
+            "Class_Blaster",
+            "Class_Corruptor",
+            "Class_Defender",
+            "Class_Tanker",
+        ] {
+            let mut archetype = Archetype::new();
+            archetype.pch_name = Some(String::from(*name));
+            archetype.pch_display_name = Some(String::from(&name[6..]));
+            archetypes.insert(NameKey::new(*name), Rc::new(archetype));
+        }
+        let mut ats: Vec<_> = archetypes.values().cloned().collect();
+        ats.sort_by(|a, b| a.pch_name.cmp(&b.pch_name));
+
+        let mut power = BasePower::new();
+        power.pch_name = Some(String::from("Flares"));
+        power.pch_full_name = Some(NameKey::new("Ranged.Fire_Blast.Flares"));
+        power.include_in_output = true;
+        power.archetypes = ats.clone();
+        for category in &["Ranged Damage", "Sniper Attacks", "Accurate Defense Debuff"] {
+            power
+                .enhancement_set_categories_allowed
+                .insert(String::from(*category));
+        }
+        let mut effect_group = EffectGroup::new();
+        effect_group.f_chance = 1.0;
+        effect_group.ppch_requires = vec![String::from("kMeter"), String::from("source>")];
+        power.pp_effects.push(Rc::new(effect_group));
+
+        let mut pset = BasePowerSet::new();
+        pset.pch_name = Some(String::from("Fire_Blast"));
+        pset.pch_full_name = Some(NameKey::new("Ranged.Fire_Blast"));
+        pset.pp_power_names
+            .push(NameKey::new("Ranged.Fire_Blast.Flares"));
+        pset.pi_available.push(0);
+        pset.pp_powers.push(Rc::new(power));
+        pset.include_in_output = true;
+
+        let mut pcat = PowerCategory::new();
+        pcat.pch_name = Some(NameKey::new("Ranged"));
+        pcat.ppch_power_set_names
+            .push(NameKey::new("Ranged.Fire_Blast"));
+        pcat.pp_power_sets.push(Rc::new(pset));
+        pcat.archetypes = ats;
+        pcat.include_in_output = true;
+
+        PowersDictionary {
+            power_categories: vec![Rc::new(pcat)],
+            archetypes,
+            boost_sets: Keyed::new(),
+            attrib_names: AttribNames::new(),
+        }
+    }
+
+    /// A temporary output path for a test, removed again when it's dropped.
+    pub(crate) struct TestOutput {
+        pub(crate) path: PathBuf,
+        pub(crate) config: PowersConfig,
+    }
+
+    impl TestOutput {
+        /// Starts with an empty output path named after the test and a config that writes to it.
+        pub(crate) fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("powers-test-{}-{}", name, process::id()));
+            let _ = fs::remove_dir_all(&path);
+            let config = make_config(&path);
+            TestOutput { path, config }
+        }
+
+        /// Writes `powers_dict` to the output path.
+        pub(crate) fn write(&self, powers_dict: PowersDictionary) -> io::Result<()> {
+            write_powers_dictionary(powers_dict, &self.config)
+        }
+
+        /// Reads a file written to the output path.
+        pub(crate) fn read(&self, path: &str) -> Vec<u8> {
+            fs::read(self.path.join(path)).unwrap()
+        }
+    }
+
+    impl Drop for TestOutput {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+
+    /// Reads every file under `path`, keyed by the path relative to `root`.
+    fn read_tree(root: &Path, path: &Path, files: &mut BTreeMap<PathBuf, Vec<u8>>) {
+        for entry in fs::read_dir(path).unwrap() {
+            let entry_path = entry.unwrap().path();
+            if entry_path.is_dir() {
+                read_tree(root, &entry_path, files);
+            } else {
+                let contents = fs::read(&entry_path).unwrap();
+                files.insert(entry_path.strip_prefix(root).unwrap().to_owned(), contents);
+            }
+        }
+    }
+
+    #[test]
+    fn test_output_is_deterministic() {
+        let mut trees = Vec::new();
+        let mut extract_date = None;
+        for run in 0..2 {
+            let mut output = TestOutput::new(&format!("deterministic-{}", run));
+            // both runs need the same timestamp in their headers
+            if extract_date.is_none() {
+                extract_date = output.config.extract_date;
+            }
+            output.config.extract_date = extract_date;
+            // a new dictionary each time, so the hash maps inside are seeded differently
+            output.write(make_powers_dict()).unwrap();
+            let mut files = BTreeMap::new();
+            read_tree(&output.path, &output.path, &mut files);
+            trees.push(files);
+        }
+        assert!(!trees[0].is_empty());
+        assert!(trees[0] == trees[1], "output trees differ between runs");
+    }
+
+    #[test]
+    fn test_output_is_incremental() {
+        let mut output = TestOutput::new("inc");
+        let pset_file = "ranged/fire-blast/index.json";
+        let read_manifest = |output: &TestOutput| -> manifest::Manifest {
+            serde_json::from_slice(&output.read(manifest::MANIFEST_FILE)).unwrap()
+        };
+
+        // first run writes everything
+        output.write(make_powers_dict()).unwrap();
+        let first = output.read(pset_file);
+        let manifest = read_manifest(&output);
+        let entry = manifest.files.iter().find(|e| e.path == pset_file).unwrap();
+        assert_eq!(entry.md5, format!("{:x}", md5::compute(&first)));
+        assert_eq!(entry.size, first.len() as u64);
+
+        // a new extract date alone doesn't rewrite anything
+        output.config.extract_date = Some(chrono::Local::now() + chrono::Duration::days(1));
+        output.write(make_powers_dict()).unwrap();
+        assert!(output.read(pset_file) == first);
+
+        // categories that disappear are removed
+        let mut powers_dict = make_powers_dict();
+        Rc::get_mut(&mut powers_dict.power_categories[0])
+            .unwrap()
+            .include_in_output = false;
+        output.write(powers_dict).unwrap();
+        let manifest = read_manifest(&output);
+        assert!(!manifest.files.iter().any(|e| e.path.starts_with("ranged/")));
+        assert!(!output.path.join(pset_file).exists());
+        assert!(!output.path.join("ranged").exists());
+    }
+
+    #[test]
+    fn test_output_policies() {
+        let mut output = TestOutput::new("policy");
+        let other_file = output.path.join("README.txt");
+        let root_file = output.path.join(JSON_FILE);
+        let staging_path = output.path.with_file_name(format!(
+            ".{}.staging",
+            output.path.file_name().unwrap().to_string_lossy()
+        ));
+
+        // merge keeps files that aren't part of the output
+        fs::create_dir_all(&output.path).unwrap();
+        fs::write(&other_file, "hello").unwrap();
+        output.config.output_policy = OutputPolicyConfig::Merge;
+        output.write(make_powers_dict()).unwrap();
+        assert!(other_file.is_file());
+        assert!(root_file.is_file());
+        assert!(!staging_path.exists());
+
+        // fail-if-exists leaves everything alone
+        output.config.output_policy = OutputPolicyConfig::FailIfExists;
+        let before = fs::read(&root_file).unwrap();
+        assert!(output.write(make_powers_dict()).is_err());
+        assert!(fs::read(&root_file).unwrap() == before);
+        assert!(!staging_path.exists());
+
+        // overwrite drops them
+        output.config.output_policy = OutputPolicyConfig::Overwrite;
+        output.write(make_powers_dict()).unwrap();
+        assert!(!other_file.exists());
+        assert!(root_file.is_file());
+        assert!(!staging_path.exists());
+    }
+}
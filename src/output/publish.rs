@@ -0,0 +1,169 @@
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// Suffixes for the directories created next to the output path.
+const STAGING_SUFFIX: &'static str = "staging";
+const BACKUP_SUFFIX: &'static str = "old";
+
+/// A directory next to the output path where files are written before being published.
+pub struct StagingDir {
+    /// The output path that will be replaced.
+    live: PathBuf,
+    /// Where files are written in the meantime.
+    staging: PathBuf,
+}
+
+impl StagingDir {
+    /// Creates an empty staging directory next to `live`, removing any left behind by an earlier run.
+    ///
+    /// # Arguments:
+    ///
+    /// * `live` - The output path.
+    ///
+    /// # Returns:
+    ///
+    /// A `StagingDir` if successful. Otherwise, an `io::Error` containing the error information.
+    pub fn create(live: &Path) -> io::Result<Self> {
+        let staging = sibling_path(live, STAGING_SUFFIX)?;
+        if staging.exists() {
+            fs::remove_dir_all(&staging)?;
+        }
+        fs::create_dir_all(&staging)?;
+        Ok(StagingDir {
+            live: live.to_owned(),
+            staging,
+        })
+    }
+
+    /// The path that files should be written to.
+    pub fn path(&self) -> &Path {
+        &self.staging
+    }
+
+    /// Swaps the staging directory into place as the output path.
+    ///
+    /// # Returns:
+    ///
+    /// Nothing if the operation was successful. Otherwise, an `io::Error` containing the error information.
+    ///
+    /// # Notes:
+    ///
+    /// This isn't atomic. The existing output path is renamed out of the way before the staging directory
+    /// is renamed into place, so for a moment there's nothing at the output path, and anything reading it
+    /// then will find it missing. The existing output is only deleted once the second rename has succeeded,
+    /// and is put back if it fails. If the process dies in between, `recover_backup` puts it back on the
+    /// next run.
+    pub fn publish(self) -> io::Result<()> {
+        println!("Publishing: {} ...", self.live.display());
+        if self.live.exists() {
+            let backup = sibling_path(&self.live, BACKUP_SUFFIX)?;
+            if backup.exists() {
+                fs::remove_dir_all(&backup)?;
+            }
+            fs::rename(&self.live, &backup)?;
+            if let Err(e) = fs::rename(&self.staging, &self.live) {
+                fs::rename(&backup, &self.live)?;
+                return Err(e);
+            }
+            fs::remove_dir_all(&backup)?;
+        } else {
+            fs::rename(&self.staging, &self.live)?;
+        }
+        Ok(())
+    }
+
+    /// Throws away the staging directory, leaving the output path untouched.
+    pub fn discard(self) {
+        let _ = fs::remove_dir_all(&self.staging);
+    }
+}
+
+/// Cleans up after a run that stopped partway through `StagingDir::publish`.
+///
+/// # Arguments:
+///
+/// * `live` - The output path.
+///
+/// # Returns:
+///
+/// Nothing if the operation was successful. Otherwise, an `io::Error` containing the error information.
+///
+/// # Notes:
+///
+/// If the backup of the previous output is still there but the output path isn't, the run stopped
+/// between the two renames, so the backup is moved back into place. If both are there, the run only
+/// missed deleting the backup, so it's deleted now.
+pub fn recover_backup(live: &Path) -> io::Result<()> {
+    let backup = sibling_path(live, BACKUP_SUFFIX)?;
+    if !backup.is_dir() {
+        return Ok(());
+    }
+    if live.exists() {
+        fs::remove_dir_all(&backup)
+    } else {
+        println!("Recovering: {} ...", live.display());
+        fs::rename(&backup, live)
+    }
+}
+
+/// Tests if `path` exists and has anything in it.
+pub fn is_non_empty_dir(path: &Path) -> io::Result<bool> {
+    if path.is_dir() {
+        Ok(path.read_dir()?.next().is_some())
+    } else {
+        Ok(false)
+    }
+}
+
+/// Gets the path of a hidden directory next to `path`, e.g. `.dist.staging` for `dist`.
+fn sibling_path(path: &Path, suffix: &str) -> io::Result<PathBuf> {
+    let name = path.file_name().ok_or_else(|| {
+        Error::new(
+            ErrorKind::Other,
+            format!("The output path {} must name a directory", path.display()),
+        )
+    })?;
+    let mut sibling = OsString::from(".");
+    sibling.push(name);
+    sibling.push(".");
+    sibling.push(suffix);
+    Ok(path.with_file_name(sibling))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+
+    #[test]
+    fn test_recover_backup() {
+        let base_path = std::env::temp_dir().join(format!("powers-test-publish-{}", process::id()));
+        let _ = fs::remove_dir_all(&base_path);
+        let live = base_path.join("dist");
+        let backup = sibling_path(&live, BACKUP_SUFFIX).unwrap();
+        fs::create_dir_all(&backup).unwrap();
+        fs::write(backup.join("index.json"), "{}").unwrap();
+
+        // stopped between the renames, so the backup goes back
+        recover_backup(&live).unwrap();
+        let restored = live.join("index.json").is_file();
+        let backup_after_restore = backup.exists();
+
+        // stopped before deleting the backup, so it's thrown away
+        fs::create_dir_all(&backup).unwrap();
+        recover_backup(&live).unwrap();
+        let live_after_cleanup = live.join("index.json").is_file();
+        let backup_after_cleanup = backup.exists();
+
+        // nothing to do
+        recover_backup(&live).unwrap();
+        let _ = fs::remove_dir_all(&base_path);
+        assert!(restored);
+        assert!(!backup_after_restore);
+        assert!(live_after_cleanup);
+        assert!(!backup_after_cleanup);
+    }
+}
@@ -0,0 +1,260 @@
+use super::manifest::OutputFiles;
+use super::structs::*;
+use crate::structs::config::{PowersConfig, SchemaConfig};
+use jsonschema::Validator;
+use schemars::generate::SchemaSettings;
+use schemars::JsonSchema;
+use serde_json::Value;
+use std::io;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+/// Added to the name of each schema to make its file name.
+const SCHEMA_EXTENSION: &'static str = ".schema.json";
+
+/// The most validation errors reported for a single file.
+const MAX_ERRORS: usize = 10;
+
+/// The kinds of .json files in the hierarchical output, each with its own schema.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputSchema {
+    Root,
+    Archetypes,
+    PowerCategory,
+    PowerSet,
+}
+
+impl OutputSchema {
+    /// Every kind of .json file, in the order the schemas are written.
+    const ALL: &'static [OutputSchema] = &[
+        OutputSchema::Root,
+        OutputSchema::Archetypes,
+        OutputSchema::PowerCategory,
+        OutputSchema::PowerSet,
+    ];
+
+    pub fn get_string(&self) -> &'static str {
+        match self {
+            OutputSchema::Root => "root",
+            OutputSchema::Archetypes => "archetypes",
+            OutputSchema::PowerCategory => "power-category",
+            OutputSchema::PowerSet => "power-set",
+        }
+    }
+
+    /// Gets the file name of the schema.
+    fn file_name(&self) -> String {
+        format!("{}{}", self.get_string(), SCHEMA_EXTENSION)
+    }
+
+    /// Generates the schema from the output struct that's serialized for this kind of file.
+    fn generate(&self) -> io::Result<Value> {
+        match self {
+            OutputSchema::Root => generate_for::<RootOutput>(),
+            OutputSchema::Archetypes => generate_for::<ArchetypesOutput>(),
+            OutputSchema::PowerCategory => generate_for::<PowerCategoryOutput>(),
+            OutputSchema::PowerSet => generate_for::<PowerSetOutput>(),
+        }
+    }
+}
+
+/// The generated schemas for every kind of .json file.
+pub struct Schemas {
+    schemas: Vec<(OutputSchema, Value)>,
+    /// Compiled schemas used by `validate`, if validation is turned on.
+    validators: Vec<(OutputSchema, Validator)>,
+}
+
+impl Schemas {
+    /// Generates the schemas.
+    ///
+    /// # Arguments:
+    ///
+    /// * `schema_config` - Schema configuration information.
+    ///
+    /// # Returns:
+    ///
+    /// A `Schemas` if successful. Otherwise, an `io::Error` containing the error information.
+    pub fn generate(schema_config: &SchemaConfig) -> io::Result<Self> {
+        let mut schemas = Vec::new();
+        let mut validators = Vec::new();
+        for kind in OutputSchema::ALL {
+            let schema = kind.generate()?;
+            if schema_config.validate {
+                let validator = jsonschema::draft7::new(&schema)
+                    .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
+                validators.push((*kind, validator));
+            }
+            schemas.push((*kind, schema));
+        }
+        Ok(Schemas {
+            schemas,
+            validators,
+        })
+    }
+
+    /// Writes the schemas to the directory configured in `schema_config`.
+    ///
+    /// # Arguments:
+    ///
+    /// * `files` - The output files being written.
+    /// * `schema_config` - Schema configuration information.
+    /// * `config` - Configuration information.
+    ///
+    /// # Returns:
+    ///
+    /// Nothing if the operation was successful. Otherwise, an `io::Error` containing the error information.
+    pub fn write(
+        &self,
+        files: &mut OutputFiles,
+        schema_config: &SchemaConfig,
+        config: &PowersConfig,
+    ) -> io::Result<()> {
+        for (kind, schema) in &self.schemas {
+            files.write(
+                &Path::new(&schema_config.path).join(kind.file_name()),
+                &super::to_json(schema, config)?,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Checks the `contents` of a .json file against its schema.
+    ///
+    /// # Arguments:
+    ///
+    /// * `kind` - The kind of .json file.
+    /// * `path` - Path of the file relative to the output path, used in error messages.
+    /// * `contents` - The contents of the file.
+    ///
+    /// # Returns:
+    ///
+    /// Nothing if the file matches its schema or validation is turned off. Otherwise, an `io::Error`
+    /// listing where the file doesn't match.
+    pub fn validate(&self, kind: OutputSchema, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let validator = match self.validators.iter().find(|(k, _)| *k == kind) {
+            Some((_, validator)) => validator,
+            None => return Ok(()),
+        };
+        let value: Value = serde_json::from_slice(contents)?;
+        let errors: Vec<_> = validator
+            .iter_errors(&value)
+            .take(MAX_ERRORS)
+            .map(|e| {
+                let pointer = e.instance_path.as_str();
+                let pointer = if pointer.is_empty() { "/" } else { pointer };
+                format!("{}: {}", pointer, e)
+            })
+            .collect();
+        if errors.is_empty() {
+            return Ok(());
+        }
+        Err(Error::new(
+            ErrorKind::Other,
+            format!(
+                "{} does not match {}:\n  {}",
+                path.display(),
+                kind.file_name(),
+                errors.join("\n  ")
+            ),
+        ))
+    }
+}
+
+/// Generates a draft-07 schema for `T`, describing the .json that's written rather than what could be read.
+fn generate_for<T: JsonSchema>() -> io::Result<Value> {
+    let generator = SchemaSettings::draft07().for_serialize().into_generator();
+    Ok(serde_json::to_value(generator.into_root_schema_for::<T>())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::tests::{make_powers_dict, TestOutput};
+    use std::env;
+    use std::fs;
+
+    /// Where the schemas are checked in, relative to the crate root.
+    const SCHEMAS_PATH: &'static str = "docs/schemas";
+
+    #[test]
+    fn test_validate() {
+        let schemas = Schemas::generate(&SchemaConfig::default()).unwrap();
+        let path = Path::new("index.json");
+        let valid = br#"{
+            "issue": "I27", "source": null, "extract_date": null, "archetypes": "archetypes/index.json",
+            "power_categories": [{ "name": "Ranged", "display_name": null, "url": "ranged/index.json" }]
+        }"#;
+        schemas.validate(OutputSchema::Root, path, valid).unwrap();
+        let invalid = br#"{
+            "archetypes": 5,
+            "power_categories": [{ "name": "Ranged", "display_name": null }]
+        }"#;
+        let message = schemas
+            .validate(OutputSchema::Root, path, invalid)
+            .unwrap_err()
+            .to_string();
+        assert!(message.contains("/archetypes: 5 is not of type \"string\""));
+        assert!(message.contains("/power_categories/0: \"url\" is a required property"));
+    }
+
+    #[test]
+    fn test_validate_off() {
+        let schema_config = SchemaConfig {
+            validate: false,
+            ..Default::default()
+        };
+        let schemas = Schemas::generate(&schema_config).unwrap();
+        schemas
+            .validate(OutputSchema::Root, Path::new("index.json"), b"{}")
+            .unwrap();
+    }
+
+    /// Fails when the output structs change the schemas, so the change shows up in the commit. Run the
+    /// tests with `POWERS_UPDATE_SCHEMAS=1` to rewrite the checked-in copies.
+    #[test]
+    fn test_schemas_are_checked_in() {
+        let schemas_path = Path::new(env!("CARGO_MANIFEST_DIR")).join(SCHEMAS_PATH);
+        let update = env::var_os("POWERS_UPDATE_SCHEMAS").is_some();
+        for kind in OutputSchema::ALL {
+            let schema_file = schemas_path.join(kind.file_name());
+            let mut generated = serde_json::to_string_pretty(&kind.generate().unwrap()).unwrap();
+            generated.push('\n');
+            if update {
+                fs::create_dir_all(&schemas_path).unwrap();
+                fs::write(&schema_file, &generated).unwrap();
+            }
+            let checked_in = fs::read_to_string(&schema_file).unwrap_or_default();
+            assert!(
+                checked_in == generated,
+                "{} is out of date, run the tests with POWERS_UPDATE_SCHEMAS=1 to update it",
+                schema_file.display()
+            );
+        }
+    }
+
+    #[test]
+    fn test_schemas() {
+        let mut output = TestOutput::new("schema");
+        output.config.schema = Some(SchemaConfig::default());
+        output.write(make_powers_dict()).unwrap();
+        let mut names: Vec<_> = fs::read_dir(output.path.join("schemas"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "archetypes.schema.json",
+                "power-category.schema.json",
+                "power-set.schema.json",
+                "root.schema.json",
+            ]
+        );
+        let schema: Value =
+            serde_json::from_slice(&output.read("schemas/power-set.schema.json")).unwrap();
+        assert!(schema["definitions"]["PowerOutput"].is_object());
+        assert!(schema["definitions"]["AttribModOutput"].is_object());
+    }
+}
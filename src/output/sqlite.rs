@@ -0,0 +1,743 @@
+use super::manifest::OutputFiles;
+use super::structs::{aspect_to_string, character_attrib_to_string, filter_archetypes_pwr};
+use crate::structs::config::{PowersConfig, SqliteConfig};
+use crate::structs::*;
+use rusqlite::{params, Connection, DatabaseName};
+use std::collections::HashMap;
+use std::io;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use std::rc::Rc;
+
+/// Tables and indexes in the database. Foreign keys follow the relationships in `PowersDictionary`.
+const SCHEMA: &'static str = r#"
+CREATE TABLE metadata (
+    key TEXT PRIMARY KEY,
+    value TEXT
+);
+CREATE TABLE archetypes (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    display_name TEXT,
+    display_help TEXT,
+    display_short_help TEXT,
+    icon TEXT,
+    primary_category TEXT,
+    secondary_category TEXT,
+    power_pool_category TEXT,
+    epic_pool_category TEXT
+);
+CREATE TABLE power_categories (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    display_name TEXT,
+    display_help TEXT,
+    display_short_help TEXT,
+    archetype_id INTEGER REFERENCES archetypes (id),
+    primary_secondary TEXT,
+    top_level INTEGER NOT NULL
+);
+CREATE TABLE power_sets (
+    id INTEGER PRIMARY KEY,
+    power_category_id INTEGER NOT NULL REFERENCES power_categories (id),
+    name TEXT NOT NULL UNIQUE,
+    short_name TEXT,
+    display_name TEXT,
+    display_help TEXT,
+    display_short_help TEXT,
+    icon TEXT,
+    specialize_at_level INTEGER
+);
+CREATE TABLE powers (
+    id INTEGER PRIMARY KEY,
+    power_set_id INTEGER NOT NULL REFERENCES power_sets (id),
+    name TEXT NOT NULL UNIQUE,
+    short_name TEXT,
+    display_name TEXT,
+    display_help TEXT,
+    display_short_help TEXT,
+    icon TEXT,
+    power_type TEXT NOT NULL,
+    available_at_level INTEGER,
+    accuracy REAL NOT NULL,
+    activation_time REAL NOT NULL,
+    effective_activation_time REAL NOT NULL,
+    recharge_time REAL NOT NULL,
+    activate_period REAL NOT NULL,
+    endurance_cost REAL NOT NULL,
+    range REAL NOT NULL,
+    radius REAL NOT NULL,
+    arc REAL NOT NULL,
+    max_targets_hit INTEGER NOT NULL,
+    effect_area TEXT NOT NULL,
+    target_type_tags TEXT,
+    max_boosts INTEGER NOT NULL
+);
+CREATE TABLE power_archetypes (
+    power_id INTEGER NOT NULL REFERENCES powers (id),
+    archetype_id INTEGER NOT NULL REFERENCES archetypes (id),
+    PRIMARY KEY (power_id, archetype_id)
+);
+CREATE TABLE power_enhancement_set_categories (
+    power_id INTEGER NOT NULL REFERENCES powers (id),
+    set_category TEXT NOT NULL,
+    PRIMARY KEY (power_id, set_category)
+);
+CREATE TABLE effect_groups (
+    id INTEGER PRIMARY KEY,
+    power_id INTEGER NOT NULL REFERENCES powers (id),
+    parent_id INTEGER REFERENCES effect_groups (id),
+    chance REAL NOT NULL,
+    procs_per_minute REAL NOT NULL,
+    delay REAL NOT NULL,
+    radius_inner REAL NOT NULL,
+    radius_outer REAL NOT NULL,
+    requires TEXT,
+    tags TEXT,
+    flags TEXT
+);
+CREATE TABLE attrib_mods (
+    id INTEGER PRIMARY KEY,
+    effect_group_id INTEGER NOT NULL REFERENCES effect_groups (id),
+    attribs TEXT,
+    aspect TEXT NOT NULL,
+    application_type TEXT NOT NULL,
+    type TEXT NOT NULL,
+    target TEXT NOT NULL,
+    table_name TEXT,
+    scale REAL NOT NULL,
+    magnitude REAL NOT NULL,
+    duration REAL,
+    duration_special TEXT,
+    delay REAL NOT NULL,
+    period REAL NOT NULL,
+    tick_chance REAL NOT NULL,
+    stack TEXT NOT NULL,
+    stack_limit INTEGER NOT NULL,
+    magnitude_expr TEXT,
+    duration_expr TEXT,
+    flags TEXT
+);
+CREATE TABLE attrib_mod_scaled (
+    attrib_mod_id INTEGER NOT NULL REFERENCES attrib_mods (id),
+    archetype_id INTEGER NOT NULL REFERENCES archetypes (id),
+    level INTEGER NOT NULL,
+    base_value REAL NOT NULL,
+    scaled_value REAL NOT NULL,
+    PRIMARY KEY (attrib_mod_id, archetype_id)
+);
+CREATE TABLE villains (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    character_class TEXT,
+    rank TEXT NOT NULL,
+    description TEXT,
+    display_class_name TEXT,
+    ai_config TEXT
+);
+CREATE TABLE villain_powers (
+    villain_id INTEGER NOT NULL REFERENCES villains (id),
+    power_category TEXT,
+    power_set TEXT,
+    power TEXT,
+    level INTEGER NOT NULL
+);
+CREATE TABLE attrib_mod_villains (
+    attrib_mod_id INTEGER NOT NULL REFERENCES attrib_mods (id),
+    villain_id INTEGER NOT NULL REFERENCES villains (id),
+    PRIMARY KEY (attrib_mod_id, villain_id)
+);
+CREATE TABLE boost_sets (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    display_name TEXT,
+    group_name TEXT,
+    min_level INTEGER NOT NULL,
+    max_level INTEGER NOT NULL
+);
+CREATE TABLE boost_set_powers (
+    boost_set_id INTEGER NOT NULL REFERENCES boost_sets (id),
+    power_name TEXT NOT NULL,
+    power_id INTEGER REFERENCES powers (id)
+);
+CREATE TABLE boost_set_bonuses (
+    id INTEGER PRIMARY KEY,
+    boost_set_id INTEGER NOT NULL REFERENCES boost_sets (id),
+    display_name TEXT,
+    min_boosts INTEGER NOT NULL,
+    max_boosts INTEGER NOT NULL,
+    requires TEXT,
+    bonus_power_name TEXT,
+    bonus_power_id INTEGER REFERENCES powers (id)
+);
+CREATE INDEX power_categories_display_name ON power_categories (display_name);
+CREATE INDEX power_sets_display_name ON power_sets (display_name);
+CREATE INDEX power_sets_power_category_id ON power_sets (power_category_id);
+CREATE INDEX powers_short_name ON powers (short_name);
+CREATE INDEX powers_display_name ON powers (display_name);
+CREATE INDEX powers_power_set_id ON powers (power_set_id);
+CREATE INDEX effect_groups_power_id ON effect_groups (power_id);
+CREATE INDEX attrib_mods_effect_group_id ON attrib_mods (effect_group_id);
+CREATE INDEX attrib_mods_attribs ON attrib_mods (attribs);
+CREATE INDEX villains_display_class_name ON villains (display_class_name);
+CREATE INDEX boost_sets_display_name ON boost_sets (display_name);
+CREATE INDEX boost_set_powers_power_name ON boost_set_powers (power_name);
+"#;
+
+/// Writes the powers dictionary as a SQLite database.
+///
+/// # Arguments:
+///
+/// * `files` - The output files being written.
+/// * `powers_dict` - A `PowersDictionary` containing a hierarchy of categories, power sets, and powers.
+/// * `sqlite_config` - SQLite export configuration information.
+/// * `config` - Configuration information.
+///
+/// # Returns:
+///
+/// Nothing if the operation was successful. Otherwise, an `io::Error` containing the error information.
+///
+/// # Notes:
+///
+/// The database is built in memory, then added to the output like any other file. Only the
+/// categories, power sets, and powers that are written to the .json files are included. Scaled values are
+/// calculated at the configured `at_level`.
+pub fn write_sqlite_database(
+    files: &mut OutputFiles,
+    powers_dict: &PowersDictionary,
+    sqlite_config: &SqliteConfig,
+    config: &PowersConfig,
+) -> io::Result<()> {
+    let conn = build_database(powers_dict, config).map_err(|e| Error::new(ErrorKind::Other, e))?;
+    let data = conn
+        .serialize(DatabaseName::Main)
+        .map_err(|e| Error::new(ErrorKind::Other, e))?;
+    files.write(Path::new(&sqlite_config.file), &data)
+}
+
+/// Creates the database in memory and fills it in.
+fn build_database(
+    powers_dict: &PowersDictionary,
+    config: &PowersConfig,
+) -> rusqlite::Result<Connection> {
+    let mut conn = Connection::open_in_memory()?;
+    conn.execute_batch(SCHEMA)?;
+    let tx = conn.transaction()?;
+    // no extract date, so the file only changes when the data does
+    tx.execute(
+        "INSERT INTO metadata VALUES ('issue', ?1), ('source', ?2)",
+        params![config.issue, config.source],
+    )?;
+    let mut writer = DatabaseWriter {
+        tx: &tx,
+        powers_dict,
+        config,
+        archetype_ids: HashMap::new(),
+        power_ids: HashMap::new(),
+        villain_ids: HashMap::new(),
+        next_effect_group_id: 1,
+        next_attrib_mod_id: 1,
+    };
+    writer.insert_archetypes()?;
+    writer.insert_power_categories()?;
+    writer.insert_boost_sets()?;
+    tx.commit()?;
+    conn.execute_batch("VACUUM;")?;
+    Ok(conn)
+}
+
+/// Keeps track of the ids handed out while filling in the database.
+struct DatabaseWriter<'a> {
+    /// The open transaction.
+    tx: &'a Connection,
+    powers_dict: &'a PowersDictionary,
+    config: &'a PowersConfig,
+    /// Archetype ids, keyed by name.
+    archetype_ids: HashMap<String, i64>,
+    /// Power ids, keyed by full name.
+    power_ids: HashMap<NameKey, i64>,
+    /// Villain ids, keyed by name.
+    villain_ids: HashMap<NameKey, i64>,
+    next_effect_group_id: i64,
+    next_attrib_mod_id: i64,
+}
+
+impl<'a> DatabaseWriter<'a> {
+    fn insert_archetypes(&mut self) -> rusqlite::Result<()> {
+        let mut ats: Vec<_> = self.powers_dict.archetypes.values().collect();
+        ats.sort_by(|a, b| a.pch_name.cmp(&b.pch_name));
+        let mut stmt = self
+            .tx
+            .prepare("INSERT INTO archetypes VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)")?;
+        for at in ats {
+            let name = match &at.pch_name {
+                Some(name) => name,
+                None => continue,
+            };
+            let id = self.archetype_ids.len() as i64 + 1;
+            stmt.execute(params![
+                id,
+                name,
+                at.pch_display_name,
+                at.pch_display_help,
+                at.pch_display_short_help,
+                at.pch_icon,
+                at.pch_primary_category.as_ref().map(|n| n.get()),
+                at.pch_secondary_category.as_ref().map(|n| n.get()),
+                at.pch_power_pool_category.as_ref().map(|n| n.get()),
+                at.pch_epic_pool_category.as_ref().map(|n| n.get()),
+            ])?;
+            self.archetype_ids.insert(name.clone(), id);
+        }
+        Ok(())
+    }
+
+    fn insert_power_categories(&mut self) -> rusqlite::Result<()> {
+        let mut category_id = 0;
+        let mut power_set_id = 0;
+        for category in &self.powers_dict.power_categories {
+            let name = match &category.pch_name {
+                Some(name) if category.include_in_output => name,
+                _ => continue,
+            };
+            category_id += 1;
+            let archetype_id = if category.archetypes.len() == 1 {
+                // same rule as the .json output: one archetype means the category belongs to it
+                self.get_archetype_id(&category.archetypes[0])
+            } else {
+                None
+            };
+            self.tx.execute(
+                "INSERT INTO power_categories VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    category_id,
+                    name.get(),
+                    category.pch_display_name,
+                    category.pch_display_help,
+                    category.pch_display_short_help,
+                    archetype_id,
+                    match category.pri_sec {
+                        PrimarySecondary::Primary => Some("Primary"),
+                        PrimarySecondary::Secondary => Some("Secondary"),
+                        PrimarySecondary::None => None,
+                    },
+                    category.top_level,
+                ],
+            )?;
+            for power_set in &category.pp_power_sets {
+                let set_name = match &power_set.pch_full_name {
+                    Some(set_name) if power_set.include_in_output => set_name,
+                    _ => continue,
+                };
+                power_set_id += 1;
+                self.tx.execute(
+                    "INSERT INTO power_sets VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                    params![
+                        power_set_id,
+                        category_id,
+                        set_name.get(),
+                        power_set.pch_name,
+                        power_set.pch_display_name,
+                        power_set.pch_display_help,
+                        power_set.pch_display_short_help,
+                        power_set.pch_icon_name,
+                        if power_set.i_specialize_at > 0 {
+                            Some(power_set.i_specialize_at + 1)
+                        } else {
+                            None
+                        },
+                    ],
+                )?;
+                self.insert_powers(power_set_id, power_set)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn insert_powers(
+        &mut self,
+        power_set_id: i64,
+        power_set: &BasePowerSet,
+    ) -> rusqlite::Result<()> {
+        let levels: HashMap<_, _> = power_set
+            .pp_power_names
+            .iter()
+            .zip(&power_set.pi_available)
+            .collect();
+        for power in &power_set.pp_powers {
+            let power_name = match &power.pch_full_name {
+                Some(power_name) if power.include_in_output => power_name,
+                _ => continue,
+            };
+            if self.power_ids.contains_key(power_name) {
+                continue;
+            }
+            let power_id = self.power_ids.len() as i64 + 1;
+            self.tx.execute(
+                "INSERT INTO powers VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, \
+                 ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23)",
+                params![
+                    power_id,
+                    power_set_id,
+                    power_name.get(),
+                    power.pch_name,
+                    power.pch_display_name,
+                    power.pch_display_help,
+                    power.pch_display_short_help,
+                    power.pch_icon_name,
+                    power.e_type.get_string(),
+                    levels.get(power_name).map(|level| **level + 1),
+                    power.f_accuracy,
+                    power.f_time_to_activate,
+                    power.effective_time_to_activate(&self.config.activation_time),
+                    power.f_recharge_time,
+                    power.f_activate_period,
+                    power.f_endurance_cost,
+                    power.f_range,
+                    power.f_radius,
+                    power.f_arc,
+                    power.i_max_targets_hit,
+                    power.e_effect_area.get_string(),
+                    join_opt(&power.e_target_type.get_strings(), ", "),
+                    power.i_max_boosts,
+                ],
+            )?;
+            self.power_ids.insert(power_name.clone(), power_id);
+
+            for at in &power.archetypes {
+                if let Some(archetype_id) = self.get_archetype_id(at) {
+                    self.tx.execute(
+                        "INSERT OR IGNORE INTO power_archetypes VALUES (?1, ?2)",
+                        params![power_id, archetype_id],
+                    )?;
+                }
+            }
+            for set_category in &power.enhancement_set_categories_allowed {
+                self.tx.execute(
+                    "INSERT INTO power_enhancement_set_categories VALUES (?1, ?2)",
+                    params![power_id, set_category],
+                )?;
+            }
+            // same archetypes as the .json files
+            let archetypes = filter_archetypes_pwr(power, &power.archetypes);
+            for effect_group in &power.pp_effects {
+                self.insert_effect_group(power_id, None, effect_group, &archetypes)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn insert_effect_group(
+        &mut self,
+        power_id: i64,
+        parent_id: Option<i64>,
+        effect_group: &EffectGroup,
+        archetypes: &[Rc<Archetype>],
+    ) -> rusqlite::Result<()> {
+        let effect_group_id = self.next_effect_group_id;
+        self.next_effect_group_id += 1;
+        self.tx.execute(
+            "INSERT INTO effect_groups VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                effect_group_id,
+                power_id,
+                parent_id,
+                effect_group.f_chance,
+                effect_group.f_procs_per_minute,
+                effect_group.f_delay,
+                effect_group.f_radius_inner,
+                effect_group.f_radius_outer,
+                join_opt(&effect_group.ppch_requires, " "),
+                join_opt(&effect_group.ppch_tags, ", "),
+                join_opt(&effect_group.i_flags.get_strings(), ", "),
+            ],
+        )?;
+        for template in &effect_group.pp_templates {
+            self.insert_attrib_mod(effect_group_id, template, archetypes)?;
+        }
+        for child in &effect_group.pp_effects {
+            self.insert_effect_group(power_id, Some(effect_group_id), child, archetypes)?;
+        }
+        Ok(())
+    }
+
+    fn insert_attrib_mod(
+        &mut self,
+        effect_group_id: i64,
+        template: &AttribModTemplate,
+        archetypes: &[Rc<Archetype>],
+    ) -> rusqlite::Result<()> {
+        let attrib_mod_id = self.next_attrib_mod_id;
+        self.next_attrib_mod_id += 1;
+        let attribs: Vec<_> = template
+            .p_attrib
+            .iter()
+            .filter_map(|attrib| {
+                character_attrib_to_string(attrib, &self.powers_dict.attrib_names)
+                    .map(|s| s.into_owned())
+                    .or_else(|| match attrib {
+                        SpecialAttrib::kSpecialAttrib_Character(_) => None,
+                        _ => Some(String::from(attrib.get_string())),
+                    })
+            })
+            .collect();
+        let (duration, duration_special) = match template.f_duration {
+            ModDuration::InSeconds(secs) => (Some(secs), None),
+            ModDuration::kModDuration_Instant => (None, Some("Instant")),
+            ModDuration::kModDuration_UntilKilled => (None, Some("UntilKilled")),
+            ModDuration::kModDuration_UntilShutOff => (None, Some("UntilShutOff")),
+        };
+        self.tx.execute(
+            "INSERT INTO attrib_mods VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, \
+             ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)",
+            params![
+                attrib_mod_id,
+                effect_group_id,
+                join_opt(&attribs, ", "),
+                aspect_to_string(template.off_aspect),
+                template.e_application_type.get_string(),
+                template.e_type.get_string(),
+                template.e_target.get_string(),
+                template.pch_table,
+                template.f_scale,
+                template.f_magnitude,
+                duration,
+                duration_special,
+                template.f_delay,
+                template.f_period,
+                template.f_tick_chance,
+                template.e_stack.get_string(),
+                template.i_stack_limit,
+                join_opt(&template.ppch_magnitude, " "),
+                join_opt(&template.ppch_duration, " "),
+                join_opt(&template.i_flags.get_strings(), ", "),
+            ],
+        )?;
+
+        // scaled values for each archetype that can use the power
+        if let Some(table_name) = &template.pch_table {
+            let at_level = self.config.at_level;
+            for at in archetypes {
+                let archetype_id = match self.get_archetype_id(at) {
+                    Some(archetype_id) => archetype_id,
+                    None => continue,
+                };
+                if let Some(named_table) = at.pp_named_tables.get(&table_name.to_lowercase()) {
+                    if let Some(base_value) = named_table.pf_values.get((at_level - 1) as usize) {
+                        self.tx.execute(
+                            "INSERT OR IGNORE INTO attrib_mod_scaled VALUES (?1, ?2, ?3, ?4, ?5)",
+                            params![
+                                attrib_mod_id,
+                                archetype_id,
+                                at_level,
+                                base_value,
+                                base_value * template.f_scale,
+                            ],
+                        )?;
+                    }
+                }
+            }
+        }
+
+        // entities created by the attribute modifier
+        if let Some(AttribModParam::EntCreate(param)) = &template.p_params {
+            if let Some(villain) = &param.villain_def {
+                if let Some(villain_id) = self.insert_villain(villain)? {
+                    self.tx.execute(
+                        "INSERT OR IGNORE INTO attrib_mod_villains VALUES (?1, ?2)",
+                        params![attrib_mod_id, villain_id],
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a villain the first time it's seen and returns its id.
+    fn insert_villain(&mut self, villain: &Rc<VillainDef>) -> rusqlite::Result<Option<i64>> {
+        let name = match &villain.name {
+            Some(name) => name,
+            None => return Ok(None),
+        };
+        if let Some(villain_id) = self.villain_ids.get(name) {
+            return Ok(Some(*villain_id));
+        }
+        let villain_id = self.villain_ids.len() as i64 + 1;
+        self.tx.execute(
+            "INSERT INTO villains VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                villain_id,
+                name.get(),
+                villain.character_class_name.as_ref().map(|n| n.get()),
+                villain.rank.get_string(),
+                villain.description,
+                villain.display_class_name,
+                villain.ai_config,
+            ],
+        )?;
+        for power_ref in &villain.powers {
+            self.tx.execute(
+                "INSERT INTO villain_powers VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    villain_id,
+                    power_ref.power_category.as_ref().map(|n| n.get()),
+                    power_ref.power_set.as_ref().map(|n| n.get()),
+                    power_ref.power.as_ref().map(|n| n.get()),
+                    power_ref.level,
+                ],
+            )?;
+        }
+        self.villain_ids.insert(name.clone(), villain_id);
+        Ok(Some(villain_id))
+    }
+
+    fn insert_boost_sets(&mut self) -> rusqlite::Result<()> {
+        let mut boost_sets: Vec<_> = self.powers_dict.boost_sets.values().collect();
+        boost_sets.sort_by_key(|boost_set| boost_set.pch_name.as_ref().map(|n| n.get()));
+        let mut bonus_id = 0;
+        for (i, boost_set) in boost_sets.iter().enumerate() {
+            let name = match &boost_set.pch_name {
+                Some(name) => name,
+                None => continue,
+            };
+            let boost_set_id = i as i64 + 1;
+            self.tx.execute(
+                "INSERT INTO boost_sets VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    boost_set_id,
+                    name.get(),
+                    boost_set.pch_display_name,
+                    boost_set.pch_group_name,
+                    boost_set.i_min_level + 1,
+                    boost_set.i_max_level + 1,
+                ],
+            )?;
+            for power_name in &boost_set.ppch_powers {
+                self.tx.execute(
+                    "INSERT INTO boost_set_powers VALUES (?1, ?2, ?3)",
+                    params![
+                        boost_set_id,
+                        power_name.get(),
+                        self.power_ids.get(power_name)
+                    ],
+                )?;
+            }
+            for bonus in &boost_set.pp_bonuses {
+                bonus_id += 1;
+                self.tx.execute(
+                    "INSERT INTO boost_set_bonuses VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    params![
+                        bonus_id,
+                        boost_set_id,
+                        bonus.pch_display_name,
+                        bonus.i_min_boosts,
+                        bonus.i_max_boosts,
+                        join_opt(&bonus.ppch_requires, " "),
+                        bonus.pch_bonus_power.as_ref().map(|n| n.get()),
+                        bonus
+                            .pch_bonus_power
+                            .as_ref()
+                            .and_then(|n| self.power_ids.get(n)),
+                    ],
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the id of an archetype that has already been added.
+    fn get_archetype_id(&self, at: &Archetype) -> Option<i64> {
+        at.pch_name
+            .as_ref()
+            .and_then(|name| self.archetype_ids.get(name))
+            .copied()
+    }
+}
+
+/// Joins `values` with `sep`, or returns `None` if there aren't any.
+fn join_opt<S: AsRef<str>>(values: &[S], sep: &str) -> Option<String> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(
+            values
+                .iter()
+                .map(|s| s.as_ref())
+                .collect::<Vec<_>>()
+                .join(sep),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::tests::{make_config, make_powers_dict, TestOutput};
+
+    #[test]
+    fn test_scaled_values_follow_purchase_requirements() {
+        // both archetypes have the table, but only blasters can buy the power
+        let mut powers_dict = make_powers_dict();
+        let mut ats = Vec::new();
+        for name in &["Class_Blaster", "Class_Tanker"] {
+            let mut table = NamedTable::new();
+            table.pf_values = vec![10.0; 50];
+            let mut archetype = Archetype::new();
+            archetype.pch_name = Some(String::from(*name));
+            archetype.class_key = Some(NameKey::new(format!("@{}", name)));
+            archetype
+                .pp_named_tables
+                .insert(String::from("ranged_damage"), table);
+            let archetype = Rc::new(archetype);
+            powers_dict
+                .archetypes
+                .insert(NameKey::new(*name), archetype.clone());
+            ats.push(archetype);
+        }
+        let pcat = Rc::get_mut(&mut powers_dict.power_categories[0]).unwrap();
+        let pset = Rc::get_mut(&mut pcat.pp_power_sets[0]).unwrap();
+        let power = Rc::get_mut(&mut pset.pp_powers[0]).unwrap();
+        power.archetypes = ats;
+        power.ppch_buy_requires = vec![String::from("@Class_Blaster"), String::from("$archetype")];
+        let mut template = AttribModTemplate::new();
+        template.pch_table = Some(String::from("Ranged_Damage"));
+        template.f_scale = 0.5;
+        let effect_group = Rc::get_mut(&mut power.pp_effects[0]).unwrap();
+        effect_group.pp_templates.push(template);
+
+        let config = make_config(Path::new("unused"));
+        let conn = build_database(&powers_dict, &config).unwrap();
+        let mut statement = conn
+            .prepare(
+                "SELECT a.name, s.scaled_value FROM attrib_mod_scaled s \
+                 JOIN archetypes a ON a.id = s.archetype_id",
+            )
+            .unwrap();
+        let rows: Vec<(String, f32)> = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .map(|row| row.unwrap())
+            .collect();
+        assert_eq!(rows, vec![(String::from("Class_Blaster"), 5.0)]);
+    }
+
+    #[test]
+    fn test_sqlite_database() {
+        let mut output = TestOutput::new("sqlite");
+        output.config.sqlite = Some(SqliteConfig::default());
+        output.write(make_powers_dict()).unwrap();
+
+        let conn = Connection::open(output.path.join("powers.sqlite")).unwrap();
+        let query = |sql: &str| -> i64 { conn.query_row(sql, [], |row| row.get(0)).unwrap() };
+        let powers = query(
+            "SELECT COUNT(*) FROM powers p \
+             JOIN power_sets s ON s.id = p.power_set_id \
+             JOIN power_categories c ON c.id = s.power_category_id \
+             WHERE p.name = 'Ranged.Fire_Blast.Flares' AND c.name = 'Ranged'",
+        );
+        assert_eq!(powers, 1);
+        assert_eq!(query("SELECT COUNT(*) FROM power_archetypes"), 4);
+        assert_eq!(query("SELECT COUNT(*) FROM effect_groups"), 1);
+        assert_eq!(query("SELECT COUNT(*) FROM pragma_foreign_key_check"), 0);
+    }
+}
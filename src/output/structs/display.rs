@@ -126,6 +126,26 @@ pub fn describe_attrib_mod(
         tags.push("Unresistable");
     }
 
+    // Suppression and cancellation
+    let mut event_rules = Vec::new();
+    for suppress in &attrib_mod.suppress {
+        let mut rule = format!(
+            "Suppressed for {} after {}",
+            get_pretty_duration(suppress.seconds as f32),
+            suppress.event.unwrap_or("(unknown event)")
+        );
+        if !suppress.always {
+            rule.push_str(" unless already applied");
+        }
+        event_rules.push(rule);
+    }
+    if !attrib_mod.cancel_events.is_empty() {
+        event_rules.push(format!(
+            "Cancelled by {}",
+            attrib_mod.cancel_events.join(", ")
+        ));
+    }
+
     // Final adjustments for each AT scale
     for scaled in &mut attrib_mod.scaled {
         // DoT/HoT
@@ -219,6 +239,9 @@ pub fn describe_attrib_mod(
         for part in &tags {
             scaled.display_info.push(Cow::Borrowed(*part));
         }
+        for rule in &event_rules {
+            scaled.display_info.push(Cow::Owned(rule.clone()));
+        }
     }
 }
 
@@ -689,6 +712,55 @@ pub fn describe_power(power: &mut PowerOutput, base_power: &BasePower, attrib_na
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::output::tests::make_config;
+    use std::path::Path;
+    use std::rc::Rc;
+
+    #[test]
+    fn describe_suppression_test() {
+        let mut archetype = Archetype::new();
+        archetype.pch_display_name = Some(String::from("Blaster"));
+        let mut table = NamedTable::default();
+        table.pf_values = vec![1.0; 50];
+        archetype
+            .pp_named_tables
+            .insert(String::from("melee_ones"), table);
+        let mut template = AttribModTemplate::new();
+        template.p_attrib = vec![SpecialAttrib::kSpecialAttrib_Character(
+            CharacterAttributes::OFFSET_FLY as i32,
+        )];
+        template.pch_table = Some(String::from("Melee_Ones"));
+        template.f_scale = 1.0;
+        let mut suppress = SuppressPair::new();
+        suppress.idx_event = PowerEvent::kPowerEvent_HitByFoe as i32;
+        suppress.ul_seconds = 4;
+        template.pp_suppress.push(suppress);
+        template.pi_cancel_events = vec![
+            PowerEvent::kPowerEvent_Knocked,
+            PowerEvent::kPowerEvent_Defeated,
+        ];
+        let mut effect_group = EffectGroup::new();
+        effect_group.f_chance = 1.0;
+        effect_group.pp_templates.push(template);
+
+        let group = EffectGroupOutput::from_effect_group(
+            &effect_group,
+            &AttribNames::new(),
+            &BasePower::new(),
+            &vec![Rc::new(archetype)],
+            &make_config(Path::new("unused")),
+        );
+        let attrib_mod = &group.effects[0];
+        assert_eq!(attrib_mod.suppress[0].event, Some("HitByFoe"));
+        assert_eq!(attrib_mod.cancel_events, vec!["Knocked", "Defeated"]);
+        let display_info = &attrib_mod.scaled[0].display_info;
+        assert!(display_info
+            .iter()
+            .any(|line| line == "Suppressed for 4s after HitByFoe unless already applied"));
+        assert!(display_info
+            .iter()
+            .any(|line| line == "Cancelled by Knocked, Defeated"));
+    }
 
     #[test]
     fn get_pretty_duration_seconds_test() {
@@ -1,24 +1,21 @@
 use super::powers::make_power_ref_url;
 use super::*;
-use crate::structs::{Archetype, AttribModParam, AttribModTemplate, AttribNames, EffectGroup};
+use crate::structs::{
+    Archetype, AttribModMessages, AttribModParam, AttribModTemplate, AttribNames, EffectGroup,
+};
 use display;
+use schemars::JsonSchema;
 use serde::Serialize;
 use std::borrow::Cow;
-use std::collections::HashSet;
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
 
 // Tags PvP vs PvE rules.
 const PVE_TAG: &'static str = "PVE";
 const PVP_TAG: &'static str = "PVP";
 
-// Offsets into character tables.
-const OFFSET_MODIFIERS: u32 = 0;
-const OFFSET_MAXIMUM: u32 = 8;
-const OFFSET_STRENGTH: u32 = 16;
-const OFFSET_RESIST: u32 = 24;
-const OFFSET_ABSOLUTE: u32 = 32;
-
 /// Describes the different types of scaled effects.
-#[derive(Serialize)]
+#[derive(JsonSchema, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ScaledUnit {
     Damage(f32),
@@ -30,7 +27,7 @@ pub enum ScaledUnit {
     Distance(f32),
 }
 
-#[derive(Serialize)]
+#[derive(JsonSchema, Serialize)]
 pub struct AttribModParamPowerOutput {
     #[serde(skip_serializing_if = "Option::is_none")]
     category: Option<String>,
@@ -40,13 +37,13 @@ pub struct AttribModParamPowerOutput {
     power: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(JsonSchema, Serialize)]
 pub struct AttribModParamScriptValueOutput {
     id: Option<String>,
     value: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(JsonSchema, Serialize)]
 pub struct AttribModParamPowerRefAndUrl {
     pub name: Option<NameKey>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -55,7 +52,7 @@ pub struct AttribModParamPowerRefAndUrl {
     pub url: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(JsonSchema, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum AttribModParamOutput {
     Costume {
@@ -207,7 +204,7 @@ impl AttribModParamOutput {
     }
 }
 
-#[derive(Serialize)]
+#[derive(JsonSchema, Serialize)]
 pub struct AttribModScaled {
     pub archetype: Option<String>,
     #[serde(flatten)]
@@ -224,7 +221,7 @@ pub struct AttribModScaled {
     pub scale: f32,
 }
 
-#[derive(Default, Serialize)]
+#[derive(Default, JsonSchema, Serialize)]
 pub struct StackingOutput {
     pub behavior: &'static str,
     pub by_caster: bool,
@@ -265,7 +262,64 @@ impl StackingOutput {
     }
 }
 
-#[derive(Default, Serialize)]
+/// Serializable representation of a rule that keeps an attribute modifier from working for a
+/// while after an event.
+#[derive(JsonSchema, Serialize)]
+pub struct SuppressOutput {
+    pub event: Option<&'static str>,
+    pub seconds: u32,
+    pub always: bool,
+}
+
+impl SuppressOutput {
+    /// Reads fields from a `SuppressPair` to create a `SuppressOutput`.
+    fn from_suppress_pair(pair: &SuppressPair) -> Self {
+        SuppressOutput {
+            event: u32::try_from(pair.idx_event)
+                .ok()
+                .and_then(|idx| PowerEvent::try_from(idx).ok())
+                .map(|event| event.get_string()),
+            seconds: pair.ul_seconds,
+            always: pair.b_always,
+        }
+    }
+}
+
+/// Serializable representation of the combat text and floaters shown when an attribute modifier
+/// goes off.
+#[derive(JsonSchema, Serialize)]
+pub struct AttribModMessagesOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attacker_hit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub victim_hit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub float: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub defense_float: Option<String>,
+}
+
+impl AttribModMessagesOutput {
+    /// Reads fields from `AttribModMessages` to create an `AttribModMessagesOutput`, or `None` if
+    /// none of the messages are set.
+    fn from_attrib_mod_messages(messages: &AttribModMessages) -> Option<Self> {
+        if messages.pch_display_attacker_hit.is_none()
+            && messages.pch_display_victim_hit.is_none()
+            && messages.pch_display_float.is_none()
+            && messages.pch_display_defense_float.is_none()
+        {
+            return None;
+        }
+        Some(AttribModMessagesOutput {
+            attacker_hit: messages.pch_display_attacker_hit.clone(),
+            victim_hit: messages.pch_display_victim_hit.clone(),
+            float: messages.pch_display_float.clone(),
+            defense_float: messages.pch_display_defense_float.clone(),
+        })
+    }
+}
+
+#[derive(Default, JsonSchema, Serialize)]
 pub struct AttribModOutput {
     pub attributes: Vec<Cow<'static, str>>,
     pub applies_to: Option<&'static str>,
@@ -296,6 +350,12 @@ pub struct AttribModOutput {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stacking: Option<StackingOutput>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub suppress: Vec<SuppressOutput>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub cancel_events: Vec<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub messages: Option<AttribModMessagesOutput>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     pub scaled: Vec<AttribModScaled>,
     // unserialized fields
     #[serde(skip)]
@@ -316,6 +376,20 @@ impl AttribModOutput {
             after_delay_seconds: normalize(attrib_mod.f_delay),
             continuous_apply_seconds: normalize(attrib_mod.f_period),
             flags: attrib_mod.i_flags.get_strings(),
+            suppress: attrib_mod
+                .pp_suppress
+                .iter()
+                .map(SuppressOutput::from_suppress_pair)
+                .collect(),
+            cancel_events: attrib_mod
+                .pi_cancel_events
+                .iter()
+                .map(|event| event.get_string())
+                .collect(),
+            messages: attrib_mod
+                .p_messages
+                .as_ref()
+                .and_then(AttribModMessagesOutput::from_attrib_mod_messages),
             ..Default::default()
         };
         // Stacking rules
@@ -449,16 +523,42 @@ impl AttribModOutput {
     }
 }
 
-#[derive(Serialize)]
+/// Serializable representation of the chance for a procs-per-minute effect group to trigger.
+#[derive(JsonSchema, Serialize)]
+pub struct ProcChanceOutput {
+    pub chance_percent: f32,
+    pub min_chance_percent: f32,
+    pub max_chance_percent: f32,
+    pub area_factor: f32,
+}
+
+impl ProcChanceOutput {
+    /// Calculates the proc chance of `procs_per_minute` in `base_power` at its unenhanced recharge.
+    fn from_procs_per_minute(procs_per_minute: f32, base_power: &BasePower) -> Self {
+        let (min_chance, max_chance) = BasePower::ppm_chance_limits(procs_per_minute);
+        ProcChanceOutput {
+            chance_percent: normalize(
+                base_power.proc_chance(procs_per_minute, base_power.f_recharge_time) * 100.0,
+            ),
+            min_chance_percent: normalize(min_chance * 100.0),
+            max_chance_percent: normalize(max_chance * 100.0),
+            area_factor: normalize(base_power.area_factor()),
+        }
+    }
+}
+
+#[derive(JsonSchema, Serialize)]
 pub struct EffectGroupOutput {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub pve_or_pvp: Option<&'static str>,
-    #[serde(skip_serializing_if = "HashSet::is_empty")]
-    pub tags: HashSet<&'static str>,
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub tags: BTreeSet<&'static str>,
     pub visible_in_info_window: bool,
     pub chance_percent: f32,
     #[serde(skip_serializing_if = "not_normal")]
     pub procs_per_minute: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proc_chance: Option<ProcChanceOutput>,
     #[serde(skip_serializing_if = "not_normal")]
     pub after_delay_seconds: f32,
     #[serde(skip_serializing_if = "Vec::is_empty")]
@@ -480,16 +580,26 @@ impl EffectGroupOutput {
     ) -> Self {
         let mut group = EffectGroupOutput {
             pve_or_pvp: get_pve_or_pvp(&effect.ppch_tags, &effect.i_flags, &effect.ppch_requires),
-            tags: HashSet::new(),
+            tags: BTreeSet::new(),
             visible_in_info_window: true,
             chance_percent: normalize(effect.f_chance * 100.0),
             procs_per_minute: normalize(effect.f_procs_per_minute),
+            proc_chance: None,
             after_delay_seconds: normalize(effect.f_delay),
             requires: Vec::new(),
             flags: effect.i_flags.get_strings(),
             effects: Vec::new(),
             child_effect_groups: Vec::new(),
         };
+        // enhancements take their proc chance from whatever power they're slotted in
+        if effect.f_procs_per_minute.is_normal()
+            && !matches!(base_power.e_type, PowerType::kPowerType_Boost)
+        {
+            group.proc_chance = Some(ProcChanceOutput::from_procs_per_minute(
+                effect.f_procs_per_minute,
+                base_power,
+            ));
+        }
         if let Some(rule) = requires_to_string(&effect.ppch_requires) {
             group.requires.push(rule);
         }
@@ -511,7 +621,7 @@ impl EffectGroupOutput {
                 &effect.ppch_requires,
                 &attrib_names,
             );
-            calculate_damage(&mut attrib_mod_output, &group, attrib_mod, base_power);
+            calculate_damage(&mut attrib_mod_output, &group, attrib_mod, base_power, config);
             group.effects.push(attrib_mod_output);
         }
         for child_group in &effect.pp_effects {
@@ -535,7 +645,9 @@ fn calculate_damage(
     effect_group: &EffectGroupOutput,
     template: &AttribModTemplate,
     base_power: &BasePower,
+    config: &PowersConfig,
 ) {
+    let activation_time = base_power.effective_time_to_activate(&config.activation_time);
     for scaled in &mut attrib_mod.scaled {
         // look for damage/healing attributes
         if matches!(attrib_mod.attr_type, Some(AttribType::kAttribType_Abs))
@@ -579,12 +691,11 @@ fn calculate_damage(
             });
 
             // derived
-            if base_power.f_time_to_activate.is_normal() {
-                scaled.per_activation = normalize(scaled.average / base_power.f_time_to_activate);
+            if activation_time.is_normal() {
+                scaled.per_activation = normalize(scaled.average / activation_time);
                 if base_power.f_recharge_time.is_normal() {
                     scaled.per_cast_cycle = normalize(
-                        scaled.average
-                            / (base_power.f_time_to_activate + base_power.f_recharge_time),
+                        scaled.average / (activation_time + base_power.f_recharge_time),
                     );
                 }
             }
@@ -876,3 +987,21 @@ fn get_scaled_effect(
     // anything else is a special case and doesn't use scaling (creating entities, granting powers, etc.)
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attrib_mod_messages_output() {
+        let mut messages = AttribModMessages::new();
+        assert!(AttribModMessagesOutput::from_attrib_mod_messages(&messages).is_none());
+
+        messages.pch_display_float = Some(String::from("Flying"));
+        let output = AttribModMessagesOutput::from_attrib_mod_messages(&messages).unwrap();
+        assert_eq!(output.float.as_deref(), Some("Flying"));
+        assert!(output.attacker_hit.is_none());
+        let json = serde_json::to_value(&output).unwrap();
+        assert_eq!(json, serde_json::json!({ "float": "Flying" }));
+    }
+}
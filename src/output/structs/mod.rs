@@ -5,10 +5,13 @@ mod powers;
 use super::{make_file_name, JSON_FILE};
 use crate::structs::config::{AssetsConfig, PowersConfig};
 use crate::structs::*;
-use powers::PowerOutput;
+pub use effects::{AttribModOutput, AttribModScaled, EffectGroupOutput, ScaledUnit};
+pub(crate) use powers::filter_archetypes_pwr;
+pub use powers::PowerOutput;
+use schemars::JsonSchema;
 use serde::Serialize;
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::rc::Rc;
 
 /// Used when joining parts of an URL together.
@@ -18,7 +21,7 @@ const URL_SEP: char = '/';
 const ORIGINS_SIZE: usize = 5;
 
 /// Common fields added to other structs.
-#[derive(Serialize)]
+#[derive(JsonSchema, Serialize)]
 pub struct HeaderOutput {
     pub issue: Option<String>,
     pub source: Option<String>,
@@ -27,7 +30,7 @@ pub struct HeaderOutput {
 
 impl HeaderOutput {
     /// Creates a `HeaderOutput` from a `PowersConfig`.
-    fn from_config(config: &PowersConfig) -> Self {
+    pub(crate) fn from_config(config: &PowersConfig) -> Self {
         HeaderOutput {
             issue: Some(config.issue.clone()),
             source: Some(config.source.clone()),
@@ -38,7 +41,7 @@ impl HeaderOutput {
 
 /// Additional fields to include in `ArchetypeOutput` if we're dumping a full
 /// view of the archetypes.
-#[derive(Serialize)]
+#[derive(JsonSchema, Serialize)]
 pub struct ExtendedArchetypeOutput {
     #[serde(skip_serializing_if = "Option::is_none")]
     display_help: Option<String>,
@@ -69,7 +72,7 @@ impl ExtendedArchetypeOutput {
 }
 
 /// Serializable representation of an archetype.
-#[derive(Serialize)]
+#[derive(JsonSchema, Serialize)]
 pub struct ArchetypeOutput {
     pub name: Option<String>,
     pub display_name: Option<String>,
@@ -114,7 +117,7 @@ impl ArchetypeOutput {
     }
 }
 
-#[derive(Serialize)]
+#[derive(JsonSchema, Serialize)]
 pub struct ArchetypesOutput {
     #[serde(flatten)]
     pub header: HeaderOutput,
@@ -128,7 +131,9 @@ impl ArchetypesOutput {
             header: HeaderOutput::from_config(config),
             archetypes: Vec::new(),
         };
-        for at in ats.values() {
+        let mut ats: Vec<_> = ats.values().collect();
+        ats.sort_by(|a, b| a.pch_name.cmp(&b.pch_name));
+        for at in ats {
             ats_out.archetypes.push(ArchetypeOutput::from_archetype(
                 at,
                 &PrimarySecondary::None,
@@ -141,7 +146,7 @@ impl ArchetypesOutput {
 }
 
 /// Serializable representation of a power category in the root index.
-#[derive(Serialize)]
+#[derive(JsonSchema, Serialize)]
 pub struct RootPowerCategory {
     pub name: Option<NameKey>,
     pub display_name: Option<String>,
@@ -151,7 +156,7 @@ pub struct RootPowerCategory {
 }
 
 /// Serializable representation of the root index.
-#[derive(Serialize)]
+#[derive(JsonSchema, Serialize)]
 pub struct RootOutput {
     #[serde(flatten)]
     pub header: HeaderOutput,
@@ -225,7 +230,7 @@ impl RootOutput {
 }
 
 /// Serializable representation of a power set in a power category.
-#[derive(Serialize)]
+#[derive(JsonSchema, Serialize)]
 pub struct PowerCategoryPowerSetOutput {
     pub name: Option<NameKey>,
     pub display_name: Option<String>,
@@ -233,7 +238,7 @@ pub struct PowerCategoryPowerSetOutput {
 }
 
 /// Serializable representation of a power category.
-#[derive(Serialize)]
+#[derive(JsonSchema, Serialize)]
 pub struct PowerCategoryOutput {
     #[serde(flatten)]
     pub header: HeaderOutput,
@@ -303,7 +308,7 @@ impl PowerCategoryOutput {
 }
 
 /// Serializable representation of a power set.
-#[derive(Serialize)]
+#[derive(JsonSchema, Serialize)]
 pub struct PowerSetOutput {
     #[serde(flatten)]
     header: HeaderOutput,
@@ -325,9 +330,21 @@ pub struct PowerSetOutput {
     #[serde(skip_serializing_if = "Option::is_none")]
     display_set_buy_requires_failed: Option<String>,
     ordered_power_names: Vec<NameKey>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    customization_themes: Vec<CustomizationThemeOutput>,
     powers: Vec<PowerOutput>,
 }
 
+/// Serializable summary of a power customization theme offered by the powers in a power set.
+#[derive(JsonSchema, Serialize)]
+pub struct CustomizationThemeOutput {
+    token: Option<String>,
+    display_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    category: Option<String>,
+    powers: Vec<NameKey>,
+}
+
 impl PowerSetOutput {
     /// Converts a `BasePowerSet` to a `PowerSetOutput` ready for serialization.
     ///
@@ -365,6 +382,7 @@ impl PowerSetOutput {
             set_buy_requires: requires_to_string(&power_set.ppch_set_buy_requires),
             display_set_buy_requires_failed: None,
             ordered_power_names: Vec::new(),
+            customization_themes: Vec::new(),
             powers: Vec::new(),
         };
         // specialization info
@@ -439,8 +457,33 @@ impl PowerSetOutput {
         // sort powers
         pset.powers
             .sort_by(|a, b| a.available_at_level.cmp(&b.available_at_level));
+        // customization themes offered by any of the powers, by token
+        let mut themes: BTreeMap<Option<String>, CustomizationThemeOutput> = BTreeMap::new();
+        for power in &pset.powers {
+            for custom_fx in &power.custom_fx {
+                let theme = themes.entry(custom_fx.token.clone()).or_insert_with(|| {
+                    CustomizationThemeOutput {
+                        token: custom_fx.token.clone(),
+                        display_name: custom_fx.display_name.clone(),
+                        category: custom_fx.category.clone(),
+                        powers: Vec::new(),
+                    }
+                });
+                if let Some(power_name) = &power.name {
+                    if theme.powers.last() != Some(power_name) {
+                        theme.powers.push(power_name.clone());
+                    }
+                }
+            }
+        }
+        pset.customization_themes = themes.into_iter().map(|(_, theme)| theme).collect();
         pset
     }
+
+    /// Takes the powers out of the power set, sorted by the level they're available at.
+    pub fn into_powers(self) -> Vec<PowerOutput> {
+        self.powers
+    }
 }
 
 /// Rewrites an icon name from a .bin file into a file name with new extension and
@@ -697,8 +740,20 @@ fn mode_attrib_to_string(attrib: &SpecialAttrib, attrib_names: &AttribNames) ->
     None
 }
 
+/// Converts an attribute aspect offset to a short aspect name.
+pub(crate) fn aspect_to_string(off_aspect: u32) -> &'static str {
+    match off_aspect {
+        OFFSET_MODIFIERS => "Cur",
+        OFFSET_MAXIMUM => "Max",
+        OFFSET_STRENGTH => "Str",
+        OFFSET_RESIST => "Res",
+        OFFSET_ABSOLUTE => "Abs",
+        _ => "Unknown",
+    }
+}
+
 /// Converts a character attribute to a human readable string.
-fn character_attrib_to_string(
+pub(crate) fn character_attrib_to_string(
     attrib: &SpecialAttrib,
     attrib_names: &AttribNames,
 ) -> Option<Cow<'static, str>> {
@@ -814,3 +869,36 @@ fn character_attrib_to_string(
     debug_assert!(false, "Unampped attrib: {:?}", attrib);
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::tests::{make_config, make_powers_dict};
+    use std::path::Path;
+
+    #[test]
+    fn test_customization_themes() {
+        let mut powers_dict = make_powers_dict();
+        let pcat = Rc::get_mut(&mut powers_dict.power_categories[0]).unwrap();
+        let pset = Rc::get_mut(&mut pcat.pp_power_sets[0]).unwrap();
+        let power = Rc::get_mut(&mut pset.pp_powers[0]).unwrap();
+        for token in &["Blue", "Blue", "Green"] {
+            let mut custom_fx = CustomPowerFX::new();
+            custom_fx.pch_token = Some(String::from(*token));
+            custom_fx.pch_display_name = Some(format!("{} Fire", token));
+            power.pp_custom_fx.push(custom_fx);
+        }
+
+        let pset = PowerSetOutput::from_base_power_set(
+            &pcat.pp_power_sets[0],
+            &powers_dict.attrib_names,
+            &make_config(Path::new("unused")),
+        );
+        let themes: Vec<_> = pset
+            .customization_themes
+            .iter()
+            .map(|theme| (theme.token.as_deref(), theme.powers.len()))
+            .collect();
+        assert_eq!(themes, vec![(Some("Blue"), 1), (Some("Green"), 1)]);
+    }
+}
@@ -2,11 +2,13 @@ use super::effects::*;
 use super::*;
 use crate::structs::*;
 use display;
+use schemars::JsonSchema;
 use serde::Serialize;
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 
 /// Serializable representation of crowd control flags.
-#[derive(Serialize)]
+#[derive(JsonSchema, Serialize)]
 pub struct StatusOptionsOutput {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub cast_through: Vec<&'static str>,
@@ -52,7 +54,7 @@ impl StatusOptionsOutput {
 }
 
 /// Serializable representation of a power's area of effect and range.
-#[derive(Serialize)]
+#[derive(JsonSchema, Serialize)]
 pub struct EffectAreaOutput {
     pub area: Option<&'static str>,
     #[serde(skip_serializing_if = "is_zero")]
@@ -99,10 +101,12 @@ impl EffectAreaOutput {
 }
 
 /// Serializable representation of a power's activation time and cost.
-#[derive(Serialize)]
+#[derive(JsonSchema, Serialize)]
 pub struct ActivationOutput {
     pub cast_time: f32,
     #[serde(skip_serializing_if = "not_normal")]
+    pub effective_cast_time: f32,
+    #[serde(skip_serializing_if = "not_normal")]
     pub animation_time: f32,
     #[serde(skip_serializing_if = "not_normal")]
     pub animation_time_before_hit: f32,
@@ -116,9 +120,12 @@ pub struct ActivationOutput {
 
 impl ActivationOutput {
     /// Reads fields from a `BasePower` to create an `ActivationOutput`.
-    fn from_base_power(power: &BasePower) -> Self {
+    fn from_base_power(power: &BasePower, config: &PowersConfig) -> Self {
         let mut activate = ActivationOutput {
             cast_time: normalize(power.f_time_to_activate),
+            effective_cast_time: normalize(
+                power.effective_time_to_activate(&config.activation_time),
+            ),
             animation_time: 0.0,
             animation_time_before_hit: 0.0,
             recharge_time: normalize(power.f_recharge_time),
@@ -135,7 +142,296 @@ impl ActivationOutput {
     }
 }
 
-#[derive(Serialize)]
+/// Serializable representation of a power's animation timing and visual effects.
+#[derive(JsonSchema, Serialize)]
+pub struct PowerFXOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_file: Option<String>,
+    #[serde(skip_serializing_if = "not_normal")]
+    pub animation_time: f32,
+    #[serde(skip_serializing_if = "not_normal")]
+    pub time_before_hit: f32,
+    #[serde(skip_serializing_if = "not_normal")]
+    pub time_before_secondary_hit: f32,
+    #[serde(skip_serializing_if = "not_normal")]
+    pub time_before_block: f32,
+    #[serde(skip_serializing_if = "not_normal")]
+    pub initial_time_before_hit: f32,
+    #[serde(skip_serializing_if = "not_normal")]
+    pub initial_time_before_block: f32,
+    #[serde(skip_serializing_if = "not_normal")]
+    pub initial_attack_fx_delay: f32,
+    pub delayed_hit: bool,
+    #[serde(skip_serializing_if = "not_normal")]
+    pub projectile_speed: f32,
+    #[serde(skip_serializing_if = "not_normal")]
+    pub secondary_projectile_speed: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub activation_fx: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deactivation_fx: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wind_up_fx: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub initial_attack_fx: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attack_fx: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secondary_attack_fx: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hit_fx: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_fx: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub death_fx: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub continuing_fx: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub conditional_fx: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub mode_bits: Vec<i32>,
+    pub important: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_tint_primary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_tint_secondary: Option<String>,
+}
+
+impl PowerFXOutput {
+    /// Reads fields from a `PowerFX` to create a `PowerFXOutput`. Frame counts are converted
+    /// into seconds. A time of 0 means the client uses its default (15 frames before the hit,
+    /// 35 frames for the attack), so it's left out.
+    pub fn from_power_fx(fx: &PowerFX) -> Self {
+        let seconds = |frames: i32| normalize4(PowerFX::frames_as_seconds(frames));
+        PowerFXOutput {
+            source_file: fx.pch_source_file.clone(),
+            animation_time: seconds(fx.i_frames_attack),
+            time_before_hit: seconds(fx.i_frames_before_hit),
+            time_before_secondary_hit: seconds(fx.i_frames_before_secondary_hit),
+            time_before_block: seconds(fx.i_frames_before_block),
+            initial_time_before_hit: seconds(fx.i_initial_frames_before_hit),
+            initial_time_before_block: seconds(fx.i_initial_frames_before_block),
+            initial_attack_fx_delay: seconds(fx.i_initial_attack_fx_frame_delay),
+            delayed_hit: fx.b_delayed_hit,
+            projectile_speed: normalize(fx.f_projectile_speed),
+            secondary_projectile_speed: normalize(fx.f_secondary_projectile_speed),
+            activation_fx: fx.pch_activation_fx.clone(),
+            deactivation_fx: fx.pch_deactivation_fx.clone(),
+            wind_up_fx: fx.pch_wind_up_fx.clone(),
+            initial_attack_fx: fx.pch_initial_attack_fx.clone(),
+            attack_fx: fx.pch_attack_fx.clone(),
+            secondary_attack_fx: fx.pch_secondary_attack_fx.clone(),
+            hit_fx: fx.pch_hit_fx.clone(),
+            block_fx: fx.pch_block_fx.clone(),
+            death_fx: fx.pch_death_fx.clone(),
+            continuing_fx: fx.ppch_continuing_fx.clone(),
+            conditional_fx: fx.ppch_conditional_fx.clone(),
+            mode_bits: fx.pi_mode_bits.clone(),
+            important: fx.b_important,
+            default_tint_primary: tint_to_string(&fx.rgba_default_tint_primary),
+            default_tint_secondary: tint_to_string(&fx.rgba_default_tint_secondary),
+        }
+    }
+}
+
+/// Serializable representation of the `PowerFXOutput` fields a customization theme changes. Fields
+/// the theme leaves alone are left out. Changed fields have the theme's value, even where
+/// `PowerFXOutput` would leave it out (e.g. a time of 0), and `null` means the theme clears a value
+/// the default FX has.
+#[derive(Default, JsonSchema, PartialEq, Serialize)]
+pub struct PowerFXChangesOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_file: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub animation_time: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_before_hit: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_before_secondary_hit: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_before_block: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub initial_time_before_hit: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub initial_time_before_block: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub initial_attack_fx_delay: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delayed_hit: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub projectile_speed: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secondary_projectile_speed: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub activation_fx: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deactivation_fx: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wind_up_fx: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub initial_attack_fx: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attack_fx: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secondary_attack_fx: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hit_fx: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_fx: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub death_fx: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub continuing_fx: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conditional_fx: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode_bits: Option<Vec<i32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub important: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_tint_primary: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_tint_secondary: Option<Option<String>>,
+}
+
+impl PowerFXChangesOutput {
+    /// Compares `custom` to `default` to create a `PowerFXChangesOutput`, or `None` if they're
+    /// the same.
+    fn from_power_fx_outputs(custom: &PowerFXOutput, default: &PowerFXOutput) -> Option<Self> {
+        macro_rules! changes {
+            ($($field:ident),*) => {
+                PowerFXChangesOutput {
+                    $($field: if custom.$field != default.$field {
+                        Some(custom.$field.clone())
+                    } else {
+                        None
+                    },)*
+                }
+            };
+        }
+        let changes = changes!(
+            source_file,
+            animation_time,
+            time_before_hit,
+            time_before_secondary_hit,
+            time_before_block,
+            initial_time_before_hit,
+            initial_time_before_block,
+            initial_attack_fx_delay,
+            delayed_hit,
+            projectile_speed,
+            secondary_projectile_speed,
+            activation_fx,
+            deactivation_fx,
+            wind_up_fx,
+            initial_attack_fx,
+            attack_fx,
+            secondary_attack_fx,
+            hit_fx,
+            block_fx,
+            death_fx,
+            continuing_fx,
+            conditional_fx,
+            mode_bits,
+            important,
+            default_tint_primary,
+            default_tint_secondary
+        );
+        if changes == PowerFXChangesOutput::default() {
+            None
+        } else {
+            Some(changes)
+        }
+    }
+}
+
+/// Serializable representation of an alternate animation theme the player can pick when
+/// customizing a power.
+#[derive(JsonSchema, Serialize)]
+pub struct CustomFXOutput {
+    pub token: Option<String>,
+    pub display_name: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub alt_themes: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub palette: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fx_changes: Option<PowerFXChangesOutput>,
+}
+
+impl CustomFXOutput {
+    /// Reads fields from a `CustomPowerFX` to create a `CustomFXOutput`. Only the FX fields
+    /// that differ from `default_fx` are kept. If the power doesn't have default FX, the fields
+    /// are compared to empty FX instead.
+    fn from_custom_power_fx(custom_fx: &CustomPowerFX, default_fx: Option<&PowerFXOutput>) -> Self {
+        let fx_changes = custom_fx.p_fx.as_ref().and_then(|fx| {
+            let custom = PowerFXOutput::from_power_fx(fx);
+            match default_fx {
+                Some(default) => PowerFXChangesOutput::from_power_fx_outputs(&custom, default),
+                None => PowerFXChangesOutput::from_power_fx_outputs(
+                    &custom,
+                    &PowerFXOutput::from_power_fx(&PowerFX::new()),
+                ),
+            }
+        });
+        CustomFXOutput {
+            token: custom_fx.pch_token.clone(),
+            display_name: custom_fx.pch_display_name.clone(),
+            alt_themes: custom_fx.ppch_alt_themes.clone(),
+            category: custom_fx.pch_category.clone(),
+            palette: custom_fx.pch_palette_name.clone(),
+            fx_changes,
+        }
+    }
+}
+
+/// Formats a tint as a hex color (`#rrggbbaa`), or `None` if there isn't one.
+fn tint_to_string(tint: &RGBA) -> Option<String> {
+    if tint.r() == 0 && tint.g() == 0 && tint.b() == 0 && tint.a() == 0 {
+        None
+    } else {
+        Some(format!(
+            "#{:02x}{:02x}{:02x}{:02x}",
+            tint.r(),
+            tint.g(),
+            tint.b(),
+            tint.a()
+        ))
+    }
+}
+
+/// Serializable representation of the combat text shown when a power hits or is rewarded.
+#[derive(JsonSchema, Serialize)]
+pub struct CombatTextOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attacker_hit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub victim_hit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub float_rewarded: Option<String>,
+}
+
+impl CombatTextOutput {
+    /// Reads fields from a `BasePower` to create a `CombatTextOutput`, or `None` if the power
+    /// doesn't have any combat text.
+    fn from_base_power(power: &BasePower) -> Option<Self> {
+        if power.pch_display_attacker_hit.is_none()
+            && power.pch_display_victim_hit.is_none()
+            && power.pch_display_float_rewarded.is_none()
+        {
+            return None;
+        }
+        Some(CombatTextOutput {
+            attacker_hit: power.pch_display_attacker_hit.clone(),
+            victim_hit: power.pch_display_victim_hit.clone(),
+            float_rewarded: power.pch_display_float_rewarded.clone(),
+        })
+    }
+}
+
+#[derive(JsonSchema, Serialize)]
 pub struct UsageOutput {
     remove_on_limit: bool,
     extend_on_additional_grant: bool,
@@ -196,7 +492,7 @@ impl UsageOutput {
     }
 }
 
-#[derive(Serialize)]
+#[derive(JsonSchema, Serialize)]
 pub struct PowerRedirectOutput {
     pub name: Option<NameKey>,
     pub fallback: bool,
@@ -218,8 +514,27 @@ impl PowerRedirectOutput {
     }
 }
 
+/// Serializable representation of a reverse reference to another power.
+#[derive(JsonSchema, Serialize)]
+pub struct PowerReferenceOutput {
+    pub relation: &'static str,
+    pub name: NameKey,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+impl PowerReferenceOutput {
+    fn from_power_reference(reference: &PowerReference, config: &PowersConfig) -> Self {
+        PowerReferenceOutput {
+            relation: reference.relation.get_string(),
+            name: reference.power_name.clone(),
+            url: make_power_ref_url(Some(&reference.power_name), config),
+        }
+    }
+}
+
 /// Serializable representation of a power.
-#[derive(Serialize)]
+#[derive(JsonSchema, Serialize)]
 pub struct PowerOutput {
     pub name: Option<NameKey>,
     pub display_name: Option<String>,
@@ -229,8 +544,8 @@ pub struct PowerOutput {
     pub display_help: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub display_short_help: Option<String>,
-    #[serde(skip_serializing_if = "HashMap::is_empty")]
-    pub display_info: HashMap<&'static str, Cow<'static, str>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub display_info: BTreeMap<&'static str, Cow<'static, str>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub requires: Option<String>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
@@ -266,9 +581,17 @@ pub struct PowerOutput {
     pub activate: ActivationOutput,
     #[serde(skip_serializing_if = "UsageOutput::is_empty")]
     pub usage: UsageOutput,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fx: Option<PowerFXOutput>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub custom_fx: Vec<CustomFXOutput>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub combat_text: Option<CombatTextOutput>,
     pub effect_groups: Vec<EffectGroupOutput>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub redirects: Vec<PowerRedirectOutput>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub referenced_by: Vec<PowerReferenceOutput>,
 }
 
 impl PowerOutput {
@@ -284,7 +607,7 @@ impl PowerOutput {
             icon: None,
             display_help: power.pch_display_help.clone(),
             display_short_help: power.pch_display_short_help.clone(),
-            display_info: HashMap::new(),
+            display_info: BTreeMap::new(),
             requires: requires_to_string(&power.ppch_buy_requires),
             attack_types: Vec::new(),
             enhancements_allowed: Vec::new(),
@@ -313,10 +636,14 @@ impl PowerOutput {
             modes_required: Vec::new(),
             modes_disallowed: Vec::new(),
             status_interaction: StatusOptionsOutput::from_base_power(power),
-            activate: ActivationOutput::from_base_power(power),
+            activate: ActivationOutput::from_base_power(power, config),
             usage: UsageOutput::from_base_power(power),
+            fx: power.p_fx.as_ref().map(PowerFXOutput::from_power_fx),
+            custom_fx: Vec::new(),
+            combat_text: CombatTextOutput::from_base_power(power),
             effect_groups: Vec::new(),
             redirects: Vec::new(),
+            referenced_by: Vec::new(),
         };
         // power icon
         if let Some(icon) = power.pch_icon_name.as_ref() {
@@ -357,6 +684,13 @@ impl PowerOutput {
                 }
             }
         }
+        // power customization themes
+        for custom_fx in &power.pp_custom_fx {
+            pwr.custom_fx.push(CustomFXOutput::from_custom_power_fx(
+                custom_fx,
+                pwr.fx.as_ref(),
+            ));
+        }
         // filter archetypes to only those that can purchase this power, if necessary
         let archetypes = filter_archetypes_pwr(power, &power.archetypes);
         // effect groups
@@ -374,6 +708,16 @@ impl PowerOutput {
             pwr.redirects
                 .push(PowerRedirectOutput::from_power_redirect(&redirect, config));
         }
+        // powers that link back to this one
+        for reference in &power.referenced_by {
+            pwr.referenced_by
+                .push(PowerReferenceOutput::from_power_reference(reference, config));
+        }
+        pwr.referenced_by.sort_by(|a, b| {
+            a.relation
+                .cmp(b.relation)
+                .then_with(|| a.name.to_string().cmp(&b.name.to_string()))
+        });
         // set display information
         display::describe_power(&mut pwr, &power, attrib_names);
         pwr
@@ -382,7 +726,10 @@ impl PowerOutput {
 
 /// Filters the archetypes vector based on any purchase requirements specified in `power`.
 /// If `power` has no requirements, all archetypes passed in will be returned.
-fn filter_archetypes_pwr(power: &BasePower, archetypes: &Vec<Rc<Archetype>>) -> Vec<Rc<Archetype>> {
+pub(crate) fn filter_archetypes_pwr(
+    power: &BasePower,
+    archetypes: &Vec<Rc<Archetype>>,
+) -> Vec<Rc<Archetype>> {
     if power
         .ppch_buy_requires
         .iter()
@@ -433,3 +780,131 @@ pub fn make_power_ref_url(power_ref: Option<&NameKey>, config: &PowersConfig) ->
     }
     Some(url)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_power_fx() -> PowerFX {
+        let mut fx = PowerFX::new();
+        fx.pch_attack_fx = Some(String::from("POWERS/Fire/FlaresAttack.fx"));
+        fx.i_frames_attack = 37;
+        fx.i_frames_before_hit = 15;
+        fx.b_delayed_hit = true;
+        fx.f_projectile_speed = 60.0;
+        fx.rgba_default_tint_primary = RGBA::new(255, 128, 0, 255);
+        fx
+    }
+
+    #[test]
+    fn test_power_fx_output() {
+        let fx = make_power_fx();
+        let output = PowerFXOutput::from_power_fx(&fx);
+        assert_eq!(output.animation_time, 1.2333);
+        assert_eq!(output.time_before_hit, 0.5);
+        assert_eq!(output.projectile_speed, 60.0);
+        assert_eq!(output.default_tint_primary.as_deref(), Some("#ff8000ff"));
+        assert!(output.default_tint_secondary.is_none());
+
+        // frame counts of 0 mean the client's defaults and are left out
+        let json = serde_json::to_value(&output).unwrap();
+        assert_eq!(json["attack_fx"], "POWERS/Fire/FlaresAttack.fx");
+        assert_eq!(json["delayed_hit"], true);
+        assert!(json.get("time_before_block").is_none());
+        assert!(json.get("default_tint_secondary").is_none());
+    }
+
+    #[test]
+    fn test_custom_fx_output() {
+        let default_fx = PowerFXOutput::from_power_fx(&make_power_fx());
+        // a different attack, no tint, and the hit at the start of the animation
+        let mut blue_fx = make_power_fx();
+        blue_fx.pch_attack_fx = Some(String::from("POWERS/Fire/FlaresAttack_Blue.fx"));
+        blue_fx.rgba_default_tint_primary = RGBA::new(0, 0, 0, 0);
+        blue_fx.i_frames_before_hit = 0;
+        let mut custom_fx = CustomPowerFX::new();
+        custom_fx.pch_token = Some(String::from("Blue"));
+        custom_fx.p_fx = Some(blue_fx);
+
+        let output = CustomFXOutput::from_custom_power_fx(&custom_fx, Some(&default_fx));
+        let changes = output.fx_changes.as_ref().unwrap();
+        assert_eq!(
+            changes.attack_fx,
+            Some(Some(String::from("POWERS/Fire/FlaresAttack_Blue.fx")))
+        );
+        assert_eq!(changes.default_tint_primary, Some(None));
+        assert_eq!(changes.time_before_hit, Some(0.0));
+        assert!(changes.animation_time.is_none());
+        assert!(changes.projectile_speed.is_none());
+
+        // cleared values are null, times of 0 are kept, and unchanged fields are left out
+        let json = serde_json::to_value(&output).unwrap();
+        let fx_changes = json["fx_changes"].as_object().unwrap();
+        assert_eq!(fx_changes.len(), 3);
+        assert!(fx_changes["default_tint_primary"].is_null());
+        assert_eq!(fx_changes["time_before_hit"], 0.0);
+
+        // the same FX as the default doesn't change anything
+        custom_fx.p_fx = Some(make_power_fx());
+        let output = CustomFXOutput::from_custom_power_fx(&custom_fx, Some(&default_fx));
+        assert!(output.fx_changes.is_none());
+
+        // without default FX, everything the theme sets is a change
+        let output = CustomFXOutput::from_custom_power_fx(&custom_fx, None);
+        let changes = output.fx_changes.unwrap();
+        assert_eq!(changes.time_before_hit, Some(0.5));
+        assert!(changes.time_before_block.is_none());
+    }
+
+    #[test]
+    fn test_combat_text_output() {
+        let mut power = BasePower::new();
+        assert!(CombatTextOutput::from_base_power(&power).is_none());
+
+        power.pch_display_attacker_hit = Some(String::from("You hit your target with Flares."));
+        power.pch_display_victim_hit = Some(String::from("Flares hits you."));
+        let combat_text = CombatTextOutput::from_base_power(&power).unwrap();
+        assert_eq!(
+            combat_text.attacker_hit.as_deref(),
+            Some("You hit your target with Flares.")
+        );
+        assert_eq!(combat_text.victim_hit.as_deref(), Some("Flares hits you."));
+        let json = serde_json::to_value(&combat_text).unwrap();
+        assert!(json.get("float_rewarded").is_none());
+    }
+
+    #[test]
+    fn test_referenced_by_output() {
+        let mut power = BasePower::new();
+        power.pch_full_name = Some(NameKey::new("Ranged.Fire_Blast.Flares"));
+        for (relation, power_name) in &[
+            (PowerRelation::SummonedBy, "Ranged.Fire_Blast.Rain_of_Fire"),
+            (PowerRelation::GrantedBy, "Ranged.Fire_Blast.Nova"),
+            (PowerRelation::GrantedBy, "Ranged.Fire_Blast.Blaze"),
+        ] {
+            power.referenced_by.push(PowerReference {
+                relation: *relation,
+                power_name: NameKey::new(*power_name),
+            });
+        }
+        let config = crate::output::tests::make_config(std::path::Path::new("output"));
+        let output = PowerOutput::from_base_power(&power, &AttribNames::new(), &config);
+        let references: Vec<_> = output
+            .referenced_by
+            .iter()
+            .map(|r| (r.relation, r.name.to_string()))
+            .collect();
+        assert_eq!(
+            references,
+            vec![
+                ("GrantedBy", String::from("Ranged.Fire_Blast.Blaze")),
+                ("GrantedBy", String::from("Ranged.Fire_Blast.Nova")),
+                ("SummonedBy", String::from("Ranged.Fire_Blast.Rain_of_Fire")),
+            ]
+        );
+        assert_eq!(
+            output.referenced_by[0].url.as_deref(),
+            Some("../../ranged/fire-blast/index.json")
+        );
+    }
+}
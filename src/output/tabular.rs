@@ -0,0 +1,445 @@
+use super::manifest::OutputFiles;
+use super::structs::*;
+use crate::structs::config::{PowersConfig, TableFormatConfig, TablesConfig};
+use crate::structs::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+/// Base names for the table files. The extension depends on the format.
+const POWERS_TABLE: &'static str = "powers";
+const EFFECTS_TABLE: &'static str = "effects";
+
+/// Columns available in the powers table, in their default order.
+const POWER_COLUMNS: &[&str] = &[
+    "name",
+    "display_name",
+    "power_set",
+    "power_type",
+    "available_at_level",
+    "accuracy",
+    "activation_time",
+    "effective_activation_time",
+    "recharge_time",
+    "endurance_cost",
+    "range",
+    "radius",
+    "arc",
+    "max_targets",
+    "effect_area",
+    "damage",
+];
+
+/// Expands to one column per archetype in the powers table.
+const DAMAGE_COLUMN: &'static str = "damage";
+
+/// Columns available in the effects table, in their default order.
+const EFFECT_COLUMNS: &[&str] = &[
+    "power",
+    "effect_group",
+    "pve_or_pvp",
+    "chance_percent",
+    "attributes",
+    "applies_to",
+    "application_type",
+    "target_type",
+    "archetype",
+    "unit",
+    "value",
+    "average",
+    "per_activation",
+    "per_cast_cycle",
+    "base_value",
+    "scale",
+    "duration_seconds",
+    "after_delay_seconds",
+    "tick_chance_percent",
+    "flags",
+];
+
+/// A power, along with the power set it came from.
+struct PowerRow<'a> {
+    power_set: &'a str,
+    power: &'a PowerOutput,
+}
+
+/// A single scaled value of an attribute modifier.
+struct EffectRow<'a> {
+    power: &'a PowerOutput,
+    /// Position of the effect group in the power, e.g. `2.1` for the first child of the second group.
+    effect_group_path: String,
+    effect_group: &'a EffectGroupOutput,
+    effect: &'a AttribModOutput,
+    scaled: &'a AttribModScaled,
+}
+
+/// Writes the powers and their scaled effects as tables that can be opened in a spreadsheet.
+///
+/// # Arguments:
+///
+/// * `files` - The output files being written.
+/// * `powers_dict` - A `PowersDictionary` containing a hierarchy of categories, power sets, and powers.
+/// * `tables_config` - Tabular export configuration information.
+/// * `config` - Configuration information.
+///
+/// # Returns:
+///
+/// Nothing if the operation was successful. Otherwise, an `io::Error` containing the error information.
+///
+/// # Notes:
+///
+/// Two files are written: `powers` has one row per power, and `effects` has one row per scaled attribute
+/// modifier (one for each archetype that can use the power). Both are derived from the same `PowerOutput`
+/// as the .json files. The `damage` column of the powers table is expanded to one column per archetype,
+/// totalling the average damage of the power's PvE effects.
+pub fn write_tables(
+    files: &mut OutputFiles,
+    powers_dict: &PowersDictionary,
+    tables_config: &TablesConfig,
+    config: &PowersConfig,
+) -> io::Result<()> {
+    let power_columns = select_columns(&tables_config.power_columns, POWER_COLUMNS, POWERS_TABLE)?;
+    let effect_columns =
+        select_columns(&tables_config.effect_columns, EFFECT_COLUMNS, EFFECTS_TABLE)?;
+
+    // collect the same powers that are written to the .json files
+    let mut power_sets = Vec::new();
+    for category in &powers_dict.power_categories {
+        if !category.include_in_output {
+            continue;
+        }
+        for set in &category.pp_power_sets {
+            if let (true, Some(set_name)) = (set.include_in_output, &set.pch_full_name) {
+                let pset =
+                    PowerSetOutput::from_base_power_set(set, &powers_dict.attrib_names, config);
+                power_sets.push((set_name.get(), pset.into_powers()));
+            }
+        }
+    }
+    let powers: Vec<_> = power_sets
+        .iter()
+        .flat_map(|(power_set, powers)| {
+            powers
+                .iter()
+                .map(move |power| PowerRow { power_set, power })
+        })
+        .collect();
+
+    // powers table
+    let damage_by_power: Vec<_> = powers.iter().map(|row| total_damage(row.power)).collect();
+    let archetypes: BTreeSet<_> = damage_by_power
+        .iter()
+        .flat_map(|damage| damage.keys().cloned())
+        .collect();
+    let mut table = Table::new(tables_config.format);
+    table.push_row(power_columns.iter().flat_map(|column| {
+        if *column == DAMAGE_COLUMN {
+            archetypes
+                .iter()
+                .map(|at| format!("{} ({})", DAMAGE_COLUMN, at))
+                .collect()
+        } else {
+            vec![String::from(*column)]
+        }
+    }));
+    for (row, damage) in powers.iter().zip(&damage_by_power) {
+        table.push_row(power_columns.iter().flat_map(|column| {
+            if *column == DAMAGE_COLUMN {
+                archetypes
+                    .iter()
+                    .map(|at| damage.get(at).map(|d| d.to_string()).unwrap_or_default())
+                    .collect()
+            } else {
+                vec![power_cell(column, row)]
+            }
+        }));
+    }
+    files.write(
+        Path::new(&tables_config.format.file_name(POWERS_TABLE)),
+        &table.into_bytes(),
+    )?;
+
+    // effects table
+    let mut table = Table::new(tables_config.format);
+    table.push_row(effect_columns.iter().map(|column| String::from(*column)));
+    for row in &powers {
+        let mut effects = Vec::new();
+        collect_effect_rows(&mut effects, row.power, &row.power.effect_groups, "");
+        for effect in effects {
+            table.push_row(
+                effect_columns
+                    .iter()
+                    .map(|column| effect_cell(column, &effect)),
+            );
+        }
+    }
+    files.write(
+        Path::new(&tables_config.format.file_name(EFFECTS_TABLE)),
+        &table.into_bytes(),
+    )
+}
+
+impl TableFormatConfig {
+    /// Gets the file name for the table called `name`.
+    fn file_name(&self, name: &str) -> String {
+        match self {
+            TableFormatConfig::Csv => format!("{}.csv", name),
+            TableFormatConfig::Tsv => format!("{}.tsv", name),
+        }
+    }
+}
+
+/// Checks the configured columns against the available columns.
+fn select_columns<'a>(
+    configured: &'a [String],
+    available: &[&'static str],
+    table: &str,
+) -> io::Result<Vec<&'a str>> {
+    if configured.is_empty() {
+        return Ok(available.to_vec());
+    }
+    for column in configured {
+        if !available.contains(&&column[..]) {
+            return Err(Error::new(
+                ErrorKind::Other,
+                format!(
+                    "Unknown column {} in the {} table. Available columns are: {}",
+                    column,
+                    table,
+                    available.join(", ")
+                ),
+            ));
+        }
+    }
+    Ok(configured.iter().map(|column| &column[..]).collect())
+}
+
+/// Gets the value of `column` for a power.
+fn power_cell(column: &str, row: &PowerRow) -> String {
+    let power = row.power;
+    match column {
+        "name" => opt_to_string(power.name.as_ref().map(|n| n.get())),
+        "display_name" => opt_to_string(power.display_name.as_ref()),
+        "power_set" => String::from(row.power_set),
+        "power_type" => opt_to_string(power.power_type),
+        "available_at_level" => power.available_at_level.to_string(),
+        "accuracy" => power.accuracy.to_string(),
+        "activation_time" => power.activate.cast_time.to_string(),
+        "effective_activation_time" => power.activate.effective_cast_time.to_string(),
+        "recharge_time" => power.activate.recharge_time.to_string(),
+        "endurance_cost" => power.activate.endurance_cost.to_string(),
+        "range" => power.effect_area.range_feet.to_string(),
+        "radius" => power.effect_area.radius_feet.to_string(),
+        "arc" => power.effect_area.arc_degrees.to_string(),
+        "max_targets" => power.effect_area.max_targets_hit.to_string(),
+        "effect_area" => opt_to_string(power.effect_area.area),
+        _ => String::new(),
+    }
+}
+
+/// Gets the value of `column` for a scaled effect.
+fn effect_cell(column: &str, row: &EffectRow) -> String {
+    let (unit, value) = scaled_unit_and_value(&row.scaled.scaled_effect);
+    match column {
+        "power" => opt_to_string(row.power.name.as_ref().map(|n| n.get())),
+        "effect_group" => row.effect_group_path.clone(),
+        "pve_or_pvp" => opt_to_string(row.effect_group.pve_or_pvp),
+        "chance_percent" => row.effect_group.chance_percent.to_string(),
+        "attributes" => row.effect.attributes.join(", "),
+        "applies_to" => opt_to_string(row.effect.applies_to),
+        "application_type" => opt_to_string(row.effect.application_type),
+        "target_type" => opt_to_string(row.effect.target_type),
+        "archetype" => opt_to_string(row.scaled.archetype.as_ref()),
+        "unit" => String::from(unit),
+        "value" => value.to_string(),
+        "average" => row.scaled.average.to_string(),
+        "per_activation" => row.scaled.per_activation.to_string(),
+        "per_cast_cycle" => row.scaled.per_cast_cycle.to_string(),
+        "base_value" => row.scaled.base_value.to_string(),
+        "scale" => row.scaled.scale.to_string(),
+        "duration_seconds" => opt_to_string(row.effect.duration_seconds),
+        "after_delay_seconds" => row.effect.after_delay_seconds.to_string(),
+        "tick_chance_percent" => opt_to_string(row.effect.tick_chance_percent),
+        "flags" => row.effect.flags.join(", "),
+        _ => String::new(),
+    }
+}
+
+/// Flattens the scaled values of every attribute modifier in `effect_groups` and their children.
+fn collect_effect_rows<'a>(
+    rows: &mut Vec<EffectRow<'a>>,
+    power: &'a PowerOutput,
+    effect_groups: &'a [EffectGroupOutput],
+    parent_path: &str,
+) {
+    for (i, effect_group) in effect_groups.iter().enumerate() {
+        let effect_group_path = format!("{}{}", parent_path, i + 1);
+        for effect in &effect_group.effects {
+            for scaled in &effect.scaled {
+                rows.push(EffectRow {
+                    power,
+                    effect_group_path: effect_group_path.clone(),
+                    effect_group,
+                    effect,
+                    scaled,
+                });
+            }
+        }
+        collect_effect_rows(
+            rows,
+            power,
+            &effect_group.child_effect_groups,
+            &format!("{}.", effect_group_path),
+        );
+    }
+}
+
+/// Totals the average damage of a power's PvE effects for each archetype.
+fn total_damage(power: &PowerOutput) -> BTreeMap<String, f32> {
+    let mut rows = Vec::new();
+    collect_effect_rows(&mut rows, power, &power.effect_groups, "");
+    let mut totals = BTreeMap::new();
+    for row in rows {
+        if row.effect_group.pve_or_pvp == Some("PVP") {
+            continue;
+        }
+        if let (ScaledUnit::Damage(_), Some(archetype)) =
+            (&row.scaled.scaled_effect, &row.scaled.archetype)
+        {
+            *totals.entry(archetype.clone()).or_insert(0.0) += row.scaled.average;
+        }
+    }
+    totals
+}
+
+/// Splits a `ScaledUnit` into a unit name and value.
+fn scaled_unit_and_value(scaled_effect: &ScaledUnit) -> (&'static str, f32) {
+    match *scaled_effect {
+        ScaledUnit::Damage(v) => ("damage", v),
+        ScaledUnit::Healing(v) => ("healing", v),
+        ScaledUnit::Percent(v) => ("percent", v),
+        ScaledUnit::DurationSeconds(v) => ("duration_seconds", v),
+        ScaledUnit::Magnitude(v) => ("magnitude", v),
+        ScaledUnit::Value(v) => ("value", v),
+        ScaledUnit::Distance(v) => ("distance", v),
+    }
+}
+
+/// Converts an optional value to a string, or an empty string if there isn't one.
+fn opt_to_string<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// Rows of text cells, written as .csv or .tsv.
+struct Table {
+    format: TableFormatConfig,
+    text: String,
+}
+
+impl Table {
+    fn new(format: TableFormatConfig) -> Self {
+        Table {
+            format,
+            text: String::new(),
+        }
+    }
+
+    /// Adds a row of cells, quoting or cleaning them up as the format requires.
+    fn push_row<I: IntoIterator<Item = String>>(&mut self, cells: I) {
+        for (i, cell) in cells.into_iter().enumerate() {
+            match self.format {
+                TableFormatConfig::Csv => {
+                    if i > 0 {
+                        self.text.push(',');
+                    }
+                    if cell.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+                        self.text.push('"');
+                        self.text.push_str(&cell.replace('"', "\"\""));
+                        self.text.push('"');
+                    } else {
+                        self.text.push_str(&cell);
+                    }
+                }
+                TableFormatConfig::Tsv => {
+                    if i > 0 {
+                        self.text.push('\t');
+                    }
+                    // TSV has no quoting, so anything that would break a row becomes a space
+                    self.text
+                        .push_str(&cell.replace(|c| c == '\t' || c == '\n' || c == '\r', " "));
+                }
+            }
+        }
+        self.text.push_str("\r\n");
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        self.text.into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::tests::{make_powers_dict, TestOutput};
+
+    #[test]
+    fn test_table_quoting() {
+        let row = || {
+            vec![
+                String::from("Fire_Blast.Flares"),
+                String::from("Flares, \"quick\""),
+                String::from("a\tb"),
+            ]
+        };
+        let mut csv = Table::new(TableFormatConfig::Csv);
+        csv.push_row(row());
+        assert_eq!(
+            csv.text,
+            "Fire_Blast.Flares,\"Flares, \"\"quick\"\"\",a\tb\r\n"
+        );
+        let mut tsv = Table::new(TableFormatConfig::Tsv);
+        tsv.push_row(row());
+        assert_eq!(tsv.text, "Fire_Blast.Flares\tFlares, \"quick\"\ta b\r\n");
+    }
+
+    #[test]
+    fn test_select_columns() {
+        let all = select_columns(&[], POWER_COLUMNS, POWERS_TABLE).unwrap();
+        assert_eq!(all.len(), POWER_COLUMNS.len());
+        let some = vec![String::from("recharge_time"), String::from("name")];
+        assert_eq!(
+            select_columns(&some, POWER_COLUMNS, POWERS_TABLE).unwrap(),
+            vec!["recharge_time", "name"]
+        );
+        let unknown = vec![String::from("recharge")];
+        assert!(select_columns(&unknown, POWER_COLUMNS, POWERS_TABLE).is_err());
+    }
+
+    #[test]
+    fn test_tables() {
+        let mut output = TestOutput::new("tables");
+        output.config.tables = Some(TablesConfig {
+            power_columns: vec![String::from("name"), String::from("power_set")],
+            ..Default::default()
+        });
+        output.write(make_powers_dict()).unwrap();
+        assert_eq!(
+            String::from_utf8(output.read("powers.csv")).unwrap(),
+            "name,power_set\r\nRanged.Fire_Blast.Flares,Ranged.Fire_Blast\r\n"
+        );
+        assert!(output
+            .read("effects.csv")
+            .starts_with(b"power,effect_group,"));
+
+        // unknown columns are rejected
+        output.config.tables = Some(TablesConfig {
+            format: TableFormatConfig::Tsv,
+            effect_columns: vec![String::from("magnitude")],
+            ..Default::default()
+        });
+        assert!(output.write(make_powers_dict()).is_err());
+        assert!(!output.path.join("powers.tsv").exists());
+    }
+}
@@ -0,0 +1,246 @@
+use crate::output::structs::{PowerOutput, PowerSetOutput};
+use crate::structs::config::PowersConfig;
+use crate::structs::PowersDictionary;
+use serde_json::{Map, Value};
+use std::fmt::Write;
+use std::io::{self, ErrorKind};
+
+/// Separates the parts of a field selector.
+const FIELD_SEPARATOR: char = '.';
+
+/// Fields shown when the query doesn't select any.
+const DEFAULT_FIELDS: &[&'static str] = &["display_name"];
+
+/// How to print the query results.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QueryFormat {
+    /// One row per power, with aligned columns.
+    Table,
+    /// An array with one object per power.
+    Json,
+}
+
+impl QueryFormat {
+    /// Gets the format from its command line name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "table" => Some(QueryFormat::Table),
+            "json" => Some(QueryFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Finds powers by full name and prints selected fields from their output.
+pub struct Query {
+    /// A full name pattern, as accepted by `NameKey::pattern_match`.
+    pub pattern: String,
+    /// Dotted paths into the power's .json, e.g. `activate.recharge_time`.
+    pub fields: Vec<String>,
+    pub format: QueryFormat,
+}
+
+impl Query {
+    /// Runs the query against the powers included in the output.
+    ///
+    /// # Arguments:
+    ///
+    /// * `powers_dict` - A `PowersDictionary` containing a hierarchy of categories, power sets, and powers.
+    /// * `config` - Configuration information.
+    ///
+    /// # Returns:
+    ///
+    /// The results formatted as text. Otherwise, an `io::Error` containing the error information.
+    ///
+    /// # Notes:
+    ///
+    /// A field selector that passes through an array selects the field from each element.
+    /// Powers that don't have a field are shown with a `null` (or empty) value.
+    pub fn run(&self, powers_dict: &PowersDictionary, config: &PowersConfig) -> io::Result<String> {
+        let fields: Vec<&str> = if self.fields.is_empty() {
+            DEFAULT_FIELDS.to_vec()
+        } else {
+            self.fields.iter().map(|f| f.as_str()).collect()
+        };
+        let mut rows = Vec::new();
+        for power in self.find_powers(powers_dict, config) {
+            let name = power.name.as_ref().map(|n| n.get().to_owned());
+            let json =
+                serde_json::to_value(&power).map_err(|e| io::Error::new(ErrorKind::Other, e))?;
+            let values: Vec<Value> = fields.iter().map(|f| select_field(&json, f)).collect();
+            rows.push((name.unwrap_or_default(), values));
+        }
+        match self.format {
+            QueryFormat::Table => Ok(format_table(&fields, &rows)),
+            QueryFormat::Json => format_json(&fields, rows),
+        }
+    }
+
+    /// Gets the output for every included power whose full name matches the pattern.
+    fn find_powers(
+        &self,
+        powers_dict: &PowersDictionary,
+        config: &PowersConfig,
+    ) -> Vec<PowerOutput> {
+        let mut powers = Vec::new();
+        for category in &powers_dict.power_categories {
+            if !category.include_in_output {
+                continue;
+            }
+            for set in &category.pp_power_sets {
+                let matches_set = set.pp_powers.iter().any(|p| {
+                    matches!(&p.pch_full_name, Some(n) if p.include_in_output && n.pattern_match(&self.pattern))
+                });
+                if !set.include_in_output || !matches_set {
+                    continue;
+                }
+                let pset =
+                    PowerSetOutput::from_base_power_set(set, &powers_dict.attrib_names, config);
+                powers.extend(
+                    pset.into_powers()
+                        .into_iter()
+                        .filter(|p| matches!(&p.name, Some(n) if n.pattern_match(&self.pattern))),
+                );
+            }
+        }
+        powers
+    }
+}
+
+/// Selects the value at the dotted `path` in `value`. Arrays along the way select from each
+/// element.
+fn select_field(value: &Value, path: &str) -> Value {
+    let parts: Vec<&str> = path.split(FIELD_SEPARATOR).collect();
+    select_parts(value, &parts)
+}
+
+fn select_parts(value: &Value, parts: &[&str]) -> Value {
+    if parts.is_empty() {
+        return value.clone();
+    }
+    match value {
+        Value::Array(elements) => Value::Array(
+            elements
+                .iter()
+                .map(|e| select_parts(e, parts))
+                .filter(|e| !e.is_null())
+                .collect(),
+        ),
+        Value::Object(map) => match map.get(parts[0]) {
+            Some(field) => select_parts(field, &parts[1..]),
+            None => Value::Null,
+        },
+        _ => Value::Null,
+    }
+}
+
+/// Formats the results as a table with a header row and columns aligned with spaces.
+fn format_table(fields: &[&str], rows: &[(String, Vec<Value>)]) -> String {
+    let mut cells = Vec::with_capacity(rows.len() + 1);
+    let mut header = vec![String::from("name")];
+    header.extend(fields.iter().map(|f| String::from(*f)));
+    cells.push(header);
+    for (name, values) in rows {
+        let mut row = vec![name.clone()];
+        row.extend(values.iter().map(format_cell));
+        cells.push(row);
+    }
+    let mut widths = vec![0; fields.len() + 1];
+    for row in &cells {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut table = String::new();
+    for row in &cells {
+        let mut line = String::new();
+        for (i, (cell, width)) in row.iter().zip(&widths).enumerate() {
+            if i > 0 {
+                line.push_str("  ");
+            }
+            let _ = write!(line, "{:width$}", cell, width = width);
+        }
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+/// Formats a single table cell. Strings are shown without quotes, other values as compact JSON.
+fn format_cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        _ => value.to_string(),
+    }
+}
+
+/// Formats the results as a JSON array of objects, each with the power's name and the
+/// selected fields.
+fn format_json(fields: &[&str], rows: Vec<(String, Vec<Value>)>) -> io::Result<String> {
+    let results: Vec<Value> = rows
+        .into_iter()
+        .map(|(name, values)| {
+            let mut object = Map::new();
+            object.insert(String::from("name"), Value::String(name));
+            for (field, value) in fields.iter().zip(values) {
+                object.insert(String::from(*field), value);
+            }
+            Value::Object(object)
+        })
+        .collect();
+    let mut json =
+        serde_json::to_string_pretty(&results).map_err(|e| io::Error::new(ErrorKind::Other, e))?;
+    json.push('\n');
+    Ok(json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::tests::{make_config, make_powers_dict};
+    use serde_json::json;
+    use std::path::Path;
+
+    #[test]
+    fn test_select_field() {
+        let value = json!({
+            "activate": { "recharge_time": 4.0 },
+            "effect_groups": [
+                { "effects": [{ "scaled": [1] }, { "scaled": [2] }] },
+                { "effects": [{ "other": true }] }
+            ]
+        });
+        assert_eq!(select_field(&value, "activate.recharge_time"), json!(4.0));
+        assert_eq!(
+            select_field(&value, "effect_groups.effects.scaled"),
+            json!([[[1], [2]], []])
+        );
+        assert_eq!(select_field(&value, "activate.nothing"), Value::Null);
+    }
+
+    #[test]
+    fn test_run_query() {
+        let config = make_config(Path::new("unused"));
+        let powers_dict = make_powers_dict();
+        let mut query = Query {
+            pattern: String::from("Ranged.Fire_Blast.*"),
+            fields: vec![String::from("activate.recharge_time")],
+            format: QueryFormat::Json,
+        };
+        let results: Value =
+            serde_json::from_str(&query.run(&powers_dict, &config).unwrap()).unwrap();
+        assert_eq!(results[0]["name"], "Ranged.Fire_Blast.Flares");
+        assert!(results[0]["activate.recharge_time"].is_number());
+
+        query.format = QueryFormat::Table;
+        let table = query.run(&powers_dict, &config).unwrap();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("name "));
+        assert!(lines[1].starts_with("Ranged.Fire_Blast.Flares  "));
+
+        query.pattern = String::from("Melee.*.*");
+        assert_eq!(query.run(&powers_dict, &config).unwrap().lines().count(), 1);
+    }
+}
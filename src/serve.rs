@@ -0,0 +1,484 @@
+use crate::output;
+use crate::output::structs::{EffectGroupOutput, PowerOutput, PowerSetOutput};
+use crate::output::JSON_FILE;
+use crate::structs::config::{OutputStyleConfig, PowersConfig};
+use crate::structs::{NameKey, PowersDictionary};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+/// How long to wait for a client to send its request before giving up on it.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Paths of the query endpoints.
+const SEARCH_PATH: &'static str = "/api/search";
+const POWERS_PATH: &'static str = "/api/powers";
+
+/// Query string parameters.
+const NAME_PARAM: &'static str = "name";
+const ARCHETYPE_PARAM: &'static str = "archetype";
+const ATTRIBUTE_PARAM: &'static str = "attribute";
+
+/// A power that can be found with the query endpoints.
+struct PowerEntry {
+    name: NameKey,
+    display_name: Option<String>,
+    /// URL of the power set the power is in.
+    url: String,
+    /// Internal and display names of the archetypes that can use the power, in lowercase.
+    archetypes: BTreeSet<String>,
+    /// Attributes modified by the power's effects, in lowercase.
+    attributes: BTreeSet<String>,
+    /// The power's .json.
+    json: Vec<u8>,
+}
+
+/// What the search and filter endpoints return for each power.
+#[derive(Serialize)]
+struct PowerSummary<'a> {
+    name: &'a str,
+    display_name: Option<&'a str>,
+    url: &'a str,
+}
+
+/// Reported to clients when something goes wrong.
+#[derive(Serialize)]
+struct ErrorOutput<'a> {
+    error: &'a str,
+}
+
+/// An HTTP response.
+struct Response {
+    status: u16,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn ok(body: Vec<u8>) -> Self {
+        Response { status: 200, body }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Response {
+            status,
+            body: serde_json::to_vec(&ErrorOutput { error: message }).unwrap_or_default(),
+        }
+    }
+
+    fn status_text(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+/// Serves the powers dictionary over HTTP, with the same URL layout as the .json files written
+/// by `write_powers_dictionary`.
+pub struct Server {
+    /// The .json files, keyed by path relative to the output path.
+    files: BTreeMap<String, Vec<u8>>,
+    powers: Vec<PowerEntry>,
+    power_index: HashMap<NameKey, usize>,
+    output_style: OutputStyleConfig,
+    /// How long `handle` waits for a request.
+    read_timeout: Duration,
+}
+
+impl Server {
+    /// Renders all of the .json files and builds the indexes used by the query endpoints.
+    ///
+    /// # Arguments:
+    ///
+    /// * `powers_dict` - A `PowersDictionary` containing a hierarchy of categories, power sets, and powers.
+    /// * `config` - Configuration information. `base_json_url` should point to the server.
+    ///
+    /// # Returns:
+    ///
+    /// A `Server` if successful. Otherwise, an `io::Error` containing the error information.
+    pub fn new(powers_dict: &PowersDictionary, config: &PowersConfig) -> io::Result<Self> {
+        let mut server = Server {
+            files: output::render_json_tree(powers_dict, config)?,
+            powers: Vec::new(),
+            power_index: HashMap::new(),
+            output_style: config.output_style,
+            read_timeout: READ_TIMEOUT,
+        };
+        let base_url = config.base_json_url.as_deref().unwrap_or("/");
+        for category in &powers_dict.power_categories {
+            let category_name = match (category.include_in_output, &category.pch_name) {
+                (true, Some(name)) => name,
+                _ => continue,
+            };
+            for set in &category.pp_power_sets {
+                let set_name = match (set.include_in_output, &set.pch_name) {
+                    (true, Some(name)) => name,
+                    _ => continue,
+                };
+                let url = format!(
+                    "{}{}/{}/",
+                    base_url,
+                    output::make_file_name(category_name.get()),
+                    output::make_file_name(set_name)
+                );
+                let pset =
+                    PowerSetOutput::from_base_power_set(set, &powers_dict.attrib_names, config);
+                // the power set output only has the included powers, in the same order
+                let base_powers = set.pp_powers.iter().filter(|p| p.include_in_output);
+                for (base_power, power) in base_powers.zip(pset.into_powers()) {
+                    let mut archetypes = BTreeSet::new();
+                    for at in &base_power.archetypes {
+                        archetypes.extend(at.pch_name.iter().map(|n| n.to_ascii_lowercase()));
+                        archetypes
+                            .extend(at.pch_display_name.iter().map(|n| n.to_ascii_lowercase()));
+                    }
+                    server.add_power(power, url.clone(), archetypes)?;
+                }
+            }
+        }
+        Ok(server)
+    }
+
+    /// Adds a power to the query indexes.
+    fn add_power(
+        &mut self,
+        power: PowerOutput,
+        url: String,
+        archetypes: BTreeSet<String>,
+    ) -> io::Result<()> {
+        let name = match &power.name {
+            Some(name) => name.clone(),
+            None => return Ok(()),
+        };
+        let mut attributes = BTreeSet::new();
+        collect_attributes(&power.effect_groups, &mut attributes);
+        let entry = PowerEntry {
+            name: name.clone(),
+            display_name: power.display_name.clone(),
+            url,
+            archetypes,
+            attributes,
+            json: self.to_json(&power)?,
+        };
+        self.power_index.insert(name, self.powers.len());
+        self.powers.push(entry);
+        Ok(())
+    }
+
+    /// Listens for requests on `address` until the program is stopped.
+    ///
+    /// # Arguments:
+    ///
+    /// * `address` - The address and port to listen on, e.g. `127.0.0.1:8080`.
+    ///
+    /// # Returns:
+    ///
+    /// Nothing if the server stops normally. Otherwise, an `io::Error` containing the error information.
+    ///
+    /// # Notes:
+    ///
+    /// Requests are handled one at a time. A client that doesn't send its request within a few seconds
+    /// is dropped, so it can't hold up everyone else. Errors talking to a client are printed and the
+    /// server carries on.
+    pub fn run(&self, address: &str) -> io::Result<()> {
+        let listener = TcpListener::bind(address)?;
+        println!(
+            "Serving {} files and {} powers on http://{}/ ...",
+            self.files.len(),
+            self.powers.len(),
+            address
+        );
+        for stream in listener.incoming() {
+            if let Err(e) = stream.and_then(|stream| self.handle(stream)) {
+                println!("Request failed: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a request from `stream` and writes the response.
+    fn handle(&self, mut stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(self.read_timeout))?;
+        let mut reader = BufReader::new(&stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        // the headers aren't needed, but have to be read before responding
+        let mut header = String::new();
+        while reader.read_line(&mut header)? > 2 {
+            header.clear();
+        }
+
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or("");
+        let target = parts.next().unwrap_or("");
+        let response = self.respond(method, target);
+        println!("{} {} {}", method, target, response.status);
+
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\n\
+             Content-Type: application/json; charset=utf-8\r\n\
+             Content-Length: {}\r\n\
+             Access-Control-Allow-Origin: *\r\n\
+             Connection: close\r\n\r\n",
+            response.status,
+            response.status_text(),
+            response.body.len()
+        )?;
+        if method != "HEAD" {
+            stream.write_all(&response.body)?;
+        }
+        stream.flush()
+    }
+
+    /// Works out the response to a request.
+    ///
+    /// # Arguments:
+    ///
+    /// * `method` - The HTTP method. Only `GET` and `HEAD` are supported.
+    /// * `target` - The path and query string that were requested.
+    fn respond(&self, method: &str, target: &str) -> Response {
+        if method != "GET" && method != "HEAD" {
+            return Response::error(405, "Only GET and HEAD are supported");
+        }
+        let (path, query) = match target.find('?') {
+            Some(i) => (&target[..i], parse_query(&target[i + 1..])),
+            None => (target, HashMap::new()),
+        };
+        let path = percent_decode(path);
+
+        let result = if path == SEARCH_PATH {
+            self.search(&query)
+        } else if path == POWERS_PATH || path == format!("{}/", POWERS_PATH) {
+            self.filter(&query)
+        } else if let Some(name) = path.strip_prefix(&format!("{}/", POWERS_PATH)) {
+            match self.power_index.get(&NameKey::new(name)) {
+                Some(i) => Ok(Response::ok(self.powers[*i].json.clone())),
+                None => Ok(Response::error(404, &format!("No power named {}", name))),
+            }
+        } else {
+            Ok(self.get_file(&path))
+        };
+        result.unwrap_or_else(|e| Response::error(500, &e.to_string()))
+    }
+
+    /// Gets one of the .json files. Directory URLs get the `index.json` inside them.
+    fn get_file(&self, path: &str) -> Response {
+        let mut key = String::from(path.trim_start_matches('/'));
+        if key.is_empty() || key.ends_with('/') {
+            key.push_str(JSON_FILE);
+        }
+        match self.files.get(&key) {
+            Some(contents) => Response::ok(contents.clone()),
+            None => Response::error(404, &format!("{} not found", path)),
+        }
+    }
+
+    /// Finds powers whose full name or display name contains the `name` parameter.
+    fn search(&self, query: &HashMap<String, String>) -> io::Result<Response> {
+        let name = match query.get(NAME_PARAM) {
+            Some(name) if !name.is_empty() => name.to_ascii_lowercase(),
+            _ => {
+                return Ok(Response::error(
+                    400,
+                    &format!("The {} parameter is required", NAME_PARAM),
+                ))
+            }
+        };
+        self.summarize(self.powers.iter().filter(|p| {
+            p.name.partial_match(&name)
+                || p.display_name
+                    .as_ref()
+                    .map_or(false, |d| d.to_ascii_lowercase().contains(&name))
+        }))
+    }
+
+    /// Finds powers usable by the `archetype` parameter and/or modifying the `attribute` parameter.
+    fn filter(&self, query: &HashMap<String, String>) -> io::Result<Response> {
+        let archetype = query.get(ARCHETYPE_PARAM).map(|a| a.to_ascii_lowercase());
+        let attribute = query.get(ATTRIBUTE_PARAM).map(|a| a.to_ascii_lowercase());
+        if archetype.is_none() && attribute.is_none() {
+            return Ok(Response::error(
+                400,
+                &format!(
+                    "At least one of the {} or {} parameters is required",
+                    ARCHETYPE_PARAM, ATTRIBUTE_PARAM
+                ),
+            ));
+        }
+        self.summarize(self.powers.iter().filter(|p| {
+            archetype
+                .as_ref()
+                .map_or(true, |a| p.archetypes.contains(a))
+                && attribute
+                    .as_ref()
+                    .map_or(true, |a| p.attributes.contains(a))
+        }))
+    }
+
+    /// Lists the matching powers, sorted by name.
+    fn summarize<'a, I>(&self, powers: I) -> io::Result<Response>
+    where
+        I: Iterator<Item = &'a PowerEntry>,
+    {
+        let mut summaries: Vec<_> = powers
+            .map(|p| PowerSummary {
+                name: p.name.get(),
+                display_name: p.display_name.as_deref(),
+                url: &p.url,
+            })
+            .collect();
+        summaries.sort_by_key(|s| s.name.to_ascii_lowercase());
+        Ok(Response::ok(self.to_json(&summaries)?))
+    }
+
+    /// Serializes `value` to .json using the configured output style.
+    fn to_json<T: Serialize>(&self, value: &T) -> io::Result<Vec<u8>> {
+        Ok(match self.output_style {
+            OutputStyleConfig::Pretty => serde_json::to_vec_pretty(value)?,
+            OutputStyleConfig::Compact => serde_json::to_vec(value)?,
+        })
+    }
+}
+
+/// Adds the attributes modified by `effect_groups` and their children to `attributes`, in lowercase.
+fn collect_attributes(effect_groups: &[EffectGroupOutput], attributes: &mut BTreeSet<String>) {
+    for effect_group in effect_groups {
+        for effect in &effect_group.effects {
+            attributes.extend(effect.attributes.iter().map(|a| a.to_ascii_lowercase()));
+        }
+        collect_attributes(&effect_group.child_effect_groups, attributes);
+    }
+}
+
+/// Splits a query string into its parameters.
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.find('=') {
+            Some(i) => (
+                percent_decode(&pair[..i].replace('+', " ")),
+                percent_decode(&pair[i + 1..].replace('+', " ")),
+            ),
+            None => (percent_decode(pair), String::new()),
+        })
+        .collect()
+}
+
+/// Decodes `%XX` escapes in part of a URL.
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(b) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                decoded.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::tests::{make_config, make_powers_dict};
+    use std::path::Path;
+
+    #[test]
+    fn test_respond() {
+        let mut config = make_config(Path::new("unused"));
+        config.base_json_url = Some(String::from("/"));
+        let server = Server::new(&make_powers_dict(), &config).unwrap();
+
+        let root = server.respond("GET", "/");
+        assert_eq!(root.status, 200);
+        assert_eq!(root.body, server.respond("GET", "/index.json").body);
+        assert_eq!(server.respond("GET", "/ranged/fire-blast/").status, 200);
+        assert_eq!(server.respond("GET", "/nowhere/").status, 404);
+        assert_eq!(server.respond("POST", "/").status, 405);
+
+        let power = server.respond("GET", "/api/powers/ranged.fire_blast.FLARES");
+        assert_eq!(power.status, 200);
+        let power: serde_json::Value = serde_json::from_slice(&power.body).unwrap();
+        assert_eq!(power["name"], "Ranged.Fire_Blast.Flares");
+
+        let search: serde_json::Value =
+            serde_json::from_slice(&server.respond("GET", "/api/search?name=fire%5Fblast").body)
+                .unwrap();
+        assert_eq!(search[0]["url"], "/ranged/fire-blast/");
+        assert_eq!(server.respond("GET", "/api/search").status, 400);
+
+        let filtered = |target: &str| -> usize {
+            let body: serde_json::Value =
+                serde_json::from_slice(&server.respond("GET", target).body).unwrap();
+            body.as_array().map_or(0, |a| a.len())
+        };
+        assert_eq!(filtered("/api/powers?archetype=blaster"), 1);
+        assert_eq!(filtered("/api/powers?archetype=Class_Tanker"), 1);
+        assert_eq!(filtered("/api/powers?archetype=Class_Scrapper"), 0);
+        assert_eq!(
+            filtered("/api/powers?archetype=Blaster&attribute=Fire_Dmg"),
+            0
+        );
+    }
+
+    #[test]
+    fn test_handle() {
+        let server = Server::new(&make_powers_dict(), &make_config(Path::new("unused"))).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let client = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream
+                .write_all(b"GET /archetypes/ HTTP/1.1\r\nHost: localhost\r\n\r\n")
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        });
+        let (stream, _) = listener.accept().unwrap();
+        server.handle(stream).unwrap();
+        let response = client.join().unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("\"Class_Blaster\""));
+    }
+
+    #[test]
+    fn test_handle_times_out() {
+        let mut server =
+            Server::new(&make_powers_dict(), &make_config(Path::new("unused"))).unwrap();
+        server.read_timeout = Duration::from_millis(50);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        // connect but never send anything
+        let _stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let error = server.handle(stream).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+        ));
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let query = parse_query("name=fire+blast&archetype=Class%5FBlaster&flag");
+        assert_eq!(query["name"], "fire blast");
+        assert_eq!(query["archetype"], "Class_Blaster");
+        assert_eq!(query["flag"], "");
+    }
+}
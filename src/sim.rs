@@ -0,0 +1,461 @@
+use crate::output::structs::{AttribModOutput, EffectGroupOutput, PowerOutput, ScaledUnit};
+use crate::structs::config::PowersConfig;
+use crate::structs::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+/// Highest recharge strength a character can have (+400% recharge).
+const RECHARGE_STRENGTH_MAX: f32 = 5.0;
+
+/// Effect group tags that don't change the average damage of a rotation. Anything else
+/// (Domination, Scourge, alternate ammo, etc.) depends on conditions the simulator doesn't model.
+const UNCONDITIONAL_TAGS: &[&str] = &["Critical", "DualPistolsLethalMode"];
+
+/// A power used in an attack chain.
+#[derive(Debug, Deserialize)]
+pub struct ChainPower {
+    /// The full name of the power (category, power set, and power).
+    pub name: NameKey,
+    /// Recharge enhancement slotted in the power (e.g. 0.95 for +95%).
+    #[serde(default)]
+    pub recharge_enhancement: f32,
+}
+
+/// Describes an attack chain to simulate.
+#[derive(Debug, Deserialize)]
+pub struct AttackChain {
+    /// Internal name (e.g. `Class_Blaster`) or display name of the archetype using the powers.
+    pub archetype: NameKey,
+    /// How long to run the simulation, in seconds.
+    pub duration: f32,
+    /// Global recharge bonus (e.g. 0.7 for +70%).
+    #[serde(default)]
+    pub global_recharge: f32,
+    /// The powers in the chain, in order of priority. Whenever the character is free to
+    /// act, the first power that is recharged will be used.
+    pub powers: Vec<ChainPower>,
+}
+
+impl AttackChain {
+    /// Parses a .toml file to create an `AttackChain`.
+    ///
+    /// # Arguments:
+    ///
+    /// * `path` - The path to the .toml file to parse.
+    ///
+    /// # Returns:
+    ///
+    /// If successful, an `AttackChain`. Otherwise, a `std::io::Error`.
+    pub fn load(path: &Path) -> Result<AttackChain> {
+        let mut file = File::open(path)?;
+        let mut buf = String::new();
+        let _ = file.read_to_string(&mut buf)?;
+        let chain: AttackChain =
+            toml::from_str(&buf).map_err(|e| Error::new(ErrorKind::Other, e))?;
+        if !chain.duration.is_normal() || chain.duration < 0.0 {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "duration must be greater than 0",
+            ));
+        }
+        Ok(chain)
+    }
+}
+
+/// Totals for a single power in the chain.
+#[derive(Debug)]
+pub struct PowerSimulationResult {
+    pub name: NameKey,
+    pub display_name: Option<String>,
+    /// How many times the power was activated.
+    pub activations: i32,
+    /// Damage dealt by the power within the simulation time.
+    pub damage: f32,
+}
+
+/// The results of running an attack chain simulation.
+#[derive(Debug)]
+pub struct SimulationResult {
+    /// Length of the simulation in seconds.
+    pub duration: f32,
+    /// Time spent activating powers, in seconds.
+    pub busy_time: f32,
+    /// Total damage dealt.
+    pub total_damage: f32,
+    /// Average damage per second over the simulation.
+    pub damage_per_second: f32,
+    /// Total damage dealt by each damage type, sorted by type.
+    pub damage_by_type: Vec<(String, f32)>,
+    /// Results for each power, in chain order.
+    pub powers: Vec<PowerSimulationResult>,
+}
+
+impl fmt::Display for SimulationResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Simulated {:.2} seconds.", self.duration)?;
+        writeln!(
+            f,
+            "Busy {:.2} seconds ({:.1}%).",
+            self.busy_time,
+            self.busy_time / self.duration * 100.0
+        )?;
+        writeln!(f, "Total damage: {:.2}", self.total_damage)?;
+        writeln!(f, "Damage per second: {:.2}", self.damage_per_second)?;
+        writeln!(f, "By type:")?;
+        for (damage_type, damage) in &self.damage_by_type {
+            writeln!(
+                f,
+                "\t{}: {:.2} ({:.2}/s)",
+                damage_type,
+                damage,
+                damage / self.duration
+            )?;
+        }
+        writeln!(f, "By power:")?;
+        for power in &self.powers {
+            writeln!(
+                f,
+                "\t{}: {} activations, {:.2} damage",
+                power
+                    .display_name
+                    .as_ref()
+                    .unwrap_or(power.name.get_string()),
+                power.activations,
+                power.damage
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Damage dealt at some point after a power is activated.
+struct DamageEvent {
+    offset: f32,
+    amount: f32,
+    damage_type: String,
+}
+
+/// A power from the chain, ready to simulate.
+struct SimulatedPower {
+    activation_time: f32,
+    recharge_time: f32,
+    events: Vec<DamageEvent>,
+    ready_at: f32,
+}
+
+/// Simulates using an attack chain over a period of time.
+///
+/// # Arguments:
+///
+/// * `chain` - The attack chain to simulate.
+/// * `powers_dict` - The powers dictionary containing the powers used by `chain`.
+/// * `config` - Configuration information. `at_level` and `activation_time` are used to
+/// calculate damage and activation times.
+///
+/// # Returns:
+///
+/// A `SimulationResult` if successful. Otherwise, a `std::io::Error` describing which
+/// part of the chain couldn't be resolved.
+///
+/// # Notes:
+///
+/// Powers are activated back to back using their effective activation time. Recharge starts
+/// once the activation is complete. Damage is based on the average damage of each effect
+/// (including chance to hit and cancel-on-miss) and damage over time effects are spread out
+/// over their ticks, so damage landing after the simulation ends doesn't count.
+pub fn simulate_attack_chain(
+    chain: &AttackChain,
+    powers_dict: &PowersDictionary,
+    config: &PowersConfig,
+) -> Result<SimulationResult> {
+    let archetype = powers_dict
+        .find_archetype(&chain.archetype)
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::Other,
+                format!("Archetype {} not found", chain.archetype),
+            )
+        })?;
+    let mut sim_powers = Vec::new();
+    let mut results = Vec::new();
+    for chain_power in &chain.powers {
+        let power = powers_dict.find_power(&chain_power.name).ok_or_else(|| {
+            Error::new(
+                ErrorKind::Other,
+                format!("Power {} not found", chain_power.name),
+            )
+        })?;
+        if !matches!(power.e_type, PowerType::kPowerType_Click) {
+            return Err(Error::new(
+                ErrorKind::Other,
+                format!("Power {} is not a click power", chain_power.name),
+            ));
+        }
+        let recharge_strength = (1.0 + chain_power.recharge_enhancement + chain.global_recharge)
+            .max(1.0)
+            .min(RECHARGE_STRENGTH_MAX);
+        let power_output = PowerOutput::from_base_power(power, &powers_dict.attrib_names, config);
+        let mut events = Vec::new();
+        for effect_group in &power_output.effect_groups {
+            add_damage_events(
+                &mut events,
+                effect_group,
+                archetype.pch_display_name.as_ref(),
+                0.0,
+            );
+        }
+        sim_powers.push(SimulatedPower {
+            // powers without a cast time still take a server tick to use
+            activation_time: power
+                .effective_time_to_activate(&config.activation_time)
+                .max(config.activation_time.server_tick_seconds),
+            recharge_time: power.f_recharge_time / recharge_strength,
+            events,
+            ready_at: 0.0,
+        });
+        results.push(PowerSimulationResult {
+            name: chain_power.name.clone(),
+            display_name: power.pch_display_name.clone(),
+            activations: 0,
+            damage: 0.0,
+        });
+    }
+    if !sim_powers.iter().any(|p| p.activation_time.is_normal()) {
+        return Err(Error::new(
+            ErrorKind::Other,
+            "None of the powers in the chain take time to activate",
+        ));
+    }
+
+    // run the chain
+    let mut time = 0.0;
+    let mut busy_time = 0.0;
+    let mut damage_by_type: HashMap<String, f32> = HashMap::new();
+    while time < chain.duration {
+        let next = sim_powers
+            .iter()
+            .position(|p| p.ready_at <= time && p.activation_time.is_normal());
+        match next {
+            Some(i) => {
+                let power = &mut sim_powers[i];
+                let result = &mut results[i];
+                result.activations += 1;
+                for event in &power.events {
+                    if time + event.offset < chain.duration {
+                        result.damage += event.amount;
+                        *damage_by_type
+                            .entry(event.damage_type.clone())
+                            .or_insert(0.0) += event.amount;
+                    }
+                }
+                time += power.activation_time;
+                busy_time += power.activation_time;
+                power.ready_at = time + power.recharge_time;
+            }
+            None => {
+                // nothing is ready, so wait for whatever recharges first
+                time = sim_powers
+                    .iter()
+                    .filter(|p| p.activation_time.is_normal())
+                    .map(|p| p.ready_at)
+                    .fold(f32::INFINITY, f32::min);
+            }
+        }
+    }
+
+    let total_damage = results.iter().map(|r| r.damage).sum();
+    let mut damage_by_type: Vec<_> = damage_by_type.into_iter().collect();
+    damage_by_type.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(SimulationResult {
+        duration: chain.duration,
+        busy_time: busy_time.min(chain.duration),
+        total_damage,
+        damage_per_second: total_damage / chain.duration,
+        damage_by_type,
+        powers: results,
+    })
+}
+
+/// Collects the damage dealt by `effect_group` (and its children) to `archetype`.
+fn add_damage_events(
+    events: &mut Vec<DamageEvent>,
+    effect_group: &EffectGroupOutput,
+    archetype: Option<&String>,
+    delay: f32,
+) {
+    if matches!(effect_group.pve_or_pvp, Some("PVP"))
+        || effect_group
+            .tags
+            .iter()
+            .any(|tag| !UNCONDITIONAL_TAGS.contains(tag))
+    {
+        return;
+    }
+    let delay = delay + effect_group.after_delay_seconds;
+    for attrib_mod in &effect_group.effects {
+        // damage to yourself doesn't count
+        if matches!(attrib_mod.target_type, Some("Self") | Some("SelfAndPets")) {
+            continue;
+        }
+        add_attrib_mod_damage_events(events, attrib_mod, archetype, delay);
+    }
+    for child in &effect_group.child_effect_groups {
+        add_damage_events(events, child, archetype, delay);
+    }
+}
+
+/// Collects the damage dealt by `attrib_mod` to `archetype`, one event per tick.
+fn add_attrib_mod_damage_events(
+    events: &mut Vec<DamageEvent>,
+    attrib_mod: &AttribModOutput,
+    archetype: Option<&String>,
+    delay: f32,
+) {
+    let damage_type = attrib_mod
+        .attributes
+        .get(0)
+        .map(|a| a.to_string())
+        .unwrap_or_else(|| String::from("Unknown"));
+    let ticks = attrib_mod.ticks.unwrap_or(1).max(1);
+    let tick_chance = attrib_mod.tick_chance_percent.unwrap_or(100.0) / 100.0;
+    let cancel_on_miss = tick_chance < 1.0 && attrib_mod.flags.contains(&"CancelOnMiss");
+    for scaled in &attrib_mod.scaled {
+        if scaled.archetype.as_ref() != archetype || !scaled.average.is_normal() {
+            continue;
+        }
+        if let ScaledUnit::Damage(_) = scaled.scaled_effect {
+            // cancel on miss averages already cover every tick, otherwise the average is per tick
+            let per_tick = if ticks == 1 {
+                scaled.average
+            } else if cancel_on_miss {
+                scaled.average / ticks as f32
+            } else {
+                scaled.average * tick_chance
+            };
+            for tick in 0..ticks {
+                events.push(DamageEvent {
+                    offset: delay
+                        + attrib_mod.after_delay_seconds
+                        + attrib_mod.continuous_apply_seconds * tick as f32,
+                    amount: per_tick,
+                    damage_type: damage_type.clone(),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::structs::AttribModScaled;
+    use crate::output::tests::{make_config, make_powers_dict};
+    use std::borrow::Cow;
+    use std::rc::Rc;
+
+    /// Makes the test dictionary's only power a click power with the given timing.
+    fn make_timed_powers_dict(activation_time: f32, recharge_time: f32) -> PowersDictionary {
+        let mut powers_dict = make_powers_dict();
+        let pcat = Rc::get_mut(&mut powers_dict.power_categories[0]).unwrap();
+        let pset = Rc::get_mut(&mut pcat.pp_power_sets[0]).unwrap();
+        let power = Rc::get_mut(&mut pset.pp_powers[0]).unwrap();
+        power.e_type = PowerType::kPowerType_Click;
+        power.f_time_to_activate = activation_time;
+        power.f_recharge_time = recharge_time;
+        powers_dict
+    }
+
+    /// Uses the raw activation times so the schedule is easy to work out by hand.
+    fn make_raw_config() -> PowersConfig {
+        let mut config = make_config(Path::new("unused"));
+        config.activation_time.server_tick_seconds = 0.0;
+        config
+    }
+
+    fn make_chain(recharge_enhancement: f32) -> AttackChain {
+        AttackChain {
+            archetype: NameKey::new("Blaster"),
+            duration: 10.0,
+            global_recharge: 0.0,
+            powers: vec![ChainPower {
+                name: NameKey::new("Ranged.Fire_Blast.Flares"),
+                recharge_enhancement,
+            }],
+        }
+    }
+
+    fn make_dot(flags: Vec<&'static str>) -> AttribModOutput {
+        AttribModOutput {
+            attributes: vec![Cow::Borrowed("Fire_Dmg")],
+            tick_chance_percent: Some(50.0),
+            continuous_apply_seconds: 1.0,
+            ticks: Some(4),
+            flags,
+            scaled: vec![AttribModScaled {
+                archetype: Some(String::from("Blaster")),
+                scaled_effect: ScaledUnit::Damage(10.0),
+                average: 20.0,
+                per_activation: 0.0,
+                per_cast_cycle: 0.0,
+                display_info: Vec::new(),
+                base_value: 10.0,
+                scale: 1.0,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_waits_for_recharge() {
+        let powers_dict = make_timed_powers_dict(1.0, 4.0);
+        let result =
+            simulate_attack_chain(&make_chain(0.0), &powers_dict, &make_raw_config()).unwrap();
+        // used at 0s and 5s, then recharged again right as the simulation ends
+        assert_eq!(result.powers[0].activations, 2);
+        assert_eq!(result.busy_time, 2.0);
+    }
+
+    #[test]
+    fn test_recharge_strength_is_capped() {
+        let powers_dict = make_timed_powers_dict(1.0, 4.0);
+        // +1000% is capped at +400%, so the power recharges in 0.8s rather than being
+        // ready again as soon as it's done activating
+        let result =
+            simulate_attack_chain(&make_chain(10.0), &powers_dict, &make_raw_config()).unwrap();
+        assert_eq!(result.powers[0].activations, 6);
+        assert_eq!(result.busy_time, 6.0);
+    }
+
+    #[test]
+    fn test_cancel_on_miss_damage_over_time() {
+        let blaster = String::from("Blaster");
+        let mut events = Vec::new();
+        add_attrib_mod_damage_events(
+            &mut events,
+            &make_dot(vec!["CancelOnMiss"]),
+            Some(&blaster),
+            0.5,
+        );
+        // the average already accounts for the ticks being cancelled, so it's split evenly
+        let offsets: Vec<_> = events.iter().map(|e| e.offset).collect();
+        assert_eq!(offsets, vec![0.5, 1.5, 2.5, 3.5]);
+        assert!(events.iter().all(|e| e.amount == 5.0));
+        assert_eq!(events[0].damage_type, "Fire_Dmg");
+
+        // otherwise each tick rolls its own chance
+        let mut events = Vec::new();
+        add_attrib_mod_damage_events(&mut events, &make_dot(Vec::new()), Some(&blaster), 0.0);
+        assert_eq!(events.len(), 4);
+        assert!(events.iter().all(|e| e.amount == 10.0));
+
+        let mut events = Vec::new();
+        let defender = String::from("Defender");
+        add_attrib_mod_damage_events(&mut events, &make_dot(Vec::new()), Some(&defender), 0.0);
+        assert!(events.is_empty());
+    }
+}
@@ -1,7 +1,8 @@
-use super::namekey::NameKey;
+use super::NameKey;
+use serde::Serialize;
 
 /// A list of boosts (enhancements) included in a `BoostSet`.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct BoostList {
     pub ppch_boosts: Vec<NameKey>,
 }
@@ -13,7 +14,7 @@ impl BoostList {
 }
 
 /// Bonus granted by a `BoostSet`.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct BoostSetBonus {
     /// The display name of the bonus.
     pub pch_display_name: Option<String>,
@@ -36,7 +37,7 @@ impl BoostSetBonus {
 }
 
 /// Structure for boost (enhancement) sets.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct BoostSet {
     /// The internal name of the set.
     pub pch_name: Option<NameKey>,
@@ -21,7 +21,7 @@ pub struct AssetsConfig {
 }
 
 /// Configuration information for JSON output style.
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Copy, Debug, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum OutputStyleConfig {
     /// Inserts whitespace so that JSON is human-readable.
@@ -36,6 +36,169 @@ impl Default for OutputStyleConfig {
     }
 }
 
+/// What to do with an existing output path.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputPolicyConfig {
+    /// Replaces the output path with only the files written by this run.
+    Overwrite,
+    /// Refuses to write if the output path already contains anything.
+    FailIfExists,
+    /// Replaces the files written by this run, but keeps any other files already in the output path.
+    Merge,
+}
+
+impl OutputPolicyConfig {
+    /// Gets the name of the policy, as used in the config file and on the command line.
+    pub fn get_string(&self) -> &'static str {
+        match self {
+            OutputPolicyConfig::Overwrite => "overwrite",
+            OutputPolicyConfig::FailIfExists => "fail-if-exists",
+            OutputPolicyConfig::Merge => "merge",
+        }
+    }
+
+    /// Finds the policy named `name`, as used in the config file and on the command line.
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            OutputPolicyConfig::Overwrite,
+            OutputPolicyConfig::FailIfExists,
+            OutputPolicyConfig::Merge,
+        ]
+        .iter()
+        .copied()
+        .find(|policy| policy.get_string() == name)
+    }
+}
+
+impl Default for OutputPolicyConfig {
+    fn default() -> Self {
+        OutputPolicyConfig::Merge
+    }
+}
+
+/// Graph file formats that can be written by the graph exporter.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphFormatConfig {
+    /// Graphviz DOT.
+    Dot,
+    /// GraphML (XML).
+    Graphml,
+}
+
+/// Configuration information for the power relationship graph.
+#[derive(Debug, Deserialize)]
+pub struct GraphConfig {
+    /// Which graph file formats to write.
+    pub formats: Vec<GraphFormatConfig>,
+    /// Optional category, power set, or power name to root the graph at. Only the nodes
+    /// reachable from the root will be written. If omitted, the whole dictionary is written.
+    pub root: Option<NameKey>,
+}
+
+/// Configuration information for the single-file bundle.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct BundleConfig {
+    /// File name for the bundle, written to the output path.
+    pub file: String,
+    /// Whether to gzip the bundle. If set, `.gz` is added to the file name.
+    pub gzip: bool,
+}
+
+impl Default for BundleConfig {
+    fn default() -> Self {
+        BundleConfig {
+            file: String::from("bundle.json"),
+            gzip: false,
+        }
+    }
+}
+
+/// Configuration information for the SQLite database export.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct SqliteConfig {
+    /// File name for the database, written to the output path.
+    pub file: String,
+}
+
+impl Default for SqliteConfig {
+    fn default() -> Self {
+        SqliteConfig {
+            file: String::from("powers.sqlite"),
+        }
+    }
+}
+
+/// Configuration information for the JSON Schema documents.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct SchemaConfig {
+    /// Directory for the schema files, relative to the output path.
+    pub path: String,
+    /// If true, every .json file is checked against its schema before anything is published.
+    pub validate: bool,
+}
+
+impl Default for SchemaConfig {
+    fn default() -> Self {
+        SchemaConfig {
+            path: String::from("schemas"),
+            validate: true,
+        }
+    }
+}
+
+/// Formats for the tabular exports.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TableFormatConfig {
+    /// Comma-separated values, quoted as needed.
+    Csv,
+    /// Tab-separated values.
+    Tsv,
+}
+
+impl Default for TableFormatConfig {
+    fn default() -> Self {
+        TableFormatConfig::Csv
+    }
+}
+
+/// Configuration information for the tabular (spreadsheet) exports.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct TablesConfig {
+    /// Whether to write .csv or .tsv files.
+    pub format: TableFormatConfig,
+    /// Columns to include in the powers table, in order. If empty, all columns are included.
+    pub power_columns: Vec<String>,
+    /// Columns to include in the effects table, in order. If empty, all columns are included.
+    pub effect_columns: Vec<String>,
+}
+
+/// Configuration information for calculating effective activation times.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ActivationTimeConfig {
+    /// Length of a server tick in seconds. Activation times are rounded up to this. Set to 0 to use
+    /// the raw activation times.
+    pub server_tick_seconds: f32,
+    /// Number of extra server ticks added after rounding.
+    pub extra_ticks: i32,
+}
+
+impl Default for ActivationTimeConfig {
+    fn default() -> Self {
+        ActivationTimeConfig {
+            server_tick_seconds: 0.132,
+            extra_ticks: 1,
+        }
+    }
+}
+
 /// Configuration information for the current run.
 #[derive(Debug, Deserialize)]
 pub struct PowersConfig {
@@ -49,6 +212,9 @@ pub struct PowersConfig {
     /// JSON output style.
     #[serde(default)]
     pub output_style: OutputStyleConfig,
+    /// What to do with an existing output path.
+    #[serde(default)]
+    pub output_policy: OutputPolicyConfig,
     /// Determines the security level used for power calculations.
     pub at_level: i32,
     /// Set the base URL for generated JSON assets.
@@ -67,6 +233,19 @@ pub struct PowersConfig {
     /// List of power set partial name matches to filter. Used to get rid of some
     /// power sets we don't want that are part of included power categories.
     pub filter_powersets: Vec<NameKey>,
+    /// Optional. Writes the relationships between powers as a graph.
+    pub graph: Option<GraphConfig>,
+    /// Optional. Writes the whole dictionary as a single bundle file.
+    pub bundle: Option<BundleConfig>,
+    /// Optional. Writes the dictionary as a SQLite database.
+    pub sqlite: Option<SqliteConfig>,
+    /// Optional. Writes the powers and their effects as spreadsheet-friendly tables.
+    pub tables: Option<TablesConfig>,
+    /// Optional. Writes JSON Schema documents and checks the .json files against them.
+    pub schema: Option<SchemaConfig>,
+    /// Optional. Server tick rounding used for effective activation times.
+    #[serde(default)]
+    pub activation_time: ActivationTimeConfig,
 }
 
 impl PowersConfig {
@@ -105,17 +284,4 @@ impl PowersConfig {
     pub fn join_to_input_path(&self, path: &str) -> PathBuf {
         Path::new(&self.input_path).join(path)
     }
-
-    /// Joins a subpath to the `output_path`.
-    ///
-    /// # Arguments:
-    ///
-    /// * `path` - A subpath to append to the `output_path`.
-    ///
-    /// # Returns:
-    ///
-    /// A `PathBuf`.
-    pub fn join_to_output_path(&self, path: &str) -> PathBuf {
-        Path::new(&self.output_path).join(path)
-    }
 }
@@ -2,6 +2,7 @@
 #![allow(non_camel_case_types)]
 use super::{NameKey, VillainDef};
 use num_enum::TryFromPrimitive;
+use serde::Serialize;
 use std::rc::Rc;
 
 macro_rules! default_new {
@@ -30,14 +31,14 @@ macro_rules! default_val {
 const ATTRIBMOD_DURATION_FOREVER: f32 = 99999.0;
 
 /// Which power system to use for advancement, level lookup, etc.
-#[derive(Debug, TryFromPrimitive)]
+#[derive(Debug, TryFromPrimitive, Serialize)]
 #[repr(u32)]
 pub enum PowerSystem {
     kPowerSystem_Powers = 0,
 }
 default_val!(PowerSystem, kPowerSystem_Powers);
 
-#[derive(Debug, TryFromPrimitive)]
+#[derive(Debug, TryFromPrimitive, Serialize)]
 #[repr(u32)]
 pub enum ShowPowerSetting {
     /// If on a powerset that the player owns, do not show this powerset or any powers in it (no matter what settings the powers have).
@@ -59,7 +60,7 @@ pub enum ShowPowerSetting {
 default_val!(ShowPowerSetting, kShowPowerSetting_Never);
 
 /// Defines if the power is auto, toggle, or click power.
-#[derive(Debug, TryFromPrimitive)]
+#[derive(Debug, TryFromPrimitive, Serialize)]
 #[repr(u32)]
 pub enum PowerType {
     /// Click powers only activate when the user has activated them.
@@ -89,7 +90,7 @@ impl PowerType {
 }
 
 // see ESpecialAttrib in Common/entity/character_attribs.h
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub enum SpecialAttrib {
     kSpecialAttrib_Character(i32),
     kSpecialAttrib_Translucency,
@@ -249,7 +250,7 @@ impl SpecialAttrib {
     }
 }
 
-#[derive(Debug, TryFromPrimitive)]
+#[derive(Debug, TryFromPrimitive, Serialize)]
 #[repr(u32)]
 pub enum DeathCastableSetting {
     kDeathCastableSetting_AliveOnly = 0, // old false.
@@ -258,7 +259,7 @@ pub enum DeathCastableSetting {
 }
 default_val!(DeathCastableSetting, kDeathCastableSetting_AliveOnly);
 
-#[derive(Debug, TryFromPrimitive)]
+#[derive(Debug, TryFromPrimitive, Serialize)]
 #[repr(u32)]
 pub enum AIReport {
     /// Report on hit or miss.
@@ -273,7 +274,7 @@ pub enum AIReport {
 default_val!(AIReport, kAIReport_Always);
 
 /// The area effected by the power.
-#[derive(Debug, TryFromPrimitive)]
+#[derive(Debug, TryFromPrimitive, Serialize)]
 #[repr(u32)]
 pub enum EffectArea {
     /// Any targeted entity
@@ -322,7 +323,7 @@ impl EffectArea {
 
 /// Defines what kind of visibility is required between the caster and
 /// the target for successful execution of the power.
-#[derive(Debug, TryFromPrimitive)]
+#[derive(Debug, TryFromPrimitive, Serialize)]
 #[repr(u32)]
 pub enum TargetVisibility {
     /// The caster must have direct line of sight to the target.
@@ -333,7 +334,7 @@ pub enum TargetVisibility {
 default_val!(TargetVisibility, kTargetVisibility_LineOfSight);
 
 /// The thing which can be targetted. Used to specify which kinds of entities are affected, auto-hit, etc. by a power.
-#[derive(Debug, TryFromPrimitive)]
+#[derive(Debug, TryFromPrimitive, Serialize)]
 #[repr(u32)]
 pub enum TargetType {
     kTargetType_None,
@@ -468,7 +469,7 @@ impl TargetType {
     }
 }
 
-#[derive(Debug, TryFromPrimitive)]
+#[derive(Debug, TryFromPrimitive, Serialize)]
 #[repr(u32)]
 pub enum ModApplicationType {
     /// While the power is running.
@@ -499,7 +500,7 @@ impl ModApplicationType {
     }
 }
 
-#[derive(Debug, TryFromPrimitive)]
+#[derive(Debug, TryFromPrimitive, Serialize)]
 #[repr(u32)]
 pub enum ModTarget {
     kModTarget_Caster,
@@ -526,7 +527,7 @@ impl ModTarget {
     }
 }
 
-#[derive(Debug, TryFromPrimitive)]
+#[derive(Debug, TryFromPrimitive, Serialize)]
 #[repr(u32)]
 pub enum ModType {
     kModType_Duration,
@@ -549,7 +550,7 @@ impl ModType {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub enum ModDuration {
     InSeconds(f32),
     kModDuration_Instant,
@@ -600,7 +601,7 @@ impl Default for ModDuration {
     }
 }
 
-#[derive(Debug, TryFromPrimitive)]
+#[derive(Debug, TryFromPrimitive, Serialize)]
 #[repr(u32)]
 pub enum CasterStackType {
     /// Stacking is handled for each caster individually.
@@ -611,7 +612,7 @@ pub enum CasterStackType {
 default_val!(CasterStackType, kCasterStackType_Individual);
 
 /// Determines how multiple identical `AttribMod`s from the same power and caster are handled.
-#[derive(Debug, TryFromPrimitive)]
+#[derive(Debug, TryFromPrimitive, Serialize)]
 #[repr(u32)]
 pub enum StackType {
     /// Stack up (allow multiples).
@@ -654,7 +655,7 @@ impl StackType {
     }
 }
 
-#[derive(Debug, TryFromPrimitive)]
+#[derive(Debug, TryFromPrimitive, Serialize)]
 #[repr(u32)]
 pub enum PowerEvent {
     // Invoke-related events.
@@ -699,20 +700,61 @@ pub enum PowerEvent {
 }
 default_val!(PowerEvent, kPowerEvent_Activate);
 
-#[derive(Debug, Default)]
+impl PowerEvent {
+    pub fn get_string(&self) -> &'static str {
+        match self {
+            PowerEvent::kPowerEvent_Activate => "Activate",
+            PowerEvent::kPowerEvent_ActivateAttackClick => "ActivateAttackClick",
+            PowerEvent::kPowerEvent_Attacked => "Attacked",
+            PowerEvent::kPowerEvent_AttackedNoException => "AttackedNoException",
+            PowerEvent::kPowerEvent_Helped => "Helped",
+            PowerEvent::kPowerEvent_Hit => "Hit",
+            PowerEvent::kPowerEvent_Miss => "Miss",
+            PowerEvent::kPowerEvent_EndActivate => "EndActivate",
+            PowerEvent::kPowerEvent_AttackedByOther => "AttackedByOther",
+            PowerEvent::kPowerEvent_AttackedByOtherClick => "AttackedByOtherClick",
+            PowerEvent::kPowerEvent_HelpedByOther => "HelpedByOther",
+            PowerEvent::kPowerEvent_HitByOther => "HitByOther",
+            PowerEvent::kPowerEvent_HitByFriend => "HitByFriend",
+            PowerEvent::kPowerEvent_HitByFoe => "HitByFoe",
+            PowerEvent::kPowerEvent_MissByOther => "MissByOther",
+            PowerEvent::kPowerEvent_MissByFriend => "MissByFriend",
+            PowerEvent::kPowerEvent_MissByFoe => "MissByFoe",
+            PowerEvent::kPowerEvent_Damaged => "Damaged",
+            PowerEvent::kPowerEvent_Healed => "Healed",
+            PowerEvent::kPowerEvent_Stunned => "Stunned",
+            PowerEvent::kPowerEvent_Immobilized => "Immobilized",
+            PowerEvent::kPowerEvent_Held => "Held",
+            PowerEvent::kPowerEvent_Sleep => "Sleep",
+            PowerEvent::kPowerEvent_Terrorized => "Terrorized",
+            PowerEvent::kPowerEvent_Confused => "Confused",
+            PowerEvent::kPowerEvent_Untouchable => "Untouchable",
+            PowerEvent::kPowerEvent_Intangible => "Intangible",
+            PowerEvent::kPowerEvent_OnlyAffectsSelf => "OnlyAffectsSelf",
+            PowerEvent::kPowerEvent_AnyStatus => "AnyStatus",
+            PowerEvent::kPowerEvent_Knocked => "Knocked",
+            PowerEvent::kPowerEvent_Defeated => "Defeated",
+            PowerEvent::kPowerEvent_MissionObjectClick => "MissionObjectClick",
+            PowerEvent::kPowerEvent_Moved => "Moved",
+            PowerEvent::kPowerEvent_Defiant => "Defiant",
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
 pub struct AttribModParam_Costume {
     pub pch_costume_name: Option<String>,
     pub i_priority: i32,
 }
 default_new!(AttribModParam_Costume);
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct AttribModParam_Reward {
     pub ppch_rewards: Vec<String>,
 }
 default_new!(AttribModParam_Reward);
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct AttribModParam_EntCreate {
     pub pch_entity_def: Option<NameKey>,
     pub pch_class: Option<String>,
@@ -727,15 +769,18 @@ pub struct AttribModParam_EntCreate {
     /// added i26p5
     pub redirects: Vec<NameKey>,
     /// reference to full Villain Def (not inline)
+    #[serde(skip)]
     pub villain_def: Option<Rc<VillainDef>>,
     /// reference to entity's powers (not inline)
+    #[serde(skip)]
     pub power_refs: Vec<NameKey>,
     /// have we resolved this already? (not inline)
+    #[serde(skip)]
     pub resolved: bool,
 }
 default_new!(AttribModParam_EntCreate);
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct AttribModParam_Power {
     pub i_count: i32,
     // flattened from PowerSpec
@@ -743,11 +788,12 @@ pub struct AttribModParam_Power {
     pub ppch_powerset_names: Vec<NameKey>,
     pub ppch_power_names: Vec<NameKey>,
     /// have we resolved this already? not inline
+    #[serde(skip)]
     pub resolved: bool,
 }
 default_new!(AttribModParam_Power);
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct AttribModParam_Phase {
     pub pi_combat_phases: Vec<i32>,
     pub pi_vision_phases: Vec<i32>,
@@ -755,32 +801,32 @@ pub struct AttribModParam_Phase {
 }
 default_new!(AttribModParam_Phase);
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct AttribModParam_Teleport {
     pub pch_destination: Option<String>,
 }
 default_new!(AttribModParam_Teleport);
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct AttribModParam_Behavior {
     pub ppch_behaviors: Vec<String>,
 }
 default_new!(AttribModParam_Behavior);
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct AttribModParam_SZEValue {
     pub ppch_script_id: Vec<String>,
     pub ppch_script_value: Vec<String>,
 }
 default_new!(AttribModParam_SZEValue);
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct AttribModParam_Token {
     pub ppch_tokens: Vec<String>,
 }
 default_new!(AttribModParam_Token);
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct AttribModParam_EffectFilter {
     pub ppch_tags: Vec<String>,
     // flattened from PowerSpec
@@ -791,7 +837,7 @@ pub struct AttribModParam_EffectFilter {
 default_new!(AttribModParam_EffectFilter);
 
 /// Added i26p5. Chain related?
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct AttribModParam_Param11 {
     pub i_unknown_1: i32,
     pub i_unknown_2: i32,
@@ -806,7 +852,7 @@ pub struct AttribModParam_Param11 {
 }
 default_new!(AttribModParam_Param11);
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub enum AttribModParam {
     Costume(AttribModParam_Costume),
     Reward(AttribModParam_Reward),
@@ -821,7 +867,7 @@ pub enum AttribModParam {
     Param11(AttribModParam_Param11),
 }
 
-#[derive(Debug, TryFromPrimitive)]
+#[derive(Debug, TryFromPrimitive, Serialize)]
 #[repr(u32)]
 pub enum ToggleDroppable {
     kToggleDroppable_Sometimes,
@@ -832,7 +878,7 @@ pub enum ToggleDroppable {
 }
 default_val!(ToggleDroppable, kToggleDroppable_Sometimes);
 
-#[derive(Debug, TryFromPrimitive)]
+#[derive(Debug, TryFromPrimitive, Serialize)]
 #[repr(u32)]
 pub enum ProcAllowed {
     kProcAllowed_All,
@@ -842,7 +888,7 @@ pub enum ProcAllowed {
 }
 default_val!(ProcAllowed, kProcAllowed_All);
 
-#[derive(Debug, PartialEq, TryFromPrimitive)]
+#[derive(Debug, PartialEq, TryFromPrimitive, Serialize)]
 #[repr(u32)]
 pub enum AttribType {
     kAttribType_Cur,
@@ -869,7 +915,7 @@ impl AttribType {
     }
 }
 
-#[derive(Debug, TryFromPrimitive)]
+#[derive(Debug, TryFromPrimitive, Serialize)]
 #[repr(u32)]
 pub enum AttribStyle {
     kAttribStyle_None,
@@ -889,7 +935,7 @@ pub enum AttribStyle {
 default_val!(AttribStyle, kAttribStyle_None);
 
 /// Rank of a villain. The "level" here is for conning purposes.
-#[derive(Debug, TryFromPrimitive)]
+#[derive(Debug, TryFromPrimitive, Serialize)]
 #[repr(u32)]
 pub enum VillainRank {
     VR_NONE,
@@ -918,7 +964,27 @@ pub enum VillainRank {
 }
 default_val!(VillainRank, VR_NONE);
 
-#[derive(Debug, TryFromPrimitive)]
+impl VillainRank {
+    /// Get a human readable string representing this `VillainRank`.
+    pub fn get_string(&self) -> &'static str {
+        match self {
+            VillainRank::VR_NONE => "None",
+            VillainRank::VR_SMALL => "Small",
+            VillainRank::VR_MINION => "Minion",
+            VillainRank::VR_LIEUTENANT => "Lieutenant",
+            VillainRank::VR_SNIPER => "Sniper",
+            VillainRank::VR_BOSS => "Boss",
+            VillainRank::VR_ELITE => "Elite Boss",
+            VillainRank::VR_ARCHVILLAIN => "Archvillain",
+            VillainRank::VR_ARCHVILLAIN2 => "Archvillain",
+            VillainRank::VR_BIGMONSTER => "Giant Monster",
+            VillainRank::VR_PET => "Pet",
+            VillainRank::VR_DESTRUCTIBLE => "Destructible",
+        }
+    }
+}
+
+#[derive(Debug, TryFromPrimitive, Serialize)]
 #[repr(u32)]
 pub enum Gender {
     GENDER_UNDEFINED,
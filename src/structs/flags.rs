@@ -1,7 +1,8 @@
 #![allow(non_upper_case_globals)]
+use serde::Serialize;
 
 bitflags! {
-    #[derive(Default)]
+    #[derive(Default, Serialize)]
     pub struct EffectGroupFlag: u32 {
         /// If true, this effect group is ignored while on PVP maps.
         const PVEOnly = 1;
@@ -41,7 +42,7 @@ impl EffectGroupFlag {
 }
 
 bitflags! {
-    #[derive(Default)]
+    #[derive(Default, Serialize)]
     pub struct AttribModFlag: u64 {
         /// If set, hides floaters (damage and healing numbers, for example) over the affected's head.
         /// If specified, `pch_display_float` is always shown, even if this is set.
@@ -188,7 +189,7 @@ impl AttribModFlag {
 }
 
 bitflags! {
-    #[derive(Default)]
+    #[derive(Default, Serialize)]
     pub struct VillainExclusion: u32
     {
         /// Allow in all games.
@@ -203,7 +204,7 @@ bitflags! {
 }
 
 bitflags! {
-    #[derive(Default)]
+    #[derive(Default, Serialize)]
     pub struct VillainDefFlags: u32 {
         /// Don't count a badge stat for the villain group when defeated.
         const VILLAINDEF_NOGROUPBADGESTAT = 1;
@@ -28,15 +28,16 @@ mod boosts;
 pub mod config;
 mod enums;
 mod flags;
-mod namekey;
 mod strings;
 mod villains;
 
 pub use boosts::*;
 pub use enums::*;
 pub use flags::*;
-pub use namekey::*;
-use std::collections::{HashMap, HashSet};
+pub use powers::NameKey;
+use serde::{Serialize, Serializer};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::hash::Hash;
 use std::default::Default;
 use std::fmt;
 use std::rc::Rc;
@@ -45,8 +46,18 @@ pub use villains::*;
 
 pub type Keyed<T> = HashMap<NameKey, Rc<T>>;
 
+/// Serializes a `HashMap` with its keys in order, so the output is the same from run to run.
+fn serialize_sorted<S, K, V>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+where
+	S: Serializer,
+	K: Ord + Hash + Serialize,
+	V: Serialize,
+{
+	map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
 /// Defines the attributes which can be modified by effects.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct CharacterAttributes {
 	/// Mod: The number of points to add or remove from current hit points.
 	/// ModBase: 0.0, Add, TimesMax, Absolute, HitPoints, DumpAttribs: NO_CUR
@@ -410,11 +421,19 @@ impl CharacterAttributes {
 	}
 }
 
+/// Byte offsets to the aspects of an attribute in `Character`, used by
+/// `AttribModTemplate::off_aspect` to pick which `CharacterAttributes` to modify.
+pub const OFFSET_MODIFIERS: u32 = 0;
+pub const OFFSET_MAXIMUM: u32 = 8;
+pub const OFFSET_STRENGTH: u32 = 16;
+pub const OFFSET_RESIST: u32 = 24;
+pub const OFFSET_ABSOLUTE: u32 = 32;
+
 /// Defines the attributes which can be modified by effects.
 /// This is essentially a version of `CharacterAttributes` where each entry is
 /// an array rather than a single value. The arrays are typically 50 entries
 /// long, representing values for levels 1-50.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct CharacterAttributesTable {
 	pub pf_damage_type: [Vec<f32>; CharacterAttributes::DAMAGE_TYPE_SIZE],
 	pub pf_hit_points: Vec<f32>,
@@ -482,7 +501,7 @@ impl CharacterAttributesTable {
 	}
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct NamedTable {
 	pub pch_name: Option<String>,
 	pub pf_values: Vec<f32>,
@@ -496,7 +515,7 @@ impl NamedTable {
 
 /// Defines the character class (archetype), which sets up the allowable powers and
 /// default hit points and defense for the character.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct Archetype {
 	pub pch_name: Option<String>,
 	pub pch_display_name: Option<String>,
@@ -552,6 +571,7 @@ pub struct Archetype {
 	pub pp_attrib_temp_resistance_max: Vec<CharacterAttributesTable>,
 	/// Tables used by powers for scaling powers by level.
 	/// Changed this to a `HashMap` to make it easier to lookup tables.
+	#[serde(serialize_with = "serialize_sorted")]
 	pub pp_named_tables: HashMap<String, NamedTable>,
 	/// Gang together hit points and status points. Modifications to hit points will affect status points and vice-versa. Hit points
 	/// are are set to be the same as status points.
@@ -565,6 +585,7 @@ pub struct Archetype {
 	pub f_defiant_scale: f32,
 	// Non-data fields.
 	/// Used for lookup table purposes.
+	#[serde(skip)]
 	pub class_key: Option<NameKey>,
 }
 
@@ -590,7 +611,7 @@ impl Archetype {
 /// If the same Power appears in more than one PowerSet (and this includes
 /// each class-specific power-pool sets) then it needs to be defined again.
 /// This is true since each BasePower refers to a single PowerSet.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct BasePowerSet {
 	/// Internal name
 	pub pch_name: Option<String>,
@@ -635,6 +656,7 @@ pub struct BasePowerSet {
 	/// Error message to display when the player fails the SetBuyRequires
 	pub pch_set_buy_requires_failed_text: Option<String>,
 	/// The list of powers which are part of this power set.
+	#[serde(skip)]
 	pub pp_powers: Vec<Rc<BasePower>>,
 	/// The array of names of included powers.
 	pub pp_power_names: Vec<NameKey>,
@@ -653,6 +675,7 @@ pub struct BasePowerSet {
 	pub i_force_level_bought: i32,
 	// Non-data fields.
 	/// Whether or not to include this power set in the output files.
+	#[serde(skip)]
 	pub include_in_output: bool,
 }
 
@@ -662,7 +685,7 @@ impl BasePowerSet {
 	}
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct PowerRedirect {
 	/// Name of the base power to redirect to.
 	pub pch_name: Option<NameKey>,
@@ -679,7 +702,7 @@ impl PowerRedirect {
 	}
 }
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, Serialize)]
 pub struct Vec3 {
 	pub x: f32,
 	pub y: f32,
@@ -687,7 +710,7 @@ pub struct Vec3 {
 }
 
 /// Extended targeting info.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct AttribModTargetInfo {
 	pub ppch_marker_names: Vec<String>,
 	pub pi_marker_count: Vec<i32>,
@@ -699,7 +722,7 @@ impl AttribModTargetInfo {
 	}
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct SuppressPair {
 	/// The index of the event to check. (See `PowerEvent` enum in character_base.h)
 	pub idx_event: i32,
@@ -717,7 +740,7 @@ impl SuppressPair {
 }
 
 /// Messages
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct AttribModMessages {
 	/// Message displayed to the attacker when he hits with this power.
 	pub pch_display_attacker_hit: Option<String>,
@@ -736,7 +759,7 @@ impl AttribModMessages {
 }
 
 /// FX
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct AttribModFX {
 	/// Sets the given bits for the lifetime of the `AttribMod`.
 	pub pi_continuing_bits: Vec<i32>,
@@ -754,6 +777,7 @@ impl AttribModFX {
 	}
 }
 
+#[derive(Serialize)]
 pub struct RGBA([u8; 4]);
 
 impl RGBA {
@@ -800,7 +824,7 @@ impl fmt::Debug for RGBA {
 /// This defines an actual effect of a power. A power may have multiple
 /// `AttribModTemplate`s. When a power is used, these `AttribModTemplate`s are
 /// pared down to `AttribMod`s and attached to the targeted character.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct AttribModTemplate {
 	/// Array of byte offsets to the attribute in the `CharacterAttributes` struct.
 	pub p_attrib: Vec<SpecialAttrib>,
@@ -867,7 +891,7 @@ impl AttribModTemplate {
 }
 
 /// An effect group is a group of AttribMod templates that are always applied together.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct EffectGroup {
 	/// Effect tags (for chance mods, etc)
 	pub ppch_tags: Vec<String>,
@@ -902,7 +926,7 @@ impl EffectGroup {
 	}
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct PowerFX {
 	/// What .pfx file this was loaded from.
 	pub pch_source_file: Option<String>,
@@ -1006,7 +1030,7 @@ impl PowerFX {
 	}
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct CustomPowerFX {
 	/// Shown in the customization menu.
 	pub pch_display_name: Option<String>,
@@ -1027,9 +1051,39 @@ impl CustomPowerFX {
 	}
 }
 
+/// Describes how a power is reached from another power.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub enum PowerRelation {
+	/// Granted by a power through an `AttribModParam::Power` parameter.
+	GrantedBy,
+	/// Referenced by a power redirect (or an `EntCreate` redirect).
+	RedirectedFrom,
+	/// Used by an entity created by a power through an `AttribModParam::EntCreate` parameter.
+	SummonedBy,
+}
+
+impl PowerRelation {
+	pub fn get_string(&self) -> &'static str {
+		match self {
+			PowerRelation::GrantedBy => "GrantedBy",
+			PowerRelation::RedirectedFrom => "RedirectedFrom",
+			PowerRelation::SummonedBy => "SummonedBy",
+		}
+	}
+}
+
+/// A reverse reference to the power that links to another power.
+#[derive(Debug, Serialize)]
+pub struct PowerReference {
+	/// How `power_name` links to the power holding this reference.
+	pub relation: PowerRelation,
+	/// Full name of the power that holds the forward link.
+	pub power_name: NameKey,
+}
+
 /// The basic definition of a power. This struct contains all the attributes of a power which are shared by all entities in the game. Character-specific
 /// differences (such as number of boosts, level, etc.) are kept in struct Power.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct BasePower {
 	/// Internal name of the power.
 	pub pch_name: Option<String>,
@@ -1279,6 +1333,8 @@ pub struct BasePower {
 	pub i_max_slot_level: i32,
 	/// For Boosts the use player level, the highest level that will be used to calc boost power
 	pub i_max_boost_level: i32,
+	/// Added i26p5. Not sure what these are, they appear to default to 1.0, 999999.0, and 1.0.
+	pub pf_unknown: [f32; 3],
 	/// List of variables which can be referenced by attrib mods on this power. These variables refer to values stored for each instance
 	/// of the base power (in struct `Power`). The major purpose of these vars is for the Invention system, where they will be used to
 	/// make Boosts.
@@ -1348,23 +1404,99 @@ pub struct BasePower {
 
 	// Non-data fields.
 	/// Whether or not to include this power in the output files.
+	#[serde(skip)]
 	pub include_in_output: bool,
 	/// Archetypes associated with this power.
+	#[serde(skip)]
 	pub archetypes: Vec<Rc<Archetype>>,
 	/// Have we resolved redirects on this power already?
+	#[serde(skip)]
 	pub redirects_resolved: bool,
 	/// Computed set of enhancement sets allowed.
-	pub enhancement_set_categories_allowed: HashSet<String>,
+	#[serde(skip)]
+	pub enhancement_set_categories_allowed: BTreeSet<String>,
+	/// Powers that link to this one (through grants, redirects, or entity creation).
+	#[serde(skip)]
+	pub referenced_by: Vec<PowerReference>,
 }
 
 impl BasePower {
 	pub fn new() -> Self {
 		Default::default()
 	}
+
+	/// Gets the area factor used to reduce the chance of procs-per-minute effects in area powers.
+	/// Single target powers have a factor of 1.
+	pub fn area_factor(&self) -> f32 {
+		match self.e_effect_area {
+			EffectArea::kEffectArea_Sphere => 1.0 + self.f_radius * 0.15,
+			EffectArea::kEffectArea_Cone => {
+				1.0 + self.f_radius * 0.15
+					- self.f_radius * 0.000366667 * (360.0 - self.f_arc.to_degrees())
+			}
+			_ => 1.0,
+		}
+	}
+
+	/// Calculates the chance that an effect group using procs-per-minute will trigger on
+	/// each activation of this power.
+	///
+	/// # Arguments:
+	///
+	/// * `procs_per_minute` - The PPM rate of the effect group.
+	/// * `recharge_time` - The recharge time of the power in seconds. Pass `f_recharge_time` for the
+	/// unenhanced chance, or the recharge after enhancements to recompute it. (Global recharge
+	/// bonuses do not count.)
+	///
+	/// # Returns:
+	///
+	/// A chance from 0 to 1, clamped to `ppm_chance_limits`.
+	pub fn proc_chance(&self, procs_per_minute: f32, recharge_time: f32) -> f32 {
+		// toggles and autos check for a proc every 10 seconds instead of per activation
+		const PERIODIC_CHECK_TIME: f32 = 10.0;
+		let time = match self.e_type {
+			PowerType::kPowerType_Toggle | PowerType::kPowerType_Auto => PERIODIC_CHECK_TIME,
+			_ => recharge_time + self.f_time_to_activate,
+		};
+		let chance = procs_per_minute * time / (60.0 * self.area_factor());
+		let (min_chance, max_chance) = BasePower::ppm_chance_limits(procs_per_minute);
+		chance.max(min_chance).min(max_chance)
+	}
+
+	/// Calculates the time it actually takes to activate this power on the server (a.k.a. "arcanatime").
+	/// The server only processes activations on ticks, so the raw activation time is rounded up to the
+	/// next tick plus `extra_ticks`.
+	///
+	/// # Arguments:
+	///
+	/// * `config` - Tick length and extra ticks to use.
+	///
+	/// # Returns:
+	///
+	/// The effective activation time in seconds, or 0 if the power has no activation time.
+	pub fn effective_time_to_activate(&self, config: &config::ActivationTimeConfig) -> f32 {
+		// Activation times that are a whole number of ticks don't divide exactly in f32
+		// (7.26 / 0.132 is 55.000004), so allow a little slack before rounding up.
+		const TICK_EPSILON: f32 = 0.001;
+		if !self.f_time_to_activate.is_normal() {
+			0.0
+		} else if config.server_tick_seconds.is_normal() {
+			let tick = config.server_tick_seconds;
+			let ticks = (self.f_time_to_activate / tick - TICK_EPSILON).ceil();
+			(ticks + config.extra_ticks as f32) * tick
+		} else {
+			self.f_time_to_activate
+		}
+	}
+
+	/// Gets the minimum and maximum chance (from 0 to 1) of an effect group using `procs_per_minute`.
+	pub fn ppm_chance_limits(procs_per_minute: f32) -> (f32, f32) {
+		(0.05 + 0.015 * procs_per_minute, 0.9)
+	}
 }
 
 /// Describes a power category as containing either primary or secondary sets.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize)]
 pub enum PrimarySecondary {
 	Primary,
 	Secondary,
@@ -1377,7 +1509,7 @@ impl Default for PrimarySecondary {
 	}
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct PowerCategory {
 	/// Filename this definition came from.
 	pub pch_source_file: Option<String>,
@@ -1392,17 +1524,22 @@ pub struct PowerCategory {
 	/// The names of power sets in this category.
 	pub ppch_power_set_names: Vec<NameKey>,
 	/// List of power sets which make up this category.
+	#[serde(skip)]
 	pub pp_power_sets: Vec<Rc<BasePowerSet>>,
 	/// Archetypes associated with this category.
+	#[serde(skip)]
 	pub archetypes: Vec<Rc<Archetype>>,
 	/// For power categories tied to a specific archetype, this indicates whether it
 	/// is a primary or secondary power pick.
+	#[serde(skip)]
 	pub pri_sec: PrimarySecondary,
 
 	// Non-data fields.
 	/// Whether or not to include this power category in the output files.
+	#[serde(skip)]
 	pub include_in_output: bool,
 	/// If true, this category should be listed in the root JSON.
+	#[serde(skip)]
 	pub top_level: bool,
 }
 
@@ -1412,7 +1549,7 @@ impl PowerCategory {
 	}
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct AttribName {
 	pub pch_name: Option<String>,
 	pub pch_display_name: Option<String>,
@@ -1425,7 +1562,7 @@ impl AttribName {
 	}
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct AttribNames {
 	pub pp_defense: Vec<AttribName>,
 	pub pp_damage: Vec<AttribName>,
@@ -1435,6 +1572,7 @@ pub struct AttribNames {
 	pub pp_elusivity: Vec<AttribName>,
 	pub pp_stack_key: Vec<AttribName>,
 	/// Not in the original struct but gives us a convenient place to hold onto them.
+	#[serde(serialize_with = "serialize_sorted")]
 	pub attr_names: HashMap<usize, Option<String>>,
 }
 
@@ -1451,6 +1589,141 @@ pub struct PowersDictionary {
 	pub power_categories: Vec<Rc<PowerCategory>>,
 	/// All of the archetype data.
 	pub archetypes: Keyed<Archetype>,
+	/// All of the boost (enhancement) sets.
+	pub boost_sets: Keyed<BoostSet>,
 	/// Character attribute names, mostly used for naming damage, defense, elusivity.
 	pub attrib_names: AttribNames,
 }
+
+impl PowersDictionary {
+	/// Finds a power by its full name (category, power set, and power).
+	///
+	/// # Arguments:
+	///
+	/// * `power_name` - The full name of the power.
+	///
+	/// # Returns:
+	///
+	/// The power, if found. Otherwise `None`.
+	pub fn find_power(&self, power_name: &NameKey) -> Option<&Rc<BasePower>> {
+		self.power_categories
+			.iter()
+			.flat_map(|pcat| pcat.pp_power_sets.iter())
+			.flat_map(|pset| pset.pp_powers.iter())
+			.find(|power| power.pch_full_name.as_ref() == Some(power_name))
+	}
+
+	/// Finds an archetype by its internal name (e.g. `Class_Blaster`) or display name (e.g. `Blaster`).
+	pub fn find_archetype(&self, name: &NameKey) -> Option<&Rc<Archetype>> {
+		self.archetypes.get(name).or_else(|| {
+			self.archetypes.values().find(|at| {
+				matches!(&at.pch_display_name, Some(display_name) if name == &display_name[..])
+			})
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A power that takes 1 second to activate and recharges in 4.
+	fn make_power(e_type: PowerType) -> BasePower {
+		let mut power = BasePower::new();
+		power.e_type = e_type;
+		power.f_time_to_activate = 1.0;
+		power.f_recharge_time = 4.0;
+		power
+	}
+
+	fn assert_close(actual: f32, expected: f32) {
+		assert!(
+			(actual - expected).abs() < 0.0001,
+			"expected {}, got {}",
+			expected,
+			actual
+		);
+	}
+
+	#[test]
+	fn test_area_factor() {
+		let mut power = make_power(PowerType::kPowerType_Click);
+		power.f_radius = 15.0;
+		assert_eq!(power.area_factor(), 1.0);
+
+		power.e_effect_area = EffectArea::kEffectArea_Sphere;
+		assert_close(power.area_factor(), 3.25);
+
+		// the arc is stored in radians, but the formula uses degrees
+		power.e_effect_area = EffectArea::kEffectArea_Cone;
+		power.f_radius = 40.0;
+		power.f_arc = 90f32.to_radians();
+		assert_close(power.area_factor(), 1.0 + 6.0 - 40.0 * 0.000366667 * 270.0);
+		power.f_arc = 360f32.to_radians();
+		assert_close(power.area_factor(), 7.0);
+	}
+
+	#[test]
+	fn test_proc_chance() {
+		let mut power = make_power(PowerType::kPowerType_Click);
+		assert_close(power.proc_chance(3.5, power.f_recharge_time), 3.5 * 5.0 / 60.0);
+		// recomputed for the enhanced recharge
+		assert_close(power.proc_chance(3.5, 2.0), 3.5 * 3.0 / 60.0);
+
+		power.e_effect_area = EffectArea::kEffectArea_Sphere;
+		power.f_radius = 15.0;
+		assert_close(power.proc_chance(3.5, 20.0), 3.5 * 21.0 / (60.0 * 3.25));
+
+		// toggles and autos use a 10 second period, no matter how long they take
+		for e_type in vec![PowerType::kPowerType_Toggle, PowerType::kPowerType_Auto] {
+			let power = make_power(e_type);
+			assert_close(power.proc_chance(3.0, power.f_recharge_time), 0.5);
+			assert_close(power.proc_chance(3.0, 100.0), 0.5);
+		}
+	}
+
+	#[test]
+	fn test_proc_chance_limits() {
+		let (min_chance, max_chance) = BasePower::ppm_chance_limits(2.0);
+		assert_close(min_chance, 0.08);
+		assert_close(max_chance, 0.9);
+
+		let power = make_power(PowerType::kPowerType_Click);
+		assert_close(power.proc_chance(2.0, 0.0), 0.08);
+		assert_close(power.proc_chance(3.5, 60.0), 0.9);
+	}
+
+	#[test]
+	fn test_effective_time_to_activate() {
+		let mut config = config::ActivationTimeConfig {
+			server_tick_seconds: 0.132,
+			extra_ticks: 0,
+		};
+		let mut power = make_power(PowerType::kPowerType_Click);
+		// an exact number of ticks isn't rounded up again
+		power.f_time_to_activate = 7.26;
+		assert_close(power.effective_time_to_activate(&config), 7.26);
+		power.f_time_to_activate = 1.056;
+		assert_close(power.effective_time_to_activate(&config), 1.056);
+		// anything else goes up to the next tick
+		power.f_time_to_activate = 1.0;
+		assert_close(power.effective_time_to_activate(&config), 8.0 * 0.132);
+		power.f_time_to_activate = 1.17;
+		assert_close(power.effective_time_to_activate(&config), 9.0 * 0.132);
+
+		config.extra_ticks = 1;
+		power.f_time_to_activate = 7.26;
+		assert_close(power.effective_time_to_activate(&config), 56.0 * 0.132);
+		power.f_time_to_activate = 1.0;
+		assert_close(power.effective_time_to_activate(&config), 9.0 * 0.132);
+
+		// no activation time stays at 0, even with extra ticks
+		power.f_time_to_activate = 0.0;
+		assert_eq!(power.effective_time_to_activate(&config), 0.0);
+
+		// a tick length of 0 uses the raw time
+		config.server_tick_seconds = 0.0;
+		power.f_time_to_activate = 1.0;
+		assert_eq!(power.effective_time_to_activate(&config), 1.0);
+	}
+}
@@ -1,9 +1,10 @@
+use serde::Serialize;
 use std::collections::HashMap;
 use std::str;
 
 /// Describes an individual entry in a `MessageStore`. When keyed to a descriptive string, this
 /// can be used to map that key to readable text.
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct TextMessage {
 	/// The index into `MessageStore.messages` the message.
 	message_index: usize,
@@ -25,13 +26,14 @@ impl TextMessage {
 }
 
 /// Represents a .bin store of client messages, used for mapping P-strings into readable text.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct MessageStore {
 	/// The table of message strings.
 	pub messages: Vec<String>,
 	/// The table of variables and variable types.
 	pub variables: Vec<String>,
 	/// The map of message IDs to message strings.
+	#[serde(serialize_with = "super::serialize_sorted")]
 	pub message_ids: HashMap<String, TextMessage>,
 }
 
@@ -1,7 +1,8 @@
 use super::*;
+use serde::Serialize;
 
 /// We use this structure to load references to powers from data files.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct PowerNameRef {
     pub power_category: Option<NameKey>,
     pub power_set: Option<NameKey>,
@@ -22,7 +23,7 @@ impl PowerNameRef {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct VillainLevelDef {
     /// What is the villain level is this definition for?
     pub level: i32,
@@ -40,7 +41,7 @@ impl VillainLevelDef {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct PetCommandStrings {
     pub ppch_passive: Vec<String>,
     pub ppch_defensive: Vec<String>,
@@ -63,7 +64,7 @@ impl PetCommandStrings {
 
 /// Defines different villain (NPC) templates. This is used to look up the definition
 /// of entities (such as pets and pseudopets) created by powers.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct VillainDef {
     /// Internal name.  NPCs should be referenced by this name.
     pub name: Option<NameKey>,
@@ -0,0 +1,272 @@
+use crate::dump;
+use crate::load::{self, RawBins};
+use crate::structs::Keyed;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, ErrorKind};
+
+/// Fields that hold data the parsers don't understand yet, by .bin file and path inside a record.
+/// Nested records (e.g. effect groups inside effect groups) are matched at any depth, so a path
+/// matches the end of the full path to the field.
+///
+/// * `f_unknown` - Power float between `f_arc` and `f_chain_delay`.
+/// * `ppch_unknown` - Power string array next to `pp_redirect`.
+/// * `pi_unknown` - Power integer array added in i26p5, maybe related to chain powers.
+/// * `pf_unknown` - Three power floats added in i26p5, before `pp_vars`.
+/// * `p_params.Param11.*` - Every field of the attribute modifier parameters added in i26p5.
+const UNKNOWN_FIELDS: &'static [(&'static str, &'static str)] = &[
+    (load::POWERS_BIN, "f_unknown"),
+    (load::POWERS_BIN, "ppch_unknown"),
+    (load::POWERS_BIN, "pi_unknown"),
+    (load::POWERS_BIN, "pf_unknown"),
+    (load::POWERS_BIN, "p_params.Param11.i_unknown_1"),
+    (load::POWERS_BIN, "p_params.Param11.i_unknown_2"),
+    (load::POWERS_BIN, "p_params.Param11.i_unknown_3"),
+    (load::POWERS_BIN, "p_params.Param11.f_unknown_4"),
+    (load::POWERS_BIN, "p_params.Param11.i_unknown_5"),
+    (load::POWERS_BIN, "p_params.Param11.i_unknown_6"),
+    (load::POWERS_BIN, "p_params.Param11.f_unknown_7"),
+    (load::POWERS_BIN, "p_params.Param11.f_unknown_8"),
+    (load::POWERS_BIN, "p_params.Param11.f_unknown_9"),
+    (load::POWERS_BIN, "p_params.Param11.f_unknown_10"),
+];
+
+/// Number of distinct values to show for each field.
+const MAX_VALUES_SHOWN: usize = 10;
+
+/// Number of example records to keep for each distinct value.
+const MAX_EXAMPLES: usize = 3;
+
+/// How often a single value appears in an unknown field.
+#[derive(Debug, Default)]
+struct ValueStats {
+    count: usize,
+    /// Names of some of the records with this value.
+    examples: Vec<String>,
+}
+
+/// The values found in an unknown field across all records.
+#[derive(Debug, Default)]
+struct FieldStats {
+    /// Number of times the field was seen.
+    count: usize,
+    /// Number of times the field had something other than a default value.
+    non_default: usize,
+    /// Distinct values, as compact JSON.
+    values: BTreeMap<String, ValueStats>,
+}
+
+/// Value distributions for every unknown field in the bins.
+#[derive(Debug, Default)]
+pub struct UnknownFieldsReport {
+    /// Keyed by the name of the .bin file and the path to the field inside a record.
+    fields: BTreeMap<(&'static str, String), FieldStats>,
+}
+
+impl UnknownFieldsReport {
+    /// Collects the values of the unknown fields in every record of the .bin files.
+    ///
+    /// # Arguments:
+    ///
+    /// * `bins` - The parsed .bin files.
+    ///
+    /// # Returns:
+    ///
+    /// An `UnknownFieldsReport` if successful. Otherwise, an `io::Error` containing the error information.
+    pub fn from_raw_bins(bins: &RawBins) -> io::Result<Self> {
+        let mut report = UnknownFieldsReport::default();
+        report.add_bin(load::CLASSES_BIN, &bins.archetypes)?;
+        report.add_bin(load::BOOST_SETS_BIN, &bins.boost_sets)?;
+        report.add_bin(load::VILLAIN_CLASSES_BIN, &bins.villain_archetypes)?;
+        report.add_bin(load::VILLAIN_DEF_BIN, &bins.villains)?;
+        report.add_bin(load::POWER_CATEGORIES_BIN, &bins.power_categories)?;
+        report.add_bin(load::POWER_SETS_BIN, &bins.power_sets)?;
+        report.add_bin(load::POWERS_BIN, &bins.powers)?;
+        Ok(report)
+    }
+
+    /// Adds the unknown fields of each record in `keyed`.
+    fn add_bin<T: Serialize>(
+        &mut self,
+        bin_name: &'static str,
+        keyed: &Keyed<T>,
+    ) -> io::Result<()> {
+        for (name, record) in dump::sorted(keyed) {
+            let value =
+                serde_json::to_value(record).map_err(|e| io::Error::new(ErrorKind::Other, e))?;
+            self.add_value(bin_name, name, "", &value);
+        }
+        Ok(())
+    }
+
+    /// Looks through `value` for unknown fields. `path` is where `value` is inside the record.
+    fn add_value(&mut self, bin_name: &'static str, record_name: &str, path: &str, value: &Value) {
+        match value {
+            Value::Object(map) => {
+                for (key, field) in map {
+                    let field_path = if path.is_empty() {
+                        key.clone()
+                    } else {
+                        format!("{}.{}", path, key)
+                    };
+                    if is_unknown_field(bin_name, &field_path) {
+                        self.add_field(bin_name, record_name, field_path, field);
+                    } else {
+                        self.add_value(bin_name, record_name, &field_path, field);
+                    }
+                }
+            }
+            Value::Array(elements) => {
+                for element in elements {
+                    self.add_value(bin_name, record_name, path, element);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Counts one value of an unknown field.
+    fn add_field(
+        &mut self,
+        bin_name: &'static str,
+        record_name: &str,
+        path: String,
+        value: &Value,
+    ) {
+        let field = self.fields.entry((bin_name, path)).or_default();
+        field.count += 1;
+        if !is_default(value) {
+            field.non_default += 1;
+        }
+        let stats = field.values.entry(value.to_string()).or_default();
+        stats.count += 1;
+        if stats.examples.len() < MAX_EXAMPLES
+            && stats.examples.last().map(|e| e.as_str()) != Some(record_name)
+        {
+            stats.examples.push(record_name.to_owned());
+        }
+    }
+
+    /// Gets the number of unknown fields that have something other than default values.
+    pub fn non_default_count(&self) -> usize {
+        self.fields.values().filter(|f| f.non_default > 0).count()
+    }
+}
+
+impl fmt::Display for UnknownFieldsReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} unknown fields, {} with non-default values.",
+            self.fields.len(),
+            self.non_default_count()
+        )?;
+        for ((bin_name, path), field) in &self.fields {
+            writeln!(f)?;
+            writeln!(
+                f,
+                "{}{} {}: {} values, {} distinct, {} non-default",
+                if field.non_default > 0 { "! " } else { "" },
+                bin_name,
+                path,
+                field.count,
+                field.values.len(),
+                field.non_default
+            )?;
+            let mut values: Vec<_> = field.values.iter().collect();
+            values.sort_by(|(_, a), (_, b)| b.count.cmp(&a.count));
+            for (value, stats) in values.iter().take(MAX_VALUES_SHOWN) {
+                writeln!(
+                    f,
+                    "    {} x{} (e.g. {})",
+                    value,
+                    stats.count,
+                    stats.examples.join(", ")
+                )?;
+            }
+            if values.len() > MAX_VALUES_SHOWN {
+                writeln!(f, "    ... and {} more", values.len() - MAX_VALUES_SHOWN)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Tests if the field at `path` in a record of `bin_name` is listed in `UNKNOWN_FIELDS`.
+fn is_unknown_field(bin_name: &str, path: &str) -> bool {
+    UNKNOWN_FIELDS.iter().any(|(bin, field)| {
+        *bin == bin_name
+            && path.ends_with(field)
+            && (path.len() == field.len() || path[..path.len() - field.len()].ends_with('.'))
+    })
+}
+
+/// Tests if `value` is what an unpopulated field would contain.
+fn is_default(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Bool(b) => !b,
+        Value::Number(n) => n.as_f64() == Some(0.0),
+        Value::String(s) => s.is_empty(),
+        Value::Array(elements) => elements.iter().all(is_default),
+        Value::Object(map) => map.values().all(is_default),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_unknown_fields_report() {
+        let mut powers = Keyed::new();
+        for (name, f_unknown) in &[("Pool.Flight.Hover", 0.0), ("Pool.Flight.Fly", 2.5)] {
+            let mut power = BasePower::new();
+            power.f_unknown = *f_unknown;
+            power.ppch_unknown = vec![String::from("x")];
+            powers.insert(NameKey::new(*name), Rc::new(power));
+        }
+        // unknown parameters in a nested effect group
+        let mut param11 = AttribModParam_Param11::new();
+        param11.i_unknown_1 = 3;
+        let mut template = AttribModTemplate::new();
+        template.p_params = Some(AttribModParam::Param11(param11));
+        let mut child = EffectGroup::new();
+        child.pp_templates.push(template);
+        let mut effect_group = EffectGroup::new();
+        effect_group.pp_effects.push(child);
+        let mut power = BasePower::new();
+        power.pp_effects.push(Rc::new(effect_group));
+        powers.insert(NameKey::new("Pool.Flight.Afterburner"), Rc::new(power));
+        let bins = RawBins {
+            powers,
+            ..Default::default()
+        };
+        let report = UnknownFieldsReport::from_raw_bins(&bins).unwrap();
+        let f_unknown = &report.fields[&(load::POWERS_BIN, String::from("f_unknown"))];
+        assert_eq!(f_unknown.count, 3);
+        assert_eq!(f_unknown.non_default, 1);
+        assert_eq!(f_unknown.values["2.5"].examples, vec!["Pool.Flight.Fly"]);
+        let param = &report.fields[&(
+            load::POWERS_BIN,
+            String::from("pp_effects.pp_effects.pp_templates.p_params.Param11.i_unknown_1"),
+        )];
+        assert_eq!(param.non_default, 1);
+        assert!(report
+            .fields
+            .contains_key(&(load::POWERS_BIN, String::from("pf_unknown"))));
+        // only the listed fields are collected
+        assert!(!report
+            .fields
+            .keys()
+            .any(|(_, path)| path.ends_with("f_chain_delay")));
+        assert_eq!(report.non_default_count(), 3);
+        assert!(report
+            .to_string()
+            .contains("! powers.bin ppch_unknown: 3 values, 2 distinct, 2 non-default"));
+    }
+}